@@ -0,0 +1,402 @@
+//! Parse, traverse, mutate and stringify every corpus in `assets/` with
+//! every backend.
+//!
+//! Timings and throughput come from criterion, so the usual flags apply:
+//! `cargo bench --bench backends -- --save-baseline main` records a
+//! baseline and `-- --baseline main` compares against it. Before timing, the
+//! peak heap usage of one parse per backend and corpus is measured through a
+//! counting wrapper around mimalloc, printed next to the previous run's
+//! figures and saved to `target/criterion/peak_memory.tsv`.
+
+use std::alloc::{GlobalAlloc, Layout};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::hint::black_box;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput};
+use mimalloc_rust::GlobalMiMalloc;
+use rowan::{GreenNode, GreenToken, Language, NodeOrToken};
+use rowan_json::{
+    chumsky as chumsky_backend, document::Document, lexer::SyntaxKind, nom as nom_backend,
+    parallel, parser::Parser, recursive, syntax::Json, tape::Tape,
+};
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// Forwards to mimalloc while tracking the live and peak heap size.
+struct Counting;
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = GlobalMiMalloc.alloc(layout);
+        if !ptr.is_null() {
+            let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(current, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+        GlobalMiMalloc.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = GlobalMiMalloc.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+            let current = CURRENT.fetch_add(new_size, Ordering::Relaxed) + new_size;
+            PEAK.fetch_max(current, Ordering::Relaxed);
+        }
+        new
+    }
+}
+
+/// Returns how many bytes above the current heap size `f` peaked at,
+/// including whatever it returns.
+fn peak_memory<T>(f: impl FnOnce() -> T) -> usize {
+    let base = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(base, Ordering::Relaxed);
+    let output = f();
+    let peak = PEAK.load(Ordering::Relaxed);
+    drop(output);
+    peak - base
+}
+
+/// One parser backend. Operations a backend does not support return `None`
+/// and are left out of the report.
+trait Backend {
+    const NAME: &'static str;
+    type Output<'a>;
+
+    fn parse(input: &str) -> Self::Output<'_>;
+
+    /// Visits every value, returning how many there were.
+    fn traverse(_: &Self::Output<'_>) -> Option<usize> {
+        None
+    }
+
+    /// Replaces every number with zero.
+    fn mutate(_: &mut Self::Output<'_>) -> Option<()> {
+        None
+    }
+
+    fn stringify(_: &Self::Output<'_>) -> Option<String> {
+        None
+    }
+}
+
+struct Rowan;
+
+impl Backend for Rowan {
+    const NAME: &'static str = "rowan";
+    type Output<'a> = GreenNode;
+
+    fn parse(input: &str) -> GreenNode {
+        Parser::new(input).parse().green_node
+    }
+
+    fn traverse(green: &GreenNode) -> Option<usize> {
+        let root = rowan_json::syntax::SyntaxNode::new_root(green.clone());
+        Some(root.preorder_with_tokens().count())
+    }
+
+    fn mutate(green: &mut GreenNode) -> Option<()> {
+        fn zero_numbers(node: &rowan::GreenNodeData) -> GreenNode {
+            let children = node.children().map(|child| match child {
+                NodeOrToken::Node(node) => NodeOrToken::Node(zero_numbers(node)),
+                NodeOrToken::Token(token)
+                    if token.kind() == Json::kind_to_raw(SyntaxKind::Number) =>
+                {
+                    NodeOrToken::Token(GreenToken::new(token.kind(), "0"))
+                }
+                NodeOrToken::Token(token) => NodeOrToken::Token(token.to_owned()),
+            });
+            GreenNode::new(node.kind(), children)
+        }
+        *green = zero_numbers(green);
+        Some(())
+    }
+
+    fn stringify(green: &GreenNode) -> Option<String> {
+        Some(green.to_string())
+    }
+}
+
+struct RowanParallel;
+
+impl Backend for RowanParallel {
+    const NAME: &'static str = "rowan-parallel";
+    type Output<'a> = GreenNode;
+
+    fn parse(input: &str) -> GreenNode {
+        parallel::parse(input).green_node
+    }
+}
+
+struct Recursive;
+
+impl Backend for Recursive {
+    const NAME: &'static str = "recursive";
+    type Output<'a> = recursive::Value<'a>;
+
+    fn parse(input: &str) -> recursive::Value<'_> {
+        recursive::Parser::new(input).parse().unwrap()
+    }
+
+    fn traverse(value: &recursive::Value<'_>) -> Option<usize> {
+        fn count(value: &recursive::Value<'_>) -> usize {
+            1 + match value {
+                recursive::Value::Object(members) => members.iter().map(|(_, v)| count(v)).sum(),
+                recursive::Value::Array(elements) => elements.iter().map(count).sum(),
+                _ => 0,
+            }
+        }
+        Some(count(value))
+    }
+
+    fn mutate(value: &mut recursive::Value<'_>) -> Option<()> {
+        match value {
+            recursive::Value::Number(number) => *number = 0.0,
+            recursive::Value::Object(members) => members.iter_mut().for_each(|(_, v)| {
+                Self::mutate(v);
+            }),
+            recursive::Value::Array(elements) => elements.iter_mut().for_each(|v| {
+                Self::mutate(v);
+            }),
+            _ => {}
+        }
+        Some(())
+    }
+
+    fn stringify(value: &recursive::Value<'_>) -> Option<String> {
+        Some(value.to_string())
+    }
+}
+
+struct Chumsky;
+
+impl Backend for Chumsky {
+    const NAME: &'static str = "chumsky";
+    type Output<'a> = Option<chumsky_backend::Spanned<chumsky_backend::Json>>;
+
+    fn parse(input: &str) -> Option<chumsky_backend::Spanned<chumsky_backend::Json>> {
+        chumsky_backend::parse(input).0
+    }
+
+    fn traverse(value: &Option<chumsky_backend::Spanned<chumsky_backend::Json>>) -> Option<usize> {
+        fn count(value: &chumsky_backend::Spanned<chumsky_backend::Json>) -> usize {
+            1 + match &value.node {
+                chumsky_backend::Json::Object(members) => {
+                    members.iter().map(|(_, v)| count(v)).sum()
+                }
+                chumsky_backend::Json::Array(elements) => elements.iter().map(count).sum(),
+                _ => 0,
+            }
+        }
+        value.as_ref().map(count)
+    }
+}
+
+struct Nom;
+
+impl Backend for Nom {
+    const NAME: &'static str = "nom";
+    type Output<'a> = nom_backend::JsonValue;
+
+    fn parse(input: &str) -> nom_backend::JsonValue {
+        nom_backend::json(input).unwrap().1
+    }
+
+    fn traverse(value: &nom_backend::JsonValue) -> Option<usize> {
+        fn count(value: &nom_backend::JsonValue) -> usize {
+            1 + match value {
+                nom_backend::JsonValue::Object(members) => {
+                    members.iter().map(|(_, v)| count(v)).sum()
+                }
+                nom_backend::JsonValue::Array(elements) => elements.iter().map(count).sum(),
+                _ => 0,
+            }
+        }
+        Some(count(value))
+    }
+}
+
+struct JsonPop;
+
+impl Backend for JsonPop {
+    const NAME: &'static str = "json-pop";
+    type Output<'a> = json_pop::value::Value<'a>;
+
+    fn parse(input: &str) -> json_pop::value::Value<'_> {
+        json_pop::parse_str(input).unwrap()
+    }
+
+    fn traverse(value: &json_pop::value::Value<'_>) -> Option<usize> {
+        use json_pop::value::Value;
+        fn count(value: &Value<'_>) -> usize {
+            1 + match value {
+                Value::Object(members) => members.iter().map(|(_, v)| count(v)).sum(),
+                Value::Array(elements) => elements.iter().map(count).sum(),
+                _ => 0,
+            }
+        }
+        Some(count(value))
+    }
+
+    fn mutate(value: &mut json_pop::value::Value<'_>) -> Option<()> {
+        use json_pop::value::Value;
+        match value {
+            Value::Number(number) => *number = 0.0,
+            Value::Object(members) => members.iter_mut().for_each(|(_, v)| {
+                Self::mutate(v);
+            }),
+            Value::Array(elements) => elements.iter_mut().for_each(|v| {
+                Self::mutate(v);
+            }),
+            _ => {}
+        }
+        Some(())
+    }
+
+    fn stringify(value: &json_pop::value::Value<'_>) -> Option<String> {
+        Some(value.to_string())
+    }
+}
+
+struct TapeBackend;
+
+impl Backend for TapeBackend {
+    const NAME: &'static str = "tape";
+    type Output<'a> = Tape<'a>;
+
+    fn parse(input: &str) -> Tape<'_> {
+        Tape::parse(input).unwrap()
+    }
+
+    fn traverse(tape: &Tape<'_>) -> Option<usize> {
+        fn count(node: rowan_json::tape::Node<'_, '_>) -> usize {
+            1 + node.iter_members().map(|(_, v)| count(v)).sum::<usize>()
+                + node.iter_elements().map(count).sum::<usize>()
+        }
+        Some(count(tape.root()))
+    }
+}
+
+struct OnDemand;
+
+impl Backend for OnDemand {
+    const NAME: &'static str = "on-demand";
+    type Output<'a> = Document<'a>;
+
+    fn parse(input: &str) -> Document<'_> {
+        Document::parse(input).unwrap()
+    }
+}
+
+struct Corpus {
+    name: String,
+    text: String,
+}
+
+fn corpora() -> Vec<Corpus> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+    let mut corpora: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .map(|path| Corpus {
+            name: path.file_name().unwrap().to_string_lossy().into_owned(),
+            text: fs::read_to_string(&path).unwrap(),
+        })
+        .collect();
+    corpora.sort_by(|a, b| a.name.cmp(&b.name));
+    corpora
+}
+
+fn bench<B: Backend>(c: &mut Criterion, corpus: &Corpus, memory: &mut Vec<(String, usize)>) {
+    let input = corpus.text.as_str();
+    memory.push((
+        format!("{}\t{}", corpus.name, B::NAME),
+        peak_memory(|| B::parse(input)),
+    ));
+
+    let mut group = c.benchmark_group(corpus.name.as_str());
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function(BenchmarkId::new("parse", B::NAME), |b| {
+        b.iter(|| B::parse(black_box(input)))
+    });
+    let parsed = B::parse(input);
+    if B::traverse(&parsed).is_some() {
+        group.bench_function(BenchmarkId::new("traverse", B::NAME), |b| {
+            b.iter(|| B::traverse(black_box(&parsed)))
+        });
+    }
+    if B::mutate(&mut B::parse(input)).is_some() {
+        group.bench_function(BenchmarkId::new("mutate", B::NAME), |b| {
+            b.iter_batched_ref(|| B::parse(input), |v| B::mutate(v), BatchSize::LargeInput)
+        });
+    }
+    if B::stringify(&parsed).is_some() {
+        group.bench_function(BenchmarkId::new("stringify", B::NAME), |b| {
+            b.iter(|| B::stringify(black_box(&parsed)))
+        });
+    }
+    group.finish();
+}
+
+fn report_memory(memory: &[(String, usize)]) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("target/criterion/peak_memory.tsv");
+    let previous: HashMap<String, usize> = fs::read_to_string(&path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let (key, bytes) = line.rsplit_once('\t')?;
+            Some((key.to_string(), bytes.parse().ok()?))
+        })
+        .collect();
+
+    println!("peak memory of one parse:");
+    let mut saved = String::new();
+    for (key, bytes) in memory {
+        let change = match previous.get(key) {
+            Some(&before) if before > 0 => {
+                format!(" ({:+.1}%)", (*bytes as f64 / before as f64 - 1.0) * 100.0)
+            }
+            _ => String::new(),
+        };
+        println!(
+            "  {:<32} {:>10.1} KiB{}",
+            key.replace('\t', " / "),
+            *bytes as f64 / 1024.0,
+            change
+        );
+        writeln!(saved, "{}\t{}", key, bytes).unwrap();
+    }
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, saved).unwrap();
+}
+
+fn main() {
+    let mut c = Criterion::default().configure_from_args();
+    let mut memory = vec![];
+    for corpus in &corpora() {
+        bench::<Rowan>(&mut c, corpus, &mut memory);
+        bench::<RowanParallel>(&mut c, corpus, &mut memory);
+        bench::<Recursive>(&mut c, corpus, &mut memory);
+        bench::<Chumsky>(&mut c, corpus, &mut memory);
+        bench::<Nom>(&mut c, corpus, &mut memory);
+        bench::<JsonPop>(&mut c, corpus, &mut memory);
+        bench::<TapeBackend>(&mut c, corpus, &mut memory);
+        bench::<OnDemand>(&mut c, corpus, &mut memory);
+    }
+    report_memory(&memory);
+    c.final_summary();
+}
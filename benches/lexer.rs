@@ -0,0 +1,24 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rowan_json::{
+    lexer::{Lexer, LexerBackend},
+    parser::Parser,
+};
+
+fn logos_vs_simd(c: &mut Criterion) {
+    let input = include_str!("../assets/big.json");
+    let mut group = c.benchmark_group("lexer");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    for backend in [LexerBackend::Logos, LexerBackend::Simd] {
+        let name = format!("{:?}", backend);
+        group.bench_with_input(BenchmarkId::new("lex", &name), input, |b, input| {
+            b.iter(|| Lexer::with_backend(input, backend).count())
+        });
+        group.bench_with_input(BenchmarkId::new("parse", &name), input, |b, input| {
+            b.iter(|| Parser::with_backend(input, backend).parse())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, logos_vs_simd);
+criterion_main!(benches);
@@ -0,0 +1,22 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rowan_json::{parallel, parser::Parser};
+
+fn sequential_vs_parallel(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parallel");
+    for (name, input) in [
+        ("200k.json", include_str!("../assets/200k.json")),
+        ("big.json", include_str!("../assets/big.json")),
+    ] {
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::new("sequential", name), input, |b, input| {
+            b.iter(|| Parser::new(input).parse())
+        });
+        group.bench_with_input(BenchmarkId::new("rayon", name), input, |b, input| {
+            b.iter(|| parallel::parse(input))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, sequential_vs_parallel);
+criterion_main!(benches);
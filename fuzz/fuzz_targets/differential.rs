@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // Every backend takes `&str`; invalid UTF-8 never reaches them.
+    if let Ok(input) = std::str::from_utf8(data) {
+        rowan_json::differential::check(input);
+    }
+});
@@ -0,0 +1,398 @@
+//! A common interface over the crate's parsers, so they can be swapped,
+//! cross-checked against each other and benchmarked uniformly.
+//!
+//! Every backend takes the same `&str` input, reports problems as
+//! [`Diagnostic`]s and converts its own output into the canonical
+//! [`Value`].
+
+use rowan::{NodeOrToken, TextRange, TextSize};
+
+use crate::diagnostic::Diagnostic;
+use crate::document::{self, Document};
+use crate::duplicates::{self, DuplicateKeys};
+use crate::escape::unescape;
+use crate::lexer::SyntaxKind;
+use crate::parser::{Parse, Parser};
+use crate::syntax::{SyntaxElement, SyntaxNode};
+use crate::tape::{self, Tape};
+use crate::value::{Number, Value};
+use crate::{chumsky, nom, parallel, recursive};
+
+pub trait JsonBackend {
+    const NAME: &'static str;
+
+    /// The backend's own representation of a document.
+    type Output<'a>;
+
+    fn parse<'a>(&self, input: &'a str) -> Result<Self::Output<'a>, Vec<Diagnostic>>;
+
+    fn to_value(&self, output: &Self::Output<'_>) -> Value;
+
+    fn parse_value(&self, input: &str) -> Result<Value, Vec<Diagnostic>> {
+        self.parse(input).map(|output| self.to_value(&output))
+    }
+}
+
+/// The object safe part of [`JsonBackend`], for picking a backend at
+/// runtime.
+pub trait DynBackend: Sync {
+    fn name(&self) -> &'static str;
+
+    fn parse_value(&self, input: &str) -> Result<Value, Vec<Diagnostic>>;
+
+    /// Parses `input` and applies a duplicate key policy. Warnings are
+    /// returned next to the value.
+    fn parse_value_with(
+        &self,
+        input: &str,
+        policy: DuplicateKeys,
+    ) -> Result<(Value, Vec<Diagnostic>), Vec<Diagnostic>> {
+        let mut value = self.parse_value(input)?;
+        let mut diagnostics = vec![];
+        // Values carry no ranges, so duplicates are located on the tree,
+        // which is only built when there are any.
+        if policy.reports() && duplicates::has_duplicates(&value) {
+            diagnostics = duplicates::check(&Parser::new(input).parse().syntax(), policy);
+            if policy == DuplicateKeys::Error {
+                return Err(diagnostics);
+            }
+        }
+        duplicates::resolve(&mut value, policy);
+        Ok((value, diagnostics))
+    }
+}
+
+impl<B: JsonBackend + Sync> DynBackend for B {
+    fn name(&self) -> &'static str {
+        B::NAME
+    }
+
+    fn parse_value(&self, input: &str) -> Result<Value, Vec<Diagnostic>> {
+        JsonBackend::parse_value(self, input)
+    }
+}
+
+/// Every backend, starting with the reference rowan parser.
+pub fn all() -> [&'static dyn DynBackend; 7] {
+    [
+        &Rowan,
+        &Parallel,
+        &Recursive,
+        &Chumsky,
+        &Nom,
+        &TapeBackend,
+        &OnDemand,
+    ]
+}
+
+/// Looks a backend up by its [`JsonBackend::NAME`].
+pub fn by_name(name: &str) -> Option<&'static dyn DynBackend> {
+    all().into_iter().find(|backend| backend.name() == name)
+}
+
+/// The lossless rowan parser, see [`crate::parser`].
+pub struct Rowan;
+
+impl JsonBackend for Rowan {
+    const NAME: &'static str = "rowan";
+    type Output<'a> = Parse;
+
+    fn parse(&self, input: &str) -> Result<Parse, Vec<Diagnostic>> {
+        checked(Parser::new(input).parse())
+    }
+
+    fn to_value(&self, parse: &Parse) -> Value {
+        syntax_to_value(&parse.syntax()).unwrap_or(Value::Null)
+    }
+}
+
+/// The rowan parser driven by [`crate::parallel`].
+pub struct Parallel;
+
+impl JsonBackend for Parallel {
+    const NAME: &'static str = "parallel";
+    type Output<'a> = Parse;
+
+    fn parse(&self, input: &str) -> Result<Parse, Vec<Diagnostic>> {
+        checked(parallel::parse(input))
+    }
+
+    fn to_value(&self, parse: &Parse) -> Value {
+        Rowan.to_value(parse)
+    }
+}
+
+fn checked(parse: Parse) -> Result<Parse, Vec<Diagnostic>> {
+    if parse.errors.is_empty() {
+        Ok(parse)
+    } else {
+        Err(parse.errors)
+    }
+}
+
+/// Converts a tree without errors into a value. Returns `None` for an empty
+/// document.
+pub fn syntax_to_value(root: &SyntaxNode) -> Option<Value> {
+    root.children_with_tokens()
+        .find(|element| !element.kind().is_trivia())
+        .map(element_to_value)
+}
+
+pub(crate) fn element_to_value(element: SyntaxElement) -> Value {
+    let node = match element {
+        NodeOrToken::Token(token) => return token_to_value(token.kind(), token.text()),
+        NodeOrToken::Node(node) => node,
+    };
+    let mut items = node
+        .children_with_tokens()
+        .filter(|element| !element.kind().is_trivia() && !is_punctuation(element.kind()));
+    match node.kind() {
+        SyntaxKind::Object => {
+            let mut members = vec![];
+            while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                let key = key.into_token().unwrap();
+                let key = unescape(&key.text()[1..key.text().len() - 1]).into_owned();
+                members.push((key, element_to_value(value)));
+            }
+            Value::Object(members)
+        }
+        SyntaxKind::Array => Value::Array(items.map(element_to_value).collect()),
+        kind => unreachable!("{:?} in a tree without errors", kind),
+    }
+}
+
+fn token_to_value(kind: SyntaxKind, text: &str) -> Value {
+    match kind {
+        SyntaxKind::String => Value::String(unescape(&text[1..text.len() - 1]).into_owned()),
+        SyntaxKind::Number => Value::Number(Number::from_text(text)),
+        SyntaxKind::True => Value::Bool(true),
+        SyntaxKind::False => Value::Bool(false),
+        _ => Value::Null,
+    }
+}
+
+fn is_punctuation(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::LeftBrace
+            | SyntaxKind::RightBrace
+            | SyntaxKind::LeftBracket
+            | SyntaxKind::RightBracket
+            | SyntaxKind::Colon
+            | SyntaxKind::Comma
+    )
+}
+
+/// The hand written recursive descent parser, see [`crate::recursive`].
+pub struct Recursive;
+
+impl JsonBackend for Recursive {
+    const NAME: &'static str = "recursive";
+    type Output<'a> = recursive::Value<'a>;
+
+    fn parse<'a>(&self, input: &'a str) -> Result<recursive::Value<'a>, Vec<Diagnostic>> {
+        recursive::Parser::new(input).parse().map_err(|e| vec![e])
+    }
+
+    fn to_value(&self, value: &recursive::Value<'_>) -> Value {
+        match value {
+            recursive::Value::String(raw) => token_to_value(SyntaxKind::String, raw),
+            recursive::Value::Boolean(b) => Value::Bool(*b),
+            recursive::Value::Null => Value::Null,
+            recursive::Value::Number(n) => Value::Number(Number::from(*n)),
+            recursive::Value::Object(members) => Value::Object(
+                members
+                    .iter()
+                    .map(|(key, value)| {
+                        let key = unescape(&key[1..key.len() - 1]).into_owned();
+                        (key, self.to_value(value))
+                    })
+                    .collect(),
+            ),
+            recursive::Value::Array(elements) => {
+                Value::Array(elements.iter().map(|v| self.to_value(v)).collect())
+            }
+        }
+    }
+}
+
+/// The parser combinator backend, see [`crate::chumsky`].
+pub struct Chumsky;
+
+impl JsonBackend for Chumsky {
+    const NAME: &'static str = "chumsky";
+    type Output<'a> = chumsky::Spanned<chumsky::Json>;
+
+    fn parse(&self, input: &str) -> Result<chumsky::Spanned<chumsky::Json>, Vec<Diagnostic>> {
+        match chumsky::parse(input) {
+            (Some(json), errors) if errors.is_empty() => Ok(json),
+            (_, errors) => Err(errors),
+        }
+    }
+
+    fn to_value(&self, json: &chumsky::Spanned<chumsky::Json>) -> Value {
+        match &json.node {
+            chumsky::Json::Invalid | chumsky::Json::Null => Value::Null,
+            chumsky::Json::Bool(b) => Value::Bool(*b),
+            chumsky::Json::Str(s) => Value::String(s.clone()),
+            chumsky::Json::Num(n) => Value::Number(Number::from(*n)),
+            chumsky::Json::Array(elements) => {
+                Value::Array(elements.iter().map(|v| self.to_value(v)).collect())
+            }
+            chumsky::Json::Object(members) => Value::Object(
+                members
+                    .iter()
+                    .map(|(key, value)| (key.node.clone(), self.to_value(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// The nom parser combinator backend, see [`crate::nom`].
+pub struct Nom;
+
+impl JsonBackend for Nom {
+    const NAME: &'static str = "nom";
+    type Output<'a> = nom::JsonValue;
+
+    fn parse(&self, input: &str) -> Result<nom::JsonValue, Vec<Diagnostic>> {
+        let at = |rest: &str, message: &str| {
+            let offset = TextSize::from((input.len() - rest.len()) as u32);
+            vec![Diagnostic::new(message, TextRange::empty(offset))]
+        };
+        match nom::json(input) {
+            Ok(("", value)) => Ok(value),
+            Ok((rest, _)) => Err(at(rest, "expected end of input")),
+            Err(::nom::Err::Error(e) | ::nom::Err::Failure(e)) => Err(at(e.input, "invalid JSON")),
+            Err(::nom::Err::Incomplete(_)) => Err(at("", "unexpected end of input")),
+        }
+    }
+
+    fn to_value(&self, value: &nom::JsonValue) -> Value {
+        match value {
+            nom::JsonValue::Null => Value::Null,
+            nom::JsonValue::Bool(b) => Value::Bool(*b),
+            nom::JsonValue::Str(s) => Value::String(s.clone()),
+            nom::JsonValue::Num(n) => Value::Number(Number::from(*n)),
+            nom::JsonValue::Array(elements) => {
+                Value::Array(elements.iter().map(|v| self.to_value(v)).collect())
+            }
+            nom::JsonValue::Object(members) => Value::Object(
+                members
+                    .iter()
+                    .map(|(key, value)| (key.clone(), self.to_value(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// The flat tape, see [`crate::tape`].
+pub struct TapeBackend;
+
+impl JsonBackend for TapeBackend {
+    const NAME: &'static str = "tape";
+    type Output<'a> = Tape<'a>;
+
+    fn parse<'a>(&self, input: &'a str) -> Result<Tape<'a>, Vec<Diagnostic>> {
+        Tape::parse(input).map_err(|e| vec![e])
+    }
+
+    fn to_value(&self, tape: &Tape<'_>) -> Value {
+        fn convert(node: tape::Node<'_, '_>) -> Value {
+            match node.kind() {
+                tape::Kind::Object => Value::Object(
+                    node.iter_members()
+                        .map(|(key, value)| (key.into_owned(), convert(value)))
+                        .collect(),
+                ),
+                tape::Kind::Array => Value::Array(node.iter_elements().map(convert).collect()),
+                tape::Kind::String => Value::String(node.as_str().unwrap().into_owned()),
+                tape::Kind::Number => Value::Number(Number::from_text(node.raw_number().unwrap())),
+                tape::Kind::Boolean => Value::Bool(node.as_bool().unwrap()),
+                tape::Kind::Null => Value::Null,
+            }
+        }
+        convert(tape.root())
+    }
+}
+
+/// The on-demand document, see [`crate::document`].
+pub struct OnDemand;
+
+impl JsonBackend for OnDemand {
+    const NAME: &'static str = "on-demand";
+    type Output<'a> = Document<'a>;
+
+    fn parse<'a>(&self, input: &'a str) -> Result<Document<'a>, Vec<Diagnostic>> {
+        Document::parse(input).map_err(|e| vec![e])
+    }
+
+    fn to_value(&self, document: &Document<'_>) -> Value {
+        fn convert(value: document::Value<'_>) -> Value {
+            if value.is_object() {
+                Value::Object(
+                    value
+                        .members()
+                        .map(|(key, value)| (key.into_owned(), convert(value)))
+                        .collect(),
+                )
+            } else if value.is_array() {
+                Value::Array(value.elements().map(convert).collect())
+            } else if let Some(s) = value.as_str() {
+                Value::String(s.into_owned())
+            } else if let Some(b) = value.as_bool() {
+                Value::Bool(b)
+            } else if value.is_null() {
+                Value::Null
+            } else {
+                Value::Number(Number::from_text(value.raw()))
+            }
+        }
+        convert(document.root())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backends_agree_on_assets() {
+        let input = include_str!("../assets/big.json");
+        let expected = JsonBackend::parse_value(&Rowan, input).unwrap();
+        for backend in all() {
+            let value = backend.parse_value(input).unwrap();
+            assert_eq!(value, expected, "{}", backend.name());
+        }
+    }
+
+    #[test]
+    fn backends_report_errors() {
+        for backend in all() {
+            for input in ["", "[1,", r#"{"a" 1}"#, "[1] 2"] {
+                let errors = backend.parse_value(input).unwrap_err();
+                assert!(
+                    !errors.is_empty(),
+                    "{} accepted {:?}",
+                    backend.name(),
+                    input
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn converts_rowan_tree() {
+        let value = JsonBackend::parse_value(
+            &Rowan,
+            r#" {"a\n": [1, -2, 3.5, "x"], "b": {}, "a\n": null} "#,
+        );
+        assert_eq!(
+            value.unwrap().to_string(),
+            r#"{"a\n":[1,-2,3.5,"x"],"b":{},"a\n":null}"#
+        );
+        assert_eq!(by_name("nom").unwrap().name(), "nom");
+    }
+}
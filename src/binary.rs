@@ -0,0 +1,1039 @@
+//! Transcoding between JSON and the binary formats CBOR (RFC 8949) and
+//! MessagePack.
+//!
+//! Every [`Value`] survives a trip through CBOR, and through MessagePack
+//! unless it holds a number beyond double precision: integers are written
+//! as integers in the smallest width that holds them, floats as single
+//! precision when that is exact and double precision otherwise, larger
+//! numbers as CBOR decimal fractions, and objects as maps that keep their
+//! members in order, duplicates included.
+//! Decoding rejects what JSON cannot express, such as byte strings,
+//! non-string map keys and floats that are NaN or infinite, and applies the
+//! limits of [`ParseOptions`] as the JSON parsers do.
+//!
+//! [`transcode`] goes straight from JSON text to either format on top of
+//! the [`EventParser`], so no value is ever built. Both formats write the
+//! length of an array or map before its items, so it reads the input twice:
+//! once to validate it and count the items of every container, and once to
+//! write it.
+
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::diagnostic::Diagnostic;
+use crate::escape::unescape;
+use crate::event::{Event, EventParser};
+use crate::options::{Limit, ParseOptions};
+use crate::value::{Number, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Cbor,
+    MessagePack,
+}
+
+/// Encodes `value` in `format`. Fails if the format cannot express the
+/// value, such as a number beyond double precision in MessagePack.
+pub fn encode(value: &Value, format: Format) -> io::Result<Vec<u8>> {
+    let mut encoder = Encoder {
+        out: vec![],
+        format,
+    };
+    encoder.value(value)?;
+    Ok(encoder.out)
+}
+
+/// Decodes a single value in `format` that takes up all of `bytes`.
+pub fn decode(bytes: &[u8], format: Format) -> Result<Value, DecodeError> {
+    decode_with(bytes, format, ParseOptions::default())
+}
+
+/// Like [`decode`], with the limits of `options`. The dialect does not
+/// apply.
+pub fn decode_with(
+    bytes: &[u8],
+    format: Format,
+    options: ParseOptions,
+) -> Result<Value, DecodeError> {
+    let mut reader = Reader {
+        bytes,
+        pos: 0,
+        format,
+        options,
+        nodes: 0,
+    };
+    if bytes.len() > options.max_bytes {
+        return Err(reader.limit_at(options.max_bytes, Limit::Bytes));
+    }
+    let value = reader.value()?;
+    if reader.pos < bytes.len() {
+        return Err(reader.error("trailing bytes after the value"));
+    }
+    Ok(value)
+}
+
+/// Validates `input` as JSON and writes it to `out` in `format`, producing
+/// the same bytes as [`encode`] would for its value.
+///
+/// Nothing is written if the input has an error.
+pub fn transcode(
+    input: &str,
+    options: ParseOptions,
+    format: Format,
+    out: impl Write,
+) -> Result<(), TranscodeError> {
+    // The number of items of every container, in the order they open.
+    let mut lengths: Vec<(bool, usize)> = vec![];
+    let mut open: Vec<usize> = vec![];
+    for event in EventParser::with_options(input, options) {
+        let (event, _) = event?;
+        if let Some(&container) = open.last() {
+            // A member is counted at its key, an element at its value.
+            let (is_object, len) = &mut lengths[container];
+            let counts = match event {
+                Event::Key(_) => true,
+                Event::EndObject | Event::EndArray => false,
+                _ => !*is_object,
+            };
+            if counts {
+                *len += 1;
+            }
+        }
+        match event {
+            Event::StartObject | Event::StartArray => {
+                open.push(lengths.len());
+                lengths.push((event == Event::StartObject, 0));
+            }
+            Event::EndObject | Event::EndArray => {
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+
+    let mut encoder = Encoder { out, format };
+    let mut lengths = lengths.into_iter();
+    for event in EventParser::with_options(input, options) {
+        let (event, _) = event?;
+        match event {
+            Event::StartObject => encoder.map_header(lengths.next().unwrap().1)?,
+            Event::StartArray => encoder.array_header(lengths.next().unwrap().1)?,
+            Event::EndObject | Event::EndArray => {}
+            Event::Key(raw) | Event::String(raw) => encoder.string(&unescape(raw))?,
+            Event::Number(text) => encoder.number(&Number::from_text(text))?,
+            Event::Boolean(b) => encoder.bool(b)?,
+            Event::Null => encoder.null()?,
+        }
+    }
+    Ok(())
+}
+
+/// Why bytes could not be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    pub message: String,
+    /// Where the offending item starts.
+    pub offset: usize,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Why [`transcode`] failed.
+#[derive(Debug)]
+pub enum TranscodeError {
+    /// The input is not valid JSON.
+    Syntax(Diagnostic),
+    Io(io::Error),
+}
+
+impl From<Diagnostic> for TranscodeError {
+    fn from(diagnostic: Diagnostic) -> Self {
+        TranscodeError::Syntax(diagnostic)
+    }
+}
+
+impl From<io::Error> for TranscodeError {
+    fn from(err: io::Error) -> Self {
+        TranscodeError::Io(err)
+    }
+}
+
+impl fmt::Display for TranscodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TranscodeError::Syntax(diagnostic) => f.write_str(&diagnostic.message),
+            TranscodeError::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for TranscodeError {}
+
+struct Encoder<W> {
+    out: W,
+    format: Format,
+}
+
+impl<W: Write> Encoder<W> {
+    fn value(&mut self, value: &Value) -> io::Result<()> {
+        match value {
+            Value::Null => self.null(),
+            Value::Bool(b) => self.bool(*b),
+            Value::Number(n) => self.number(n),
+            Value::String(s) => self.string(s),
+            Value::Array(elements) => {
+                self.array_header(elements.len())?;
+                elements.iter().try_for_each(|element| self.value(element))
+            }
+            Value::Object(members) => {
+                self.map_header(members.len())?;
+                members.iter().try_for_each(|(name, value)| {
+                    self.string(name)?;
+                    self.value(value)
+                })
+            }
+        }
+    }
+
+    fn null(&mut self) -> io::Result<()> {
+        self.out.write_all(match self.format {
+            Format::Cbor => &[0xf6],
+            Format::MessagePack => &[0xc0],
+        })
+    }
+
+    fn bool(&mut self, b: bool) -> io::Result<()> {
+        let byte = match (self.format, b) {
+            (Format::Cbor, false) => 0xf4,
+            (Format::Cbor, true) => 0xf5,
+            (Format::MessagePack, false) => 0xc2,
+            (Format::MessagePack, true) => 0xc3,
+        };
+        self.out.write_all(&[byte])
+    }
+
+    fn number(&mut self, n: &Number) -> io::Result<()> {
+        match (self.format, n) {
+            (_, &Number::PosInt(n)) => self.unsigned(n),
+            (_, &Number::NegInt(n)) if n >= 0 => self.unsigned(n as u64),
+            (Format::Cbor, &Number::NegInt(n)) => self.cbor_header(1, !n as u64),
+            (Format::MessagePack, &Number::NegInt(n)) => {
+                if n >= -32 {
+                    self.out.write_all(&[n as u8])
+                } else if let Ok(n) = i8::try_from(n) {
+                    self.out.write_all(&[0xd0, n as u8])
+                } else if let Ok(n) = i16::try_from(n) {
+                    self.write(0xd1, &n.to_be_bytes())
+                } else if let Ok(n) = i32::try_from(n) {
+                    self.write(0xd2, &n.to_be_bytes())
+                } else {
+                    self.write(0xd3, &n.to_be_bytes())
+                }
+            }
+            (Format::Cbor, Number::Large(text)) => {
+                // A decimal fraction: tag 4 on an array of the exponent and
+                // the mantissa.
+                let (negative, mantissa, exponent) = decimal_parts(text).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("`{}` does not fit a CBOR decimal fraction", text),
+                    )
+                })?;
+                self.out.write_all(&[0xc4, 0x82])?;
+                match u64::try_from(exponent) {
+                    Ok(exponent) => self.cbor_header(0, exponent)?,
+                    Err(_) => self.cbor_header(1, !exponent as u64)?,
+                }
+                match (negative, mantissa) {
+                    (true, 1..) => self.cbor_header(1, mantissa - 1),
+                    _ => self.cbor_header(0, mantissa),
+                }
+            }
+            (Format::MessagePack, Number::Large(text)) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("`{}` is beyond the precision of a MessagePack float", text),
+            )),
+            (_, Number::Float(n)) => {
+                let n = *n;
+                let (single, double) = match self.format {
+                    Format::Cbor => (0xfa, 0xfb),
+                    Format::MessagePack => (0xca, 0xcb),
+                };
+                if n as f32 as f64 == n || n.is_nan() {
+                    self.write(single, &(n as f32).to_be_bytes())
+                } else {
+                    self.write(double, &n.to_be_bytes())
+                }
+            }
+        }
+    }
+
+    fn unsigned(&mut self, n: u64) -> io::Result<()> {
+        match self.format {
+            Format::Cbor => self.cbor_header(0, n),
+            Format::MessagePack if n < 0x80 => self.out.write_all(&[n as u8]),
+            Format::MessagePack => {
+                if let Ok(n) = u8::try_from(n) {
+                    self.out.write_all(&[0xcc, n])
+                } else if let Ok(n) = u16::try_from(n) {
+                    self.write(0xcd, &n.to_be_bytes())
+                } else if let Ok(n) = u32::try_from(n) {
+                    self.write(0xce, &n.to_be_bytes())
+                } else {
+                    self.write(0xcf, &n.to_be_bytes())
+                }
+            }
+        }
+    }
+
+    fn string(&mut self, s: &str) -> io::Result<()> {
+        match self.format {
+            Format::Cbor => self.cbor_header(3, s.len() as u64)?,
+            Format::MessagePack => self.msgpack_length(s.len(), (0xa0, 32), Some(0xd9), 0xda)?,
+        }
+        self.out.write_all(s.as_bytes())
+    }
+
+    fn array_header(&mut self, len: usize) -> io::Result<()> {
+        match self.format {
+            Format::Cbor => self.cbor_header(4, len as u64),
+            Format::MessagePack => self.msgpack_length(len, (0x90, 16), None, 0xdc),
+        }
+    }
+
+    fn map_header(&mut self, len: usize) -> io::Result<()> {
+        match self.format {
+            Format::Cbor => self.cbor_header(5, len as u64),
+            Format::MessagePack => self.msgpack_length(len, (0x80, 16), None, 0xde),
+        }
+    }
+
+    /// The initial byte of a CBOR item with major type `major` and argument
+    /// `arg`, followed by the argument in the fewest bytes that hold it.
+    fn cbor_header(&mut self, major: u8, arg: u64) -> io::Result<()> {
+        let major = major << 5;
+        if arg < 24 {
+            self.out.write_all(&[major | arg as u8])
+        } else if let Ok(arg) = u8::try_from(arg) {
+            self.out.write_all(&[major | 24, arg])
+        } else if let Ok(arg) = u16::try_from(arg) {
+            self.write(major | 25, &arg.to_be_bytes())
+        } else if let Ok(arg) = u32::try_from(arg) {
+            self.write(major | 26, &arg.to_be_bytes())
+        } else {
+            self.write(major | 27, &arg.to_be_bytes())
+        }
+    }
+
+    /// A MessagePack length: folded into the `fixed` marker if it is below
+    /// the bound, or else after the 8-bit marker, if the type has one, or
+    /// after `marker16` or the 32-bit marker that follows it.
+    fn msgpack_length(
+        &mut self,
+        len: usize,
+        (fixed, bound): (u8, usize),
+        marker8: Option<u8>,
+        marker16: u8,
+    ) -> io::Result<()> {
+        if len < bound {
+            self.out.write_all(&[fixed | len as u8])
+        } else if let (Some(marker), Ok(len)) = (marker8, u8::try_from(len)) {
+            self.out.write_all(&[marker, len])
+        } else if let Ok(len) = u16::try_from(len) {
+            self.write(marker16, &len.to_be_bytes())
+        } else if let Ok(len) = u32::try_from(len) {
+            self.write(marker16 + 1, &len.to_be_bytes())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "MessagePack lengths are limited to 32 bits",
+            ))
+        }
+    }
+
+    fn write(&mut self, marker: u8, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(&[marker])?;
+        self.out.write_all(bytes)
+    }
+}
+
+/// The start of a value, with the length of a container whose items
+/// follow it. CBOR allows containers of indefinite length, which end at a
+/// [`Item::Break`].
+enum Item {
+    Scalar(Value),
+    Array(Option<u64>),
+    Map(Option<u64>),
+    Break,
+}
+
+/// A container being decoded.
+enum Frame {
+    Array(Vec<Value>, Option<u64>),
+    Map(Vec<(String, Value)>, Option<u64>, Option<String>),
+}
+
+struct Reader<'b> {
+    bytes: &'b [u8],
+    pos: usize,
+    format: Format,
+    options: ParseOptions,
+    /// The values decoded so far, containers included.
+    nodes: usize,
+}
+
+impl Reader<'_> {
+    /// Decodes a value, keeping open containers on an explicit stack so
+    /// that deep nesting cannot overflow the call stack.
+    fn value(&mut self) -> Result<Value, DecodeError> {
+        let mut stack: Vec<Frame> = vec![];
+        loop {
+            let start = self.pos;
+            let expects_key = matches!(stack.last(), Some(Frame::Map(_, _, None)));
+            let item = self.item()?;
+            if !expects_key && !matches!(item, Item::Break) {
+                self.nodes += 1;
+                if self.nodes > self.options.max_nodes {
+                    return Err(self.limit_at(start, Limit::Nodes));
+                }
+            }
+            let mut value = match item {
+                Item::Break => match stack.pop() {
+                    Some(Frame::Array(elements, None)) => Value::Array(elements),
+                    Some(Frame::Map(members, None, None)) => Value::Object(members),
+                    _ => return Err(self.error_at(start, "unexpected break")),
+                },
+                Item::Scalar(Value::String(key)) if expects_key => {
+                    if let Some(Frame::Map(members, _, pending)) = stack.last_mut() {
+                        if members.len() == self.options.max_members {
+                            return Err(self.limit_at(start, Limit::Members));
+                        }
+                        *pending = Some(key);
+                    }
+                    continue;
+                }
+                _ if expects_key => return Err(self.error_at(start, "map keys must be strings")),
+                Item::Scalar(value) => value,
+                Item::Array(_) | Item::Map(_) if stack.len() == self.options.max_depth => {
+                    return Err(self.limit_at(start, Limit::Depth));
+                }
+                Item::Array(Some(0)) => Value::Array(vec![]),
+                Item::Map(Some(0)) => Value::Object(vec![]),
+                Item::Array(len) => {
+                    stack.push(Frame::Array(self.capacity(len), len));
+                    continue;
+                }
+                Item::Map(len) => {
+                    stack.push(Frame::Map(self.capacity(len), len, None));
+                    continue;
+                }
+            };
+            // Add the value to its container, and every container it
+            // completes to theirs.
+            loop {
+                let complete = match stack.last_mut() {
+                    None => return Ok(value),
+                    Some(Frame::Array(elements, len)) => {
+                        elements.push(value);
+                        Some(elements.len() as u64) == *len
+                    }
+                    Some(Frame::Map(members, len, key)) => {
+                        members.push((key.take().unwrap(), value));
+                        Some(members.len() as u64) == *len
+                    }
+                };
+                if !complete {
+                    break;
+                }
+                value = match stack.pop() {
+                    Some(Frame::Array(elements, _)) => Value::Array(elements),
+                    Some(Frame::Map(members, _, _)) => Value::Object(members),
+                    None => unreachable!(),
+                };
+            }
+        }
+    }
+
+    /// Room for `len` items, but no more than the remaining bytes could
+    /// hold, so a bogus length cannot exhaust memory up front.
+    fn capacity<T>(&self, len: Option<u64>) -> Vec<T> {
+        let remaining = (self.bytes.len() - self.pos) as u64;
+        Vec::with_capacity(len.unwrap_or(0).min(remaining) as usize)
+    }
+
+    fn item(&mut self) -> Result<Item, DecodeError> {
+        match self.format {
+            Format::Cbor => self.cbor_item(),
+            Format::MessagePack => self.msgpack_item(),
+        }
+    }
+
+    fn cbor_item(&mut self) -> Result<Item, DecodeError> {
+        // Errors point at the tags in front of the item, if any.
+        let start = self.pos;
+        loop {
+            let initial = self.byte()?;
+            let (major, info) = (initial >> 5, initial & 0x1f);
+            if info == 31 {
+                return match major {
+                    3 => self
+                        .cbor_chunks(start)
+                        .map(|s| Item::Scalar(Value::String(s))),
+                    4 => Ok(Item::Array(None)),
+                    5 => Ok(Item::Map(None)),
+                    7 => Ok(Item::Break),
+                    _ => Err(self.error_at(start, "invalid indefinite length")),
+                };
+            }
+            let arg = self.cbor_argument(start, info)?;
+            let value = match major {
+                0 => Value::Number(Number::PosInt(arg)),
+                1 => Value::Number(match i64::try_from(arg) {
+                    Ok(arg) => Number::NegInt(-1 - arg),
+                    Err(_) => Number::Float(-1.0 - arg as f64),
+                }),
+                2 => return Err(self.error_at(start, "byte strings have no JSON equivalent")),
+                3 => Value::String(self.text(start, arg)?),
+                4 => return Ok(Item::Array(Some(arg))),
+                5 => return Ok(Item::Map(Some(arg))),
+                6 if arg == 4 => self.decimal_fraction(start)?,
+                // Other tags only say how to interpret the item after them.
+                6 => continue,
+                _ => match (info, arg) {
+                    (20, _) => Value::Bool(false),
+                    (21, _) => Value::Bool(true),
+                    (22 | 23, _) => Value::Null,
+                    (25, bits) => self.float(start, f16_to_f64(bits as u16))?,
+                    (26, bits) => self.float(start, f32::from_bits(bits as u32) as f64)?,
+                    (27, bits) => self.float(start, f64::from_bits(bits))?,
+                    _ => return Err(self.error_at(start, "unsupported simple value")),
+                },
+            };
+            return Ok(Item::Scalar(value));
+        }
+    }
+
+    /// The argument that follows an initial byte with additional
+    /// information `info`, other than an indefinite length.
+    fn cbor_argument(&mut self, start: usize, info: u8) -> Result<u64, DecodeError> {
+        Ok(match info {
+            0..=23 => info as u64,
+            24 => self.byte()? as u64,
+            25 => u16::from_be_bytes(self.array()?) as u64,
+            26 => u32::from_be_bytes(self.array()?) as u64,
+            27 => u64::from_be_bytes(self.array()?),
+            _ => return Err(self.error_at(start, "reserved additional information")),
+        })
+    }
+
+    /// The number of a decimal fraction, the array of an integer exponent
+    /// and an integer mantissa after tag 4. Bignum mantissas are not
+    /// supported.
+    fn decimal_fraction(&mut self, start: usize) -> Result<Value, DecodeError> {
+        if self.byte()? != 0x82 {
+            return Err(self.error_at(start, "invalid decimal fraction"));
+        }
+        let exponent = self.cbor_integer(start)?;
+        let mantissa = self.cbor_integer(start)?;
+        let text = format!("{}e{}", mantissa, exponent);
+        Ok(Value::Number(Number::from_text(&text)))
+    }
+
+    /// An integer in a decimal fraction.
+    fn cbor_integer(&mut self, start: usize) -> Result<i128, DecodeError> {
+        let initial = self.byte()?;
+        let arg = self.cbor_argument(start, initial & 0x1f)? as i128;
+        match initial >> 5 {
+            0 => Ok(arg),
+            1 => Ok(-1 - arg),
+            _ => Err(self.error_at(start, "invalid decimal fraction")),
+        }
+    }
+
+    /// The chunks of an indefinite-length text string, joined. Each chunk
+    /// must be a definite-length text string.
+    fn cbor_chunks(&mut self, start: usize) -> Result<String, DecodeError> {
+        let mut text = String::new();
+        loop {
+            let chunk = self.pos;
+            match self.byte()? {
+                0xff => return Ok(text),
+                initial if initial >> 5 == 3 && initial & 0x1f != 31 => self.pos = chunk,
+                _ => return Err(self.error_at(chunk, "invalid text string chunk")),
+            }
+            match self.cbor_item()? {
+                Item::Scalar(Value::String(chunk)) => text.push_str(&chunk),
+                _ => unreachable!(),
+            }
+            if text.len() > self.options.max_string_len {
+                return Err(self.limit_at(start, Limit::StringLen));
+            }
+        }
+    }
+
+    fn msgpack_item(&mut self) -> Result<Item, DecodeError> {
+        let start = self.pos;
+        let marker = self.byte()?;
+        let value = match marker {
+            0x00..=0x7f => Value::Number(Number::PosInt(marker as u64)),
+            0x80..=0x8f => return Ok(Item::Map(Some((marker & 0x0f) as u64))),
+            0x90..=0x9f => return Ok(Item::Array(Some((marker & 0x0f) as u64))),
+            0xa0..=0xbf => Value::String(self.text(start, (marker & 0x1f) as u64)?),
+            0xc0 => Value::Null,
+            0xc2 => Value::Bool(false),
+            0xc3 => Value::Bool(true),
+            0xc4..=0xc6 => return Err(self.error_at(start, "binary data has no JSON equivalent")),
+            0xc7..=0xc9 | 0xd4..=0xd8 => {
+                return Err(self.error_at(start, "extension types have no JSON equivalent"))
+            }
+            0xca => {
+                let f = f32::from_be_bytes(self.array()?);
+                self.float(start, f as f64)?
+            }
+            0xcb => {
+                let f = f64::from_be_bytes(self.array()?);
+                self.float(start, f)?
+            }
+            0xcc => Value::Number(Number::PosInt(self.byte()? as u64)),
+            0xcd => Value::Number(Number::PosInt(u16::from_be_bytes(self.array()?) as u64)),
+            0xce => Value::Number(Number::PosInt(u32::from_be_bytes(self.array()?) as u64)),
+            0xcf => Value::Number(Number::PosInt(u64::from_be_bytes(self.array()?))),
+            0xd0 => Value::Number(signed(self.byte()? as i8 as i64)),
+            0xd1 => Value::Number(signed(i16::from_be_bytes(self.array()?) as i64)),
+            0xd2 => Value::Number(signed(i32::from_be_bytes(self.array()?) as i64)),
+            0xd3 => Value::Number(signed(i64::from_be_bytes(self.array()?))),
+            0xd9 => {
+                let len = self.byte()? as u64;
+                Value::String(self.text(start, len)?)
+            }
+            0xda => {
+                let len = u16::from_be_bytes(self.array()?) as u64;
+                Value::String(self.text(start, len)?)
+            }
+            0xdb => {
+                let len = u32::from_be_bytes(self.array()?) as u64;
+                Value::String(self.text(start, len)?)
+            }
+            0xdc => return Ok(Item::Array(Some(u16::from_be_bytes(self.array()?) as u64))),
+            0xdd => return Ok(Item::Array(Some(u32::from_be_bytes(self.array()?) as u64))),
+            0xde => return Ok(Item::Map(Some(u16::from_be_bytes(self.array()?) as u64))),
+            0xdf => return Ok(Item::Map(Some(u32::from_be_bytes(self.array()?) as u64))),
+            0xe0..=0xff => Value::Number(Number::NegInt(marker as i8 as i64)),
+            0xc1 => return Err(self.error_at(start, "invalid marker 0xc1")),
+        };
+        Ok(Item::Scalar(value))
+    }
+
+    /// The float `f` of the item at `start`, which JSON has no way to
+    /// write if it is NaN or infinite.
+    fn float(&self, start: usize, f: f64) -> Result<Value, DecodeError> {
+        match f.is_finite() {
+            true => Ok(Value::Number(Number::Float(f))),
+            false => Err(self.error_at(start, "NaN and infinity have no JSON equivalent")),
+        }
+    }
+
+    /// The UTF-8 string of `len` bytes at the current position, for the
+    /// item at `start`.
+    fn text(&mut self, start: usize, len: u64) -> Result<String, DecodeError> {
+        if len > self.options.max_string_len as u64 {
+            return Err(self.limit_at(start, Limit::StringLen));
+        }
+        let bytes = usize::try_from(len)
+            .ok()
+            .and_then(|len| self.bytes.get(self.pos..self.pos.checked_add(len)?))
+            .ok_or_else(|| self.error("unexpected end of input"))?;
+        self.pos += bytes.len();
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| self.error_at(start, "invalid UTF-8 in string"))
+    }
+
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + N)
+            .ok_or_else(|| self.error("unexpected end of input"))?;
+        self.pos += N;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn error(&self, message: &str) -> DecodeError {
+        self.error_at(self.pos, message)
+    }
+
+    fn limit_at(&self, offset: usize, limit: Limit) -> DecodeError {
+        self.error_at(offset, &limit.message(&self.options))
+    }
+
+    fn error_at(&self, offset: usize, message: &str) -> DecodeError {
+        DecodeError {
+            message: message.to_string(),
+            offset,
+        }
+    }
+}
+
+/// Splits the text of a JSON number into its sign, its significant digits
+/// as an integer and the power of ten that scales them, if both fit 64
+/// bits.
+fn decimal_parts(text: &str) -> Option<(bool, u64, i64)> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(text) => (true, text),
+        None => (false, text),
+    };
+    let (digits, exponent) = match text.split_once(['e', 'E']) {
+        Some((digits, exponent)) => (digits, exponent.parse::<i64>().ok()?),
+        None => (text, 0),
+    };
+    let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let digits = format!("{}{}", integer, fraction);
+    let significant = digits.trim_end_matches('0');
+    let exponent = exponent
+        .checked_sub(fraction.len().try_into().ok()?)?
+        .checked_add((digits.len() - significant.len()).try_into().ok()?)?;
+    let mantissa = match significant.trim_start_matches('0') {
+        "" => 0,
+        significant => significant.parse().ok()?,
+    };
+    Some((negative, mantissa, exponent))
+}
+
+/// A MessagePack signed integer, which may well be positive.
+fn signed(n: i64) -> Number {
+    match u64::try_from(n) {
+        Ok(n) => Number::PosInt(n),
+        Err(_) => Number::NegInt(n),
+    }
+}
+
+/// Widens an IEEE 754 half-precision float.
+fn f16_to_f64(bits: u16) -> f64 {
+    let exponent = (bits >> 10) & 0x1f;
+    let mantissa = (bits & 0x3ff) as f64;
+    let magnitude = match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        31 if mantissa == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        _ => (mantissa + 1024.0) * 2f64.powi(exponent as i32 - 25),
+    };
+    if bits & 0x8000 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::backend::syntax_to_value;
+    use crate::parser::Parser;
+    use crate::strategies::{layout, value};
+
+    fn parse(text: &str) -> Value {
+        let parse = Parser::new(text).parse();
+        assert!(parse.ok(), "{:?}", parse.errors);
+        syntax_to_value(&parse.syntax()).unwrap()
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn unhex(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn transcoded(text: &str, format: Format) -> Vec<u8> {
+        let mut out = vec![];
+        transcode(text, ParseOptions::default(), format, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn encodes_in_the_smallest_form() {
+        let mut actual = String::new();
+        for text in [
+            "0",
+            "24",
+            "1000",
+            "4294967296",
+            "18446744073709551615",
+            "-1",
+            "-33",
+            "-1000",
+            "-9223372036854775808",
+            "1.5",
+            "0.1",
+            "-0.0",
+            r#""aü""#,
+            r#"[1, [2, 3], {}]"#,
+            r#"{"a": true, "a": null, "b": false}"#,
+        ] {
+            let value = parse(text);
+            let cbor = encode(&value, Format::Cbor).unwrap();
+            let msgpack = encode(&value, Format::MessagePack).unwrap();
+            assert_eq!(transcoded(text, Format::Cbor), cbor);
+            assert_eq!(transcoded(text, Format::MessagePack), msgpack);
+            actual += &format!("{}: {} {}\n", text, hex(&cbor), hex(&msgpack));
+        }
+        expect![[r#"
+            0: 00 00
+            24: 1818 18
+            1000: 1903e8 cd03e8
+            4294967296: 1b0000000100000000 cf0000000100000000
+            18446744073709551615: 1bffffffffffffffff cfffffffffffffffff
+            -1: 20 ff
+            -33: 3820 d0df
+            -1000: 3903e7 d1fc18
+            -9223372036854775808: 3b7fffffffffffffff d38000000000000000
+            1.5: fa3fc00000 ca3fc00000
+            0.1: fb3fb999999999999a cb3fb999999999999a
+            -0.0: fa80000000 ca80000000
+            "aü": 6361c3bc a361c3bc
+            [1, [2, 3], {}]: 8301820203a0 930192020380
+            {"a": true, "a": null, "b": false}: a36161f56161f66162f4 83a161c3a161c0a162c2
+        "#]]
+        .assert_eq(&actual);
+    }
+
+    fn has_large(value: &Value) -> bool {
+        match value {
+            Value::Number(Number::Large(_)) => true,
+            Value::Array(elements) => elements.iter().any(has_large),
+            Value::Object(members) => members.iter().any(|(_, value)| has_large(value)),
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn encodes_large_numbers_as_decimal_fractions() {
+        let mut actual = String::new();
+        for text in [
+            "1e400",
+            "-1.50E+400",
+            "[123456789e999]",
+            "1e9223372036854775808",
+        ] {
+            let value = parse(text);
+            for format in [Format::Cbor, Format::MessagePack] {
+                let line = match encode(&value, format) {
+                    Ok(bytes) => {
+                        assert_eq!(transcoded(text, format), bytes);
+                        let decoded = decode(&bytes, format).unwrap();
+                        format!("{} {}", hex(&bytes), decoded)
+                    }
+                    Err(err) => err.to_string(),
+                };
+                actual += &format!("{}: {}\n", text, line);
+            }
+        }
+        expect![[r#"
+            1e400: c48219019001 1e400
+            1e400: `1e400` is beyond the precision of a MessagePack float
+            -1.50E+400: c48219018f2e -15e399
+            -1.50E+400: `-1.50E+400` is beyond the precision of a MessagePack float
+            [123456789e999]: 81c4821903e71a075bcd15 [123456789e999]
+            [123456789e999]: `123456789e999` is beyond the precision of a MessagePack float
+            1e9223372036854775808: `1e9223372036854775808` does not fit a CBOR decimal fraction
+            1e9223372036854775808: `1e9223372036854775808` is beyond the precision of a MessagePack float
+        "#]].assert_eq(&actual);
+    }
+
+    #[test]
+    fn decodes_what_other_encoders_write() {
+        let check = |hex: &str, format| decode(&unhex(hex), format).unwrap().to_string();
+        // Half floats, indefinite lengths and tags, from RFC 8949 appendix A.
+        assert_eq!(check("f93c00", Format::Cbor), "1.0");
+        assert_eq!(check("f97bff", Format::Cbor), "65504.0");
+        assert_eq!(check("f90001", Format::Cbor), "5.960464477539063e-8");
+        assert_eq!(check("f9c400", Format::Cbor), "-4.0");
+        assert_eq!(
+            check("3bffffffffffffffff", Format::Cbor),
+            "-1.8446744073709552e19"
+        );
+        assert_eq!(
+            check("bf61610161629f0203ffff", Format::Cbor),
+            r#"{"a":1,"b":[2,3]}"#
+        );
+        assert_eq!(
+            check("7f657374726561646d696e67ff", Format::Cbor),
+            r#""streaming""#
+        );
+        assert_eq!(check("c11a514b67b0", Format::Cbor), "1363896240");
+        assert_eq!(check("c48221196ab3", Format::Cbor), "273.15");
+        assert_eq!(check("f7", Format::Cbor), "null");
+        // Signed markers for positive numbers, and wide forms of small ones.
+        assert_eq!(check("d3000000000000002a", Format::MessagePack), "42");
+        assert_eq!(
+            check("dc000181d90161c0", Format::MessagePack),
+            r#"[{"a":null}]"#
+        );
+    }
+
+    #[test]
+    fn rejects_what_json_cannot_express() {
+        let check = |hex: &str, format| decode(&unhex(hex), format).unwrap_err().to_string();
+        expect![[r#"
+            byte strings have no JSON equivalent at byte 0
+            map keys must be strings at byte 1
+            unexpected break at byte 0
+            unsupported simple value at byte 0
+            unexpected end of input at byte 2
+            invalid UTF-8 in string at byte 0
+            trailing bytes after the value at byte 1
+            binary data has no JSON equivalent at byte 0
+            extension types have no JSON equivalent at byte 0
+            invalid marker 0xc1 at byte 0
+            unexpected end of input at byte 5
+            NaN and infinity have no JSON equivalent at byte 0
+            NaN and infinity have no JSON equivalent at byte 1
+            NaN and infinity have no JSON equivalent at byte 0
+            invalid text string chunk at byte 1
+            invalid text string chunk at byte 1
+            invalid decimal fraction at byte 0"#]]
+        .assert_eq(
+            &[
+                check("4161", Format::Cbor),
+                check("a10102", Format::Cbor),
+                check("ff", Format::Cbor),
+                check("f8ff", Format::Cbor),
+                check("8201", Format::Cbor),
+                check("6261ff", Format::Cbor),
+                check("0000", Format::Cbor),
+                check("c40161", Format::MessagePack),
+                check("d40100", Format::MessagePack),
+                check("c1", Format::MessagePack),
+                check("dbffffffff", Format::MessagePack),
+                check("f97c00", Format::Cbor),
+                check("81fb7ff8000000000000", Format::Cbor),
+                check("cbfff0000000000000", Format::MessagePack),
+                check("7f7f6161ffff", Format::Cbor),
+                check("7f01ff", Format::Cbor),
+                check("c4820161", Format::Cbor),
+            ]
+            .join("\n"),
+        );
+    }
+
+    #[test]
+    fn enforces_limits() {
+        let check = |bytes: &[u8], format, options| {
+            decode_with(bytes, format, options).unwrap_err().to_string()
+        };
+        let options = ParseOptions::default();
+        let mut deep = vec![0x81; 1_000_000];
+        deep.push(0x80);
+        let mut tagged = vec![0xc1; 1_000_000];
+        tagged.push(0x00);
+        assert_eq!(
+            decode(&tagged, Format::Cbor),
+            Ok(Value::Number(Number::PosInt(0)))
+        );
+        expect![[r#"
+            nesting exceeds the limit of 128 levels at byte 128
+            nesting exceeds the limit of 128 levels at byte 128
+            document exceeds the limit of 2 bytes at byte 2
+            string exceeds the limit of 1 bytes at byte 1
+            string exceeds the limit of 1 bytes at byte 0
+            object exceeds the limit of 1 members at byte 4
+            document exceeds the limit of 2 values at byte 2"#]]
+        .assert_eq(
+            &[
+                check(&deep, Format::Cbor, options),
+                check(&[0x91; 200], Format::MessagePack, options),
+                check(
+                    &unhex("820102"),
+                    Format::Cbor,
+                    ParseOptions {
+                        max_bytes: 2,
+                        ..options
+                    },
+                ),
+                check(
+                    &unhex("816261"),
+                    Format::Cbor,
+                    ParseOptions {
+                        max_string_len: 1,
+                        ..options
+                    },
+                ),
+                check(
+                    &unhex("7f61616161ff"),
+                    Format::Cbor,
+                    ParseOptions {
+                        max_string_len: 1,
+                        ..options
+                    },
+                ),
+                check(
+                    &unhex("a2616101616202"),
+                    Format::Cbor,
+                    ParseOptions {
+                        max_members: 1,
+                        ..options
+                    },
+                ),
+                check(
+                    &unhex("820102"),
+                    Format::Cbor,
+                    ParseOptions {
+                        max_nodes: 2,
+                        ..options
+                    },
+                ),
+            ]
+            .join("\n"),
+        );
+    }
+
+    #[test]
+    fn round_trips_big_json() {
+        let text = include_str!("../assets/big.json");
+        let value = parse(text);
+        for format in [Format::Cbor, Format::MessagePack] {
+            let bytes = transcoded(text, format);
+            assert_eq!(bytes, encode(&value, format).unwrap());
+            assert!(decode(&bytes, format).unwrap() == value);
+        }
+    }
+
+    #[test]
+    fn transcode_reports_syntax_errors() {
+        let mut out = vec![];
+        let err = transcode("[1, 2", ParseOptions::default(), Format::Cbor, &mut out).unwrap_err();
+        assert_eq!(err.to_string(), "expected `,` or closing delimiter");
+        assert!(out.is_empty());
+    }
+
+    proptest! {
+        #[test]
+        fn round_trips((value, text) in value().prop_flat_map(layout)) {
+            for format in [Format::Cbor, Format::MessagePack] {
+                match encode(&value, format) {
+                    Ok(bytes) => {
+                        prop_assert_eq!(decode(&bytes, format).unwrap(), value.clone());
+                        prop_assert_eq!(transcoded(&text, format), bytes);
+                    }
+                    Err(_) => prop_assert!(format == Format::MessagePack && has_large(&value)),
+                }
+            }
+        }
+    }
+}
@@ -1,25 +1,192 @@
-// use ariadne::{Color, Fmt, Label, Report, ReportKind, Source};
+//! A parser combinator backend built with chumsky.
+//!
+//! The input is fed to chumsky as a stream of chars spanned by their byte
+//! ranges, so every span in the output and in the errors is a byte range
+//! like the rest of the crate's.
+
+use std::ops::Range;
+
 use chumsky::prelude::*;
-use std::{collections::HashMap, env, fs};
+use chumsky::Stream;
+use rowan::{TextRange, TextSize};
+
+use crate::diagnostic::Diagnostic;
+use crate::escape::unescape;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub range: TextRange,
+}
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Json {
+    /// A value that failed to parse and was skipped by error recovery.
     Invalid,
     Null,
     Bool(bool),
     Str(String),
     Num(f64),
-    Array(Vec<Json>),
-    Object(HashMap<String, Json>),
+    Array(Vec<Spanned<Json>>),
+    /// Members in document order, duplicates included.
+    Object(Vec<(Spanned<String>, Spanned<Json>)>),
+}
+
+type Span = Range<usize>;
+
+/// The parser's error type, converted into a [`Diagnostic`] by
+/// [`ParseError::to_diagnostic`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    span: Span,
+    kind: ErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum ErrorKind {
+    /// `None` stands for the end of input in both fields.
+    Unexpected {
+        expected: Vec<Option<char>>,
+        found: Option<char>,
+    },
+    Unclosed {
+        open_span: Span,
+        open: char,
+        close: char,
+    },
+    Custom(String),
+}
+
+impl ParseError {
+    fn custom(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            kind: ErrorKind::Custom(message.into()),
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        match &self.kind {
+            ErrorKind::Unexpected { expected, found } => {
+                let mut message = format!("unexpected {}", describe(*found));
+                // Only structural tokens make for a useful suggestion; the
+                // full set also lists every digit, escape and whitespace char.
+                let expected: Vec<_> = expected
+                    .iter()
+                    .filter(|c| c.is_none_or(|c| "{}[]:,\"".contains(c)))
+                    .map(|&c| describe(c))
+                    .collect();
+                if !expected.is_empty() {
+                    message.push_str(", expected ");
+                    message.push_str(&expected.join(" or "));
+                }
+                Diagnostic::new(message, text_range(self.span.clone()))
+            }
+            ErrorKind::Unclosed {
+                open_span,
+                open,
+                close,
+            } => Diagnostic::new(
+                format!("expected `{}`", close),
+                text_range(self.span.clone()),
+            )
+            .with_label(
+                format!("unclosed `{}` opened here", open),
+                text_range(open_span.clone()),
+            ),
+            ErrorKind::Custom(message) => {
+                Diagnostic::new(message.clone(), text_range(self.span.clone()))
+            }
+        }
+    }
+}
+
+fn describe(c: Option<char>) -> String {
+    match c {
+        Some(c) => format!("`{}`", c),
+        None => "end of input".to_string(),
+    }
+}
+
+impl chumsky::Error<char> for ParseError {
+    type Span = Span;
+    type Label = &'static str;
+
+    fn expected_input_found<Iter: IntoIterator<Item = Option<char>>>(
+        span: Span,
+        expected: Iter,
+        found: Option<char>,
+    ) -> Self {
+        let mut expected: Vec<_> = expected.into_iter().collect();
+        expected.sort_unstable();
+        expected.dedup();
+        Self {
+            span,
+            kind: ErrorKind::Unexpected { expected, found },
+        }
+    }
+
+    fn unclosed_delimiter(
+        open_span: Span,
+        open: char,
+        span: Span,
+        close: char,
+        _found: Option<char>,
+    ) -> Self {
+        Self {
+            span,
+            kind: ErrorKind::Unclosed {
+                open_span,
+                open,
+                close,
+            },
+        }
+    }
+
+    fn with_label(self, _label: &'static str) -> Self {
+        self
+    }
+
+    fn merge(mut self, other: Self) -> Self {
+        if let (
+            ErrorKind::Unexpected { expected, .. },
+            ErrorKind::Unexpected {
+                expected: other, ..
+            },
+        ) = (&mut self.kind, other.kind)
+        {
+            expected.extend(other);
+            expected.sort_unstable();
+            expected.dedup();
+        }
+        self
+    }
+}
+
+fn text_range(span: Span) -> TextRange {
+    TextRange::new(
+        TextSize::from(span.start as u32),
+        TextSize::from(span.end as u32),
+    )
 }
 
-pub fn parser() -> impl Parser<char, Json, Error = Simple<char>> {
+fn spanned<T>(node: T, span: Span) -> Spanned<T> {
+    Spanned {
+        node,
+        range: text_range(span),
+    }
+}
+
+pub fn parser() -> impl Parser<char, Spanned<Json>, Error = ParseError> {
+    // `padded` would skip any Unicode whitespace, JSON only allows these four.
+    let ws = || one_of(" \t\r\n").repeated();
+
     recursive(|value| {
         let frac = just('.').chain(text::digits(10));
 
         let exp = just('e')
             .or(just('E'))
-            .ignore_then(just('+').or(just('-')).or_not())
+            .chain(just('+').or(just('-')).or_not())
             .chain(text::digits(10));
 
         let number = just('-')
@@ -28,25 +195,35 @@ pub fn parser() -> impl Parser<char, Json, Error = Simple<char>> {
             .chain(frac.or_not().flatten())
             .chain::<char, _, _>(exp.or_not().flatten())
             .collect::<String>()
-            .from_str()
-            .unwrapped()
+            // Recovery can hand over a partial number such as `1e`.
+            .try_map(|s, span| {
+                s.parse()
+                    .map_err(|_| ParseError::custom(span, "invalid number"))
+            })
             .labelled("number");
 
-        let escape = just('\\').ignore_then(
-            just('\\')
-                .or(just('/'))
-                .or(just('"'))
-                .or(just('b').to('\x08'))
-                .or(just('f').to('\x0C'))
-                .or(just('n').to('\n'))
-                .or(just('r').to('\r'))
-                .or(just('t').to('\t')),
+        let hex = filter(|c: &char| c.is_ascii_hexdigit())
+            .repeated()
+            .exactly(4);
+        // Escapes are validated here and decoded by `unescape`, which also
+        // pairs up surrogates.
+        let escape = just('\\').chain(
+            one_of("\"\\/bfnrt")
+                .map(|c| vec![c])
+                .or(just('u').chain(hex)),
         );
 
         let string = just('"')
-            .ignore_then(filter(|c| *c != '\\' && *c != '"').or(escape).repeated())
+            .ignore_then(
+                filter(|c: &char| *c != '\\' && *c != '"' && *c >= ' ')
+                    .map(|c| vec![c])
+                    .or(escape)
+                    .repeated()
+                    .flatten(),
+            )
             .then_ignore(just('"'))
             .collect::<String>()
+            .map(|raw| unescape(&raw).into_owned())
             .labelled("string");
 
         let array = value
@@ -54,19 +231,23 @@ pub fn parser() -> impl Parser<char, Json, Error = Simple<char>> {
             .chain(just(',').ignore_then(value.clone()).repeated())
             .or_not()
             .flatten()
+            .padded_by(ws())
             .delimited_by(just('['), just(']'))
             .map(Json::Array)
             .labelled("array");
 
-        let member = string.clone().then_ignore(just(':').padded()).then(value);
+        let member = string
+            .clone()
+            .map_with_span(spanned)
+            .then_ignore(just(':').padded_by(ws()))
+            .then(value);
         let object = member
             .clone()
-            .chain(just(',').padded().ignore_then(member).repeated())
+            .chain(just(',').padded_by(ws()).ignore_then(member).repeated())
             .or_not()
             .flatten()
-            .padded()
+            .padded_by(ws())
             .delimited_by(just('{'), just('}'))
-            .collect::<HashMap<String, Json>>()
             .map(Json::Object)
             .labelled("object");
 
@@ -79,77 +260,153 @@ pub fn parser() -> impl Parser<char, Json, Error = Simple<char>> {
             .or(string.map(Json::Str))
             .or(array)
             .or(object)
-            .recover_with(nested_delimiters('{', '}', [('[', ']')], |_| Json::Invalid))
-            .recover_with(nested_delimiters('[', ']', [('{', '}')], |_| Json::Invalid))
+            .map_with_span(spanned)
+            .recover_with(nested_delimiters('{', '}', [('[', ']')], |span| {
+                spanned(Json::Invalid, span)
+            }))
+            .recover_with(nested_delimiters('[', ']', [('{', '}')], |span| {
+                spanned(Json::Invalid, span)
+            }))
             .recover_with(skip_then_retry_until(['}', ']']))
-            .padded()
+            .padded_by(ws())
     })
     .then_ignore(end().recover_with(skip_then_retry_until([])))
 }
 
-// fn main() {
-//     let src = fs::read_to_string(env::args().nth(1).expect("Expected file argument"))
-//         .expect("Failed to read file");
-
-//     let (json, errs) = parser().parse_recovery(src.trim());
-//     println!("{:#?}", json);
-//     errs.into_iter().for_each(|e| {
-//         let msg = format!(
-//             "{}{}, expected {}",
-//             if e.found().is_some() {
-//                 "Unexpected token"
-//             } else {
-//                 "Unexpected end of input"
-//             },
-//             if let Some(label) = e.label() {
-//                 format!(" while parsing {}", label)
-//             } else {
-//                 String::new()
-//             },
-//             if e.expected().len() == 0 {
-//                 "something else".to_string()
-//             } else {
-//                 e.expected()
-//                     .map(|expected| match expected {
-//                         Some(expected) => expected.to_string(),
-//                         None => "end of input".to_string(),
-//                     })
-//                     .collect::<Vec<_>>()
-//                     .join(", ")
-//             },
-//         );
-
-//         let report = Report::build(ReportKind::Error, (), e.span().start)
-//             .with_code(3)
-//             .with_message(msg)
-//             .with_label(
-//                 Label::new(e.span())
-//                     .with_message(format!(
-//                         "Unexpected {}",
-//                         e.found()
-//                             .map(|c| format!("token {}", c.fg(Color::Red)))
-//                             .unwrap_or_else(|| "end of input".to_string())
-//                     ))
-//                     .with_color(Color::Red),
-//             );
-
-//         let report = match e.reason() {
-//             chumsky::error::SimpleReason::Unclosed { span, delimiter } => report.with_label(
-//                 Label::new(span.clone())
-//                     .with_message(format!(
-//                         "Unclosed delimiter {}",
-//                         delimiter.fg(Color::Yellow)
-//                     ))
-//                     .with_color(Color::Yellow),
-//             ),
-//             chumsky::error::SimpleReason::Unexpected => report,
-//             chumsky::error::SimpleReason::Custom(msg) => report.with_label(
-//                 Label::new(e.span())
-//                     .with_message(format!("{}", msg.fg(Color::Yellow)))
-//                     .with_color(Color::Yellow),
-//             ),
-//         };
-
-//         report.finish().print(Source::from(&src)).unwrap();
-//     });
-// }
+/// Parses `input`, recovering from errors where it can. Every value that
+/// recovery replaced with [`Json::Invalid`] is covered by a diagnostic.
+pub fn parse(input: &str) -> (Option<Spanned<Json>>, Vec<Diagnostic>) {
+    let chars = input.char_indices().map(|(i, c)| (c, i..i + c.len_utf8()));
+    let stream = Stream::from_iter(input.len()..input.len(), chars);
+    let (json, errors) = parser().parse_recovery(stream);
+    let mut diagnostics: Vec<_> = errors.iter().map(ParseError::to_diagnostic).collect();
+    if let Some(json) = &json {
+        report_invalid(json, &mut diagnostics);
+    }
+    (json, diagnostics)
+}
+
+/// Reports the `Invalid` values in `json` that no diagnostic points into.
+fn report_invalid(json: &Spanned<Json>, diagnostics: &mut Vec<Diagnostic>) {
+    match &json.node {
+        Json::Invalid => {
+            let covered = diagnostics
+                .iter()
+                .any(|d| json.range.contains_range(d.range) || d.range.contains_range(json.range));
+            if !covered {
+                diagnostics.push(Diagnostic::new("invalid value", json.range));
+            }
+        }
+        Json::Array(elements) => {
+            for element in elements {
+                report_invalid(element, diagnostics);
+            }
+        }
+        Json::Object(members) => {
+            for (_, value) in members {
+                report_invalid(value, diagnostics);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+
+    use super::*;
+
+    #[test]
+    fn keeps_spans_order_and_duplicates() {
+        let (json, errors) = parse(r#"{"é": [1, "é😀"], "a": null, "é": true}"#);
+        assert!(errors.is_empty());
+        expect![[r#"
+            Some(
+                Spanned {
+                    node: Object(
+                        [
+                            (
+                                Spanned {
+                                    node: "é",
+                                    range: 1..5,
+                                },
+                                Spanned {
+                                    node: Array(
+                                        [
+                                            Spanned {
+                                                node: Num(
+                                                    1.0,
+                                                ),
+                                                range: 8..9,
+                                            },
+                                            Spanned {
+                                                node: Str(
+                                                    "é😀",
+                                                ),
+                                                range: 11..19,
+                                            },
+                                        ],
+                                    ),
+                                    range: 7..20,
+                                },
+                            ),
+                            (
+                                Spanned {
+                                    node: "a",
+                                    range: 22..25,
+                                },
+                                Spanned {
+                                    node: Null,
+                                    range: 27..31,
+                                },
+                            ),
+                            (
+                                Spanned {
+                                    node: "é",
+                                    range: 33..37,
+                                },
+                                Spanned {
+                                    node: Bool(
+                                        true,
+                                    ),
+                                    range: 39..43,
+                                },
+                            ),
+                        ],
+                    ),
+                    range: 0..44,
+                },
+            )
+        "#]]
+        .assert_debug_eq(&json);
+    }
+
+    #[test]
+    fn reports_recovered_values() {
+        let (json, errors) = parse("[1, [2 3], 4]");
+        let elements = match json.unwrap().node {
+            Json::Array(elements) => elements,
+            json => panic!("{:?}", json),
+        };
+        assert_eq!(
+            elements[1],
+            Spanned {
+                node: Json::Invalid,
+                range: TextRange::new(4.into(), 9.into()),
+            }
+        );
+        expect![[r#"
+            [
+                Diagnostic {
+                    severity: Error,
+                    message: "unexpected `3`, expected `,` or `]`",
+                    range: 7..8,
+                    labels: [],
+                    fix: [],
+                },
+            ]
+        "#]]
+        .assert_debug_eq(&errors);
+    }
+}
@@ -0,0 +1,491 @@
+//! The compact format of the `compress-json` npm package (version 2).
+//!
+//! A compressed document is the array `[values, root]`. `values` is a
+//! table of strings, each stored once, and every key into it is the
+//! value's index written in base 62 (`0-9A-Za-z`). A value is spelled as:
+//!
+//! - `""` as a key for `null`, and `_` for `null` inside an array;
+//! - `b|T` and `b|F` for booleans;
+//! - `n|` and the number in base 62, with `.` separating the integer part,
+//!   the fraction digits reversed (so leading zeros survive) and the
+//!   exponent, and `:` marking an integer too big for a JavaScript number;
+//! - `a|k1|k2` for an array of the values at keys `k1` and `k2`, `a|` when
+//!   it is empty;
+//! - `o|s|k1|k2` for an object whose key names are the array at `s` and
+//!   whose values are at `k1` and `k2`, `o|` when it is empty;
+//! - the string itself, with `s|` in front if it starts with one of the
+//!   prefixes above.
+//!
+//! `compress-json` runs on the objects `JSON.parse` returns, so [`compress`]
+//! reads objects the same way: the last of duplicate keys wins, and keys
+//! that are array indices come first in ascending order.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::printer::{print, Style};
+use crate::value::{Number, Value};
+
+const DIGITS: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// The largest integer a JavaScript number holds exactly.
+const MAX_SAFE_INTEGER: u128 = (1 << 53) - 1;
+
+/// Compresses `value` into `[values, root]`.
+pub fn compress(value: &Value) -> Value {
+    let mut memory = Memory::default();
+    let root = memory.add(value);
+    Value::Array(vec![
+        Value::Array(memory.values.into_iter().map(Value::String).collect()),
+        Value::String(root),
+    ])
+}
+
+/// Decompresses a `[values, root]` pair produced by [`compress`] or by
+/// `compress-json`. Payloads nested too deeply or that expand to more than
+/// a million values are rejected.
+pub fn decompress(compressed: &Value) -> Result<Value, DecodeError> {
+    let (values, root) = match compressed {
+        Value::Array(pair) => match pair.as_slice() {
+            [Value::Array(values), Value::String(root)] => (values, root),
+            _ => return Err(DecodeError::new("expected `[values, root]`")),
+        },
+        _ => return Err(DecodeError::new("expected `[values, root]`")),
+    };
+    Decoder {
+        values,
+        decoded: 0,
+        decoded_bytes: 0,
+    }
+    .decode(root, 0)
+}
+
+/// Why a compressed document could not be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    pub message: String,
+}
+
+impl DecodeError {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+#[derive(Default)]
+struct Memory {
+    values: Vec<String>,
+    keys: HashMap<String, String>,
+}
+
+impl Memory {
+    fn add(&mut self, value: &Value) -> String {
+        let encoded = match value {
+            Value::Null => return String::new(),
+            // JSON has no infinities or NaN, and `JSON.stringify` writes them
+            // as `null`.
+            Value::Number(n) if !n.as_f64().is_finite() => return String::new(),
+            Value::Bool(true) => "b|T".to_string(),
+            Value::Bool(false) => "b|F".to_string(),
+            Value::Number(n) => format!("n|{}", encode_number(n)),
+            Value::String(s) => encode_string(s),
+            Value::Array(elements) => {
+                let mut encoded = "a".to_string();
+                for element in elements {
+                    encoded.push('|');
+                    match self.add(element) {
+                        key if key.is_empty() => encoded.push('_'),
+                        key => encoded.push_str(&key),
+                    }
+                }
+                if elements.is_empty() {
+                    encoded.push('|');
+                }
+                encoded
+            }
+            Value::Object(members) if members.is_empty() => "o|".to_string(),
+            Value::Object(members) => {
+                let members = js_members(members);
+                let names = members
+                    .iter()
+                    .map(|(name, _)| Value::String(name.to_string()))
+                    .collect();
+                let mut encoded = format!("o|{}", self.add(&Value::Array(names)));
+                for (_, value) in members {
+                    encoded.push('|');
+                    encoded.push_str(&self.add(value));
+                }
+                encoded
+            }
+        };
+        self.key(encoded)
+    }
+
+    /// The key of `encoded`, adding it to the table if it is new.
+    fn key(&mut self, encoded: String) -> String {
+        if let Some(key) = self.keys.get(&encoded) {
+            return key.clone();
+        }
+        let key = to_base62(self.values.len() as u128);
+        self.values.push(encoded.clone());
+        self.keys.insert(encoded, key.clone());
+        key
+    }
+}
+
+/// The members of an object in the order `Object.keys` lists them after
+/// `JSON.parse`.
+fn js_members(members: &[(String, Value)]) -> Vec<(&str, &Value)> {
+    let mut unique: Vec<(&str, &Value)> = vec![];
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    for (name, value) in members {
+        match positions.get(name.as_str()) {
+            Some(&i) => unique[i].1 = value,
+            None => {
+                positions.insert(name.as_str(), unique.len());
+                unique.push((name, value));
+            }
+        }
+    }
+    // A stable sort keeps the other keys in insertion order.
+    unique.sort_by_key(|(name, _)| array_index(name).unwrap_or(u64::MAX));
+    unique
+}
+
+/// The index `name` spells, if it is one: a canonical integer below
+/// 2^32 - 1.
+fn array_index(name: &str) -> Option<u64> {
+    let index: u64 = name.parse().ok()?;
+    (index < u32::MAX as u64 && index.to_string() == name).then_some(index)
+}
+
+fn encode_string(s: &str) -> String {
+    match s.get(..2) {
+        Some("b|" | "o|" | "n|" | "a|" | "s|") => format!("s|{}", s),
+        _ => s.to_string(),
+    }
+}
+
+fn encode_number(n: &Number) -> String {
+    let text = match n {
+        Number::PosInt(n) => n.to_string(),
+        Number::NegInt(n) => n.to_string(),
+        // The way JavaScript spells it, e.g. `1.5e-7`.
+        Number::Float(_) | Number::Large(_) => print(&Value::Number(n.clone()), Style::Canonical),
+    };
+    let (sign, text) = match text.strip_prefix('-') {
+        Some(text) => ("-", text),
+        None => ("", text.as_str()),
+    };
+    let (mantissa, exponent) = match text.split_once('e') {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (text, None),
+    };
+    let (int, fraction) = match mantissa.split_once('.') {
+        Some((int, fraction)) => (int, Some(fraction)),
+        None if exponent.is_some() => (mantissa, Some("0")),
+        None => return format!("{}{}", sign, encode_digits(mantissa)),
+    };
+    let fraction: String = fraction.unwrap().chars().rev().collect();
+    let mut encoded = format!(
+        "{}{}.{}",
+        sign,
+        encode_digits(int),
+        encode_digits(&fraction)
+    );
+    if let Some(exponent) = exponent {
+        encoded.push('.');
+        let digits = match exponent.strip_prefix('-') {
+            Some(digits) => {
+                encoded.push('-');
+                digits
+            }
+            None => exponent.trim_start_matches('+'),
+        };
+        encoded.push_str(&encode_digits(digits));
+    }
+    encoded
+}
+
+/// Base 62 for a string of decimal digits, with a `:` in front if it does
+/// not fit a JavaScript number exactly.
+fn encode_digits(digits: &str) -> String {
+    let n: u128 = digits.parse().unwrap_or(0);
+    if n <= MAX_SAFE_INTEGER && n.to_string() == digits {
+        to_base62(n)
+    } else {
+        format!(":{}", to_base62(n))
+    }
+}
+
+fn to_base62(mut n: u128) -> String {
+    let mut digits = vec![];
+    loop {
+        digits.push(DIGITS[(n % 62) as usize]);
+        n /= 62;
+        if n == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap()
+}
+
+fn from_base62(s: &str) -> Option<u128> {
+    if s.is_empty() {
+        return None;
+    }
+    s.bytes().try_fold(0u128, |n, byte| {
+        let digit = DIGITS.iter().position(|&d| d == byte)? as u128;
+        n.checked_mul(62)?.checked_add(digit)
+    })
+}
+
+/// Nesting deeper than this is reported instead of recursed into.
+const MAX_DEPTH: usize = 128;
+
+/// How many values a payload may decode to in total. Keys can be shared,
+/// so a few entries that each refer to the one before twice would
+/// otherwise expand to an exponentially large value.
+const MAX_DECODED_VALUES: usize = 1_000_000;
+
+/// How many bytes of strings a payload may decode to in total, since a
+/// long string shared by many entries expands without adding values.
+const MAX_DECODED_BYTES: usize = 64 << 20;
+
+struct Decoder<'v> {
+    values: &'v [Value],
+    /// How many values have been decoded so far.
+    decoded: usize,
+    /// How many bytes of strings have been decoded so far.
+    decoded_bytes: usize,
+}
+
+impl Decoder<'_> {
+    fn decode(&mut self, key: &str, depth: usize) -> Result<Value, DecodeError> {
+        self.decoded += 1;
+        if self.decoded > MAX_DECODED_VALUES {
+            let message = format!("values expand to more than {} values", MAX_DECODED_VALUES);
+            return Err(DecodeError::new(message));
+        }
+        if key.is_empty() || key == "_" {
+            return Ok(Value::Null);
+        }
+        if depth > MAX_DEPTH {
+            return Err(DecodeError::new("values are nested too deeply"));
+        }
+        let index = from_base62(key)
+            .and_then(|index| usize::try_from(index).ok())
+            .ok_or_else(|| DecodeError::new(format!("invalid key `{}`", key)))?;
+        let encoded = match self.values.get(index) {
+            Some(Value::String(encoded)) => encoded,
+            // Older versions stored numbers and `null` as themselves.
+            Some(value @ (Value::Number(_) | Value::Null)) => return Ok(value.clone()),
+            Some(_) => return Err(DecodeError::new(format!("invalid value at `{}`", key))),
+            None => return Err(DecodeError::new(format!("no value at `{}`", key))),
+        };
+        let (prefix, rest) = match encoded.get(..2) {
+            Some(prefix) => (prefix, &encoded[2..]),
+            None => return self.string(encoded),
+        };
+        match prefix {
+            "b|" => match rest {
+                "T" => Ok(Value::Bool(true)),
+                "F" => Ok(Value::Bool(false)),
+                _ => Err(DecodeError::new(format!("invalid boolean `{}`", encoded))),
+            },
+            "n|" => decode_number(rest)
+                .map(Value::Number)
+                .ok_or_else(|| DecodeError::new(format!("invalid number `{}`", encoded))),
+            "s|" => self.string(rest),
+            "a|" if rest.is_empty() => Ok(Value::Array(vec![])),
+            "a|" => rest
+                .split('|')
+                .map(|key| self.decode(key, depth + 1))
+                .collect::<Result<_, _>>()
+                .map(Value::Array),
+            "o|" if rest.is_empty() => Ok(Value::Object(vec![])),
+            "o|" => {
+                let mut keys = rest.split('|');
+                let names = match self.decode(keys.next().unwrap(), depth + 1)? {
+                    Value::Array(names) => names,
+                    // Older versions stored a single name as itself.
+                    name @ Value::String(_) => vec![name],
+                    _ => return Err(DecodeError::new(format!("invalid keys in `{}`", encoded))),
+                };
+                let keys: Vec<_> = keys.collect();
+                if names.len() != keys.len() {
+                    let message = format!("{} keys for {} values", names.len(), keys.len());
+                    return Err(DecodeError::new(message));
+                }
+                names
+                    .into_iter()
+                    .zip(keys)
+                    .map(|(name, key)| match name {
+                        Value::String(name) => Ok((name, self.decode(key, depth + 1)?)),
+                        _ => Err(DecodeError::new(format!("invalid keys in `{}`", encoded))),
+                    })
+                    .collect::<Result<_, _>>()
+                    .map(Value::Object)
+            }
+            _ => self.string(encoded),
+        }
+    }
+
+    fn string(&mut self, text: &str) -> Result<Value, DecodeError> {
+        self.decoded_bytes += text.len();
+        if self.decoded_bytes > MAX_DECODED_BYTES {
+            let message = format!("strings expand to more than {} bytes", MAX_DECODED_BYTES);
+            return Err(DecodeError::new(message));
+        }
+        Ok(Value::String(text.to_string()))
+    }
+}
+
+fn decode_number(encoded: &str) -> Option<Number> {
+    let (sign, encoded) = match encoded.strip_prefix('-') {
+        Some(encoded) => ("-", encoded),
+        None => ("", encoded),
+    };
+    let mut parts = encoded.splitn(3, '.');
+    let mut text = format!("{}{}", sign, decode_digits(parts.next()?)?);
+    if let Some(fraction) = parts.next() {
+        let fraction: String = decode_digits(fraction)?.chars().rev().collect();
+        text.push('.');
+        text.push_str(&fraction);
+    }
+    if let Some(exponent) = parts.next() {
+        text.push('e');
+        let digits = match exponent.strip_prefix('-') {
+            Some(digits) => {
+                text.push('-');
+                digits
+            }
+            None => exponent,
+        };
+        text.push_str(&decode_digits(digits)?);
+    }
+    Some(Number::from_text(&text))
+}
+
+fn decode_digits(encoded: &str) -> Option<String> {
+    let encoded = encoded.strip_prefix(':').unwrap_or(encoded);
+    from_base62(encoded).map(|n| n.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::backend::syntax_to_value;
+    use crate::parser::Parser;
+    use crate::strategies::value;
+
+    fn parse(text: &str) -> Value {
+        let parse = Parser::new(text).parse();
+        assert!(parse.ok(), "{:?}", parse.errors);
+        syntax_to_value(&parse.syntax()).unwrap()
+    }
+
+    /// The payload `compress-json` produced for `assets/big.json`, without
+    /// the timing line after it.
+    fn big_payload() -> &'static str {
+        include_str!("../test.txt").lines().next().unwrap()
+    }
+
+    /// `value` as `JSON.parse` would hand it to `compress-json`.
+    fn normalize(value: &Value) -> Value {
+        match value {
+            Value::Number(n) if !n.as_f64().is_finite() => Value::Null,
+            Value::Array(elements) => Value::Array(elements.iter().map(normalize).collect()),
+            Value::Object(members) => Value::Object(
+                js_members(members)
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), normalize(value)))
+                    .collect(),
+            ),
+            value => value.clone(),
+        }
+    }
+
+    #[test]
+    fn matches_compress_json_on_big_json() {
+        let big = parse(include_str!("../assets/big.json"));
+        let payload = parse(big_payload());
+        assert_eq!(compress(&big).to_string(), big_payload());
+        assert!(decompress(&payload).unwrap() == big);
+    }
+
+    #[test]
+    fn encodes_each_kind_of_value() {
+        let value = parse(
+            r#"{"b": [true, false, null, "a|b", "", {}, []],
+                "2": 2, "1": -1.5, "n": [0.001, 1e21, 12345678901234567890],
+                "2": "two"}"#,
+        );
+        let compressed = compress(&value);
+        expect![[r#"[["1","2","b","n","a|0|1|2|3","n|-1.5","two","b|T","b|F","s|a|b","","o|","a|","a|7|8|_|9|A|B|C","n|0.1c","n|1.0.L","n|:EhzL6HwZ5ow","a|E|F|G","o|4|5|6|D|H"],"I"]"#]]
+            .assert_eq(&compressed.to_string());
+        assert_eq!(decompress(&compressed).unwrap(), normalize(&value));
+    }
+
+    #[test]
+    fn rejects_malformed_payloads() {
+        let check = |text: &str| decompress(&parse(text)).unwrap_err().to_string();
+        assert_eq!(check("[]"), "expected `[values, root]`");
+        assert_eq!(check(r#"[["a|1"], "0"]"#), "no value at `1`");
+        assert_eq!(check(r#"[["n|?"], "0"]"#), "invalid number `n|?`");
+        assert_eq!(check(r#"[["a|0"], "0"]"#), "values are nested too deeply");
+        assert_eq!(
+            check(r#"[["o|1|2|2", "a|2", "s"], "0"]"#),
+            "1 keys for 2 values"
+        );
+    }
+
+    #[test]
+    fn limits_expansion() {
+        // Each entry is an array of the next one twice, so the root expands
+        // to 2^40 values.
+        let mut values: Vec<_> = (1..=40)
+            .map(|next| Value::String(format!("a|{0}|{0}", to_base62(next))))
+            .collect();
+        values.push(Value::String("n|1".to_string()));
+        let payload = Value::Array(vec![Value::Array(values), Value::String("0".to_string())]);
+        let err = decompress(&payload).unwrap_err();
+        assert_eq!(err.message, "values expand to more than 1000000 values");
+
+        // A 4 KB string repeated through two levels of 900 references
+        // stays under the value limit but expands to gigabytes.
+        let refs = |key: &str| format!("a|{}", vec![key; 900].join("|"));
+        let values = vec![
+            Value::String(refs("1")),
+            Value::String(refs("2")),
+            Value::String(format!("s|{}", "x".repeat(4096))),
+        ];
+        let payload = Value::Array(vec![Value::Array(values), Value::String("0".to_string())]);
+        let err = decompress(&payload).unwrap_err();
+        assert_eq!(err.message, "strings expand to more than 67108864 bytes");
+    }
+
+    proptest! {
+        #[test]
+        fn round_trips(value in value()) {
+            let compressed = compress(&value);
+            prop_assert_eq!(decompress(&compressed).unwrap(), normalize(&value));
+            let reparsed = parse(&compressed.to_string());
+            prop_assert_eq!(compress(&decompress(&reparsed).unwrap()), compressed);
+        }
+    }
+}
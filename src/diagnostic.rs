@@ -0,0 +1,64 @@
+use rowan::TextRange;
+
+use crate::edit::TextEdit;
+
+/// A problem found in the input, anchored to the byte range it refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub range: TextRange,
+    /// Other ranges that help explain the problem, such as where an unclosed
+    /// bracket was opened.
+    pub labels: Vec<Label>,
+    /// Edits that fix the problem, when there is an obvious fix such as
+    /// inserting a missing comma.
+    pub fix: Vec<TextEdit>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The input is not valid JSON, or breaks a rule the caller asked to
+    /// enforce.
+    #[default]
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub message: String,
+    pub range: TextRange,
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>, range: TextRange) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            range,
+            labels: vec![],
+            fix: vec![],
+        }
+    }
+
+    pub fn warning(message: impl Into<String>, range: TextRange) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::new(message, range)
+        }
+    }
+
+    pub fn with_label(mut self, message: impl Into<String>, range: TextRange) -> Self {
+        self.labels.push(Label {
+            message: message.into(),
+            range,
+        });
+        self
+    }
+
+    pub fn with_fix(mut self, fix: Vec<TextEdit>) -> Self {
+        self.fix = fix;
+        self
+    }
+}
@@ -0,0 +1,131 @@
+//! Differential checks across every backend.
+//!
+//! [`check`] is what the `cargo fuzz` targets in `fuzz/` run on each input,
+//! and what the tests below run on the checked-in corpus, so a crash found
+//! by the fuzzer can be turned into a regression test by dropping the input
+//! into `fuzz/corpus/differential`.
+
+use crate::backend::{self, JsonBackend, Rowan};
+use crate::parser::Parser;
+
+/// Nesting deeper than this is skipped: the chumsky and nom backends recurse
+/// once per level without a [depth limit](crate::options::ParseOptions), and
+/// the fuzzer would find nothing but stack overflows.
+pub const MAX_DEPTH: usize = 64;
+
+/// Runs every backend on `input` and panics if any of them panics, if they
+/// disagree on whether the input is valid or on its decoded value, or if the
+/// rowan tree does not reproduce the input.
+pub fn check(input: &str) {
+    if depth(input) > MAX_DEPTH {
+        return;
+    }
+    let parse = Parser::new(input).parse();
+    assert_eq!(
+        parse.syntax().to_string(),
+        input,
+        "the tree is not lossless"
+    );
+
+    let expected = JsonBackend::parse_value(&Rowan, input);
+    for backend in backend::all() {
+        let actual = backend.parse_value(input);
+        match (&expected, &actual) {
+            (Ok(expected), Ok(actual)) => assert_eq!(
+                expected,
+                actual,
+                "{} decodes {:?} differently",
+                backend.name(),
+                input
+            ),
+            (Err(_), Err(_)) => {}
+            (Ok(_), Err(errors)) => {
+                panic!("{} rejects {:?} with {:?}", backend.name(), input, errors)
+            }
+            (Err(_), Ok(_)) => panic!("{} accepts {:?}", backend.name(), input),
+        }
+    }
+}
+
+/// The deepest bracket nesting in `input`, counting brackets inside strings
+/// too since that is cheaper and only makes the bound stricter.
+fn depth(input: &str) -> usize {
+    let mut depth = 0usize;
+    let mut max = 0;
+    for byte in input.bytes() {
+        match byte {
+            b'[' | b'{' => {
+                depth += 1;
+                max = max.max(depth);
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    max
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// Every input in the fuzz corpus, plus the small assets.
+    fn corpus() -> Vec<String> {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fuzz/corpus/differential");
+        let mut entries: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        entries.sort();
+        let mut inputs: Vec<String> = entries
+            .iter()
+            .filter_map(|path| String::from_utf8(fs::read(path).unwrap()).ok())
+            .collect();
+        inputs.push(include_str!("../assets/test.json").to_string());
+        inputs.push(include_str!("../assets/array.json").to_string());
+        inputs
+    }
+
+    #[test]
+    fn corpus_agrees() {
+        for input in corpus() {
+            check(&input);
+        }
+    }
+
+    /// A fixed-seed stand-in for the fuzzer: truncates, deletes and inserts
+    /// bytes in every corpus input, so the common kinds of malformed input
+    /// are covered without `cargo fuzz`.
+    #[test]
+    fn mutated_corpus_agrees() {
+        const ALPHABET: &[u8] = b"\x0c\x01\t\rAFx{}[]:,\"\\ \n0123456789-+.eEtrufalsn/u";
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut random = move |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize % bound.max(1)
+        };
+        for input in corpus() {
+            let input = &input.as_bytes()[..input.len().min(512)];
+            for _ in 0..200 {
+                let mut bytes = input.to_vec();
+                for _ in 0..=random(3) {
+                    let at = random(bytes.len() + 1);
+                    match random(3) {
+                        0 => bytes.truncate(at),
+                        1 if at < bytes.len() => {
+                            bytes.remove(at);
+                        }
+                        _ => bytes.insert(at, ALPHABET[random(ALPHABET.len())]),
+                    }
+                }
+                if let Ok(input) = String::from_utf8(bytes) {
+                    check(&input);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,304 @@
+//! On-demand access to a document.
+//!
+//! [`Document::parse`] runs the [`EventParser`] over the whole input, so it
+//! accepts and rejects exactly what a full parse does, but keeps nothing
+//! except the input itself. Accessors then re-lex only the tokens on the
+//! path they follow and jump over every other container with a byte-level
+//! bracket match, so reading a few fields of a large document never
+//! materializes the rest of it.
+
+use std::borrow::Cow;
+
+use crate::diagnostic::Diagnostic;
+use crate::escape::unescape;
+use crate::event::EventParser;
+use crate::lexer::{Lexer, SyntaxKind};
+use crate::options::{Dialect, ParseOptions};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Document<'a> {
+    input: &'a str,
+    root: usize,
+}
+
+impl<'a> Document<'a> {
+    pub fn parse(input: &'a str) -> Result<Self, Diagnostic> {
+        Self::parse_with(input, ParseOptions::default())
+    }
+
+    /// Like [`Document::parse`], with other limits. The accessors skip
+    /// whitespace byte by byte, so the input is always read as strict
+    /// [`Dialect::Json`] whatever `options` says.
+    pub fn parse_with(input: &'a str, options: ParseOptions) -> Result<Self, Diagnostic> {
+        let options = ParseOptions {
+            dialect: Dialect::Json,
+            ..options
+        };
+        for event in EventParser::with_options(input, options) {
+            event?;
+        }
+        Ok(Self {
+            input,
+            root: skip_whitespace(input.as_bytes(), 0),
+        })
+    }
+
+    pub fn root(&self) -> Value<'a> {
+        Value {
+            input: self.input,
+            start: self.root,
+        }
+    }
+
+    /// Shorthand for `self.root().get(index)`.
+    pub fn get(&self, index: impl Index) -> Option<Value<'a>> {
+        self.root().get(index)
+    }
+}
+
+/// A value of a validated [`Document`], identified by its start offset.
+#[derive(Debug, Clone, Copy)]
+pub struct Value<'a> {
+    input: &'a str,
+    start: usize,
+}
+
+/// Something a [`Value`] can be indexed by: a member name or an array index.
+pub trait Index {
+    fn index_into<'a>(&self, value: &Value<'a>) -> Option<Value<'a>>;
+}
+
+impl Index for str {
+    fn index_into<'a>(&self, value: &Value<'a>) -> Option<Value<'a>> {
+        value
+            .members()
+            .find(|(key, _)| key == self)
+            .map(|(_, value)| value)
+    }
+}
+
+impl Index for usize {
+    fn index_into<'a>(&self, value: &Value<'a>) -> Option<Value<'a>> {
+        value.elements().nth(*self)
+    }
+}
+
+impl<T: Index + ?Sized> Index for &T {
+    fn index_into<'a>(&self, value: &Value<'a>) -> Option<Value<'a>> {
+        (**self).index_into(value)
+    }
+}
+
+impl<'a> Value<'a> {
+    /// Looks up an object member by name or an array element by position.
+    pub fn get(&self, index: impl Index) -> Option<Value<'a>> {
+        index.index_into(self)
+    }
+
+    pub fn is_object(&self) -> bool {
+        self.first_byte() == b'{'
+    }
+
+    pub fn is_array(&self) -> bool {
+        self.first_byte() == b'['
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.token() == (SyntaxKind::Null, "null")
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.token().0 {
+            SyntaxKind::True => Some(true),
+            SyntaxKind::False => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.number()?.parse().ok()
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.number()?.parse().ok()
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.number()?.parse().ok()
+    }
+
+    /// The unescaped value of a string.
+    pub fn as_str(&self) -> Option<Cow<'a, str>> {
+        match self.token() {
+            (SyntaxKind::String, text) => Some(unescape(&text[1..text.len() - 1])),
+            _ => None,
+        }
+    }
+
+    /// The text of the value as written in the input.
+    pub fn raw(&self) -> &'a str {
+        &self.input[self.start..value_end(self.input, self.start)]
+    }
+
+    /// Iterates over the members of an object, skipping over their values.
+    pub fn members(&self) -> impl Iterator<Item = (Cow<'a, str>, Value<'a>)> {
+        let input = self.input;
+        let mut items = self.items(b'{');
+        std::iter::from_fn(move || {
+            let key_start = items.next()?;
+            let (_, key) = token_at(input, key_start);
+            let colon = skip_whitespace(input.as_bytes(), key_start + key.len());
+            let start = skip_whitespace(input.as_bytes(), colon + 1);
+            Some((unescape(&key[1..key.len() - 1]), Value { input, start }))
+        })
+    }
+
+    /// Iterates over the elements of an array, skipping over their contents.
+    pub fn elements(&self) -> impl Iterator<Item = Value<'a>> {
+        let input = self.input;
+        self.items(b'[').map(move |start| Value { input, start })
+    }
+
+    /// Yields the start offset of every item of the container opened by
+    /// `open`, or nothing if this value is something else.
+    fn items(&self, open: u8) -> impl Iterator<Item = usize> + 'a {
+        let input = self.input;
+        let bytes = input.as_bytes();
+        let mut next = (self.first_byte() == open).then(|| skip_whitespace(bytes, self.start + 1));
+        std::iter::from_fn(move || {
+            let start = next.take()?;
+            if matches!(bytes[start], b']' | b'}') {
+                return None;
+            }
+            // For members this skips the key, then the colon and the value.
+            let mut end = value_end(input, start);
+            if open == b'{' {
+                let colon = skip_whitespace(bytes, end);
+                end = value_end(input, skip_whitespace(bytes, colon + 1));
+            }
+            let after = skip_whitespace(bytes, end);
+            if bytes[after] == b',' {
+                next = Some(skip_whitespace(bytes, after + 1));
+            }
+            Some(start)
+        })
+    }
+
+    fn number(&self) -> Option<&'a str> {
+        match self.token() {
+            (SyntaxKind::Number, text) => Some(text),
+            _ => None,
+        }
+    }
+
+    fn token(&self) -> (SyntaxKind, &'a str) {
+        token_at(self.input, self.start)
+    }
+
+    fn first_byte(&self) -> u8 {
+        self.input.as_bytes()[self.start]
+    }
+}
+
+fn token_at(input: &str, start: usize) -> (SyntaxKind, &str) {
+    Lexer::new(&input[start..]).next().unwrap()
+}
+
+/// Returns the end of the value starting at `start`. Containers are matched
+/// bracket by bracket without lexing their contents.
+fn value_end(input: &str, start: usize) -> usize {
+    let bytes = input.as_bytes();
+    if !matches!(bytes[start], b'[' | b'{') {
+        return start + token_at(input, start).1.len();
+    }
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, &byte) in bytes.iter().enumerate().skip(start) {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    unreachable!("documents are validated before they are accessed")
+}
+
+fn skip_whitespace(bytes: &[u8], mut pos: usize) -> usize {
+    while pos < bytes.len() && matches!(bytes[pos], b' ' | b'\t' | b'\r' | b'\n') {
+        pos += 1;
+    }
+    pos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_nested_fields() {
+        let doc = Document::parse(r#" {"a": [0, {"x": "}]"}, 2, 3], "b\n": -1.5e2, "c": null} "#)
+            .unwrap();
+        assert_eq!(
+            doc.get("a").and_then(|a| a.get(3)).and_then(|v| v.as_u64()),
+            Some(3)
+        );
+        assert_eq!(
+            doc.get("a")
+                .unwrap()
+                .get(1)
+                .unwrap()
+                .get("x")
+                .unwrap()
+                .as_str()
+                .unwrap(),
+            "}]"
+        );
+        assert_eq!(doc.get("b\n").unwrap().as_f64(), Some(-150.0));
+        assert_eq!(
+            doc.get("a").unwrap().get(1).unwrap().raw(),
+            r#"{"x": "}]"}"#
+        );
+        assert!(doc.get("c").unwrap().is_null());
+        assert!(doc.get("d").is_none());
+        assert!(doc.get("a").unwrap().get(4).is_none());
+        assert!(doc.get(0).is_none());
+        let keys: Vec<_> = doc.root().members().map(|(key, _)| key).collect();
+        assert_eq!(keys, ["a", "b\n", "c"]);
+    }
+
+    #[test]
+    fn reads_big_json() {
+        let doc = Document::parse(include_str!("../assets/big.json")).unwrap();
+        let names = doc.get("areaNames").unwrap();
+        assert_eq!(
+            names.get("205705994").unwrap().as_str().unwrap(),
+            "1er balcon central"
+        );
+        assert_eq!(doc.get("performances").unwrap().elements().count(), 243);
+        assert_eq!(doc.get("blockNames").unwrap().members().count(), 0);
+    }
+
+    #[test]
+    fn validates_like_a_full_parse() {
+        for input in ["", "[1,]", r#"{"a" 1}"#, "[1] 2", "[[]", "\"\\x\""] {
+            assert!(Document::parse(input).is_err(), "{}", input);
+            assert!(!crate::parser::Parser::new(input).parse().ok(), "{}", input);
+        }
+    }
+}
@@ -0,0 +1,238 @@
+//! What to do about an object that has the same key more than once.
+//!
+//! RFC 8259 only says such names "SHOULD be unique", and parsers disagree
+//! on what a duplicate means. Every backend keeps all members, so the
+//! policy is applied afterwards: [`check`] reports duplicates on the tree,
+//! where both occurrences have a range, and [`resolve`] drops members from a
+//! decoded [`Value`]. Keys are compared after unescaping, so `"a"` and
+//! `"\u0061"` are the same key.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::diagnostic::Diagnostic;
+use crate::escape::unescape;
+use crate::lexer::SyntaxKind;
+use crate::syntax::{SyntaxNode, SyntaxToken};
+use crate::value::Value;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Reject the document.
+    Error,
+    /// Keep every member, but report a warning.
+    Warn,
+    /// Keep the first member with a key.
+    FirstWins,
+    /// Keep the value of the last member with a key, at the position of the
+    /// first, like `JSON.parse` does.
+    LastWins,
+    /// Keep every member silently.
+    #[default]
+    KeepAll,
+}
+
+impl DuplicateKeys {
+    /// Whether duplicates are reported as diagnostics under this policy.
+    pub fn reports(self) -> bool {
+        matches!(self, DuplicateKeys::Error | DuplicateKeys::Warn)
+    }
+}
+
+/// Reports every repeated key in the objects under `root`, pointing at the
+/// repetition and at the first occurrence. Reports nothing unless `policy`
+/// [reports](DuplicateKeys::reports) duplicates.
+pub fn check(root: &SyntaxNode, policy: DuplicateKeys) -> Vec<Diagnostic> {
+    if !policy.reports() {
+        return vec![];
+    }
+    let mut diagnostics = vec![];
+    for object in root
+        .descendants()
+        .filter(|node| node.kind() == SyntaxKind::Object)
+    {
+        let mut seen = HashMap::new();
+        for key in object_keys(&object) {
+            let text = key.text();
+            let name = unescape(&text[1..text.len() - 1]).into_owned();
+            match seen.get(&name) {
+                Some(first) => {
+                    let message = format!("duplicate key `{}`", name);
+                    let diagnostic = match policy {
+                        DuplicateKeys::Warn => Diagnostic::warning(message, key.text_range()),
+                        _ => Diagnostic::new(message, key.text_range()),
+                    };
+                    diagnostics.push(diagnostic.with_label("first defined here", *first));
+                }
+                None => {
+                    seen.insert(name, key.text_range());
+                }
+            }
+        }
+    }
+    diagnostics.sort_by_key(|diagnostic| diagnostic.range.start());
+    diagnostics
+}
+
+/// The key tokens of an object: the strings that follow its `{` or a `,`.
+pub fn object_keys(object: &SyntaxNode) -> impl Iterator<Item = SyntaxToken> {
+    let mut after_separator = false;
+    object
+        .children_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !token.kind().is_trivia())
+        .filter_map(move |token| {
+            let is_key = after_separator && token.kind() == SyntaxKind::String;
+            after_separator = matches!(token.kind(), SyntaxKind::LeftBrace | SyntaxKind::Comma);
+            is_key.then_some(token)
+        })
+}
+
+/// Whether any object in `value` repeats a key.
+pub fn has_duplicates(value: &Value) -> bool {
+    match value {
+        Value::Object(members) => {
+            let mut seen = HashSet::with_capacity(members.len());
+            members.iter().any(|(key, _)| !seen.insert(key.as_str()))
+                || members.iter().any(|(_, value)| has_duplicates(value))
+        }
+        Value::Array(elements) => elements.iter().any(has_duplicates),
+        _ => false,
+    }
+}
+
+/// Drops the members `policy` discards from every object in `value`.
+pub fn resolve(value: &mut Value, policy: DuplicateKeys) {
+    match value {
+        Value::Object(members) => {
+            if matches!(policy, DuplicateKeys::FirstWins | DuplicateKeys::LastWins) {
+                let mut kept: Vec<(String, Value)> = Vec::with_capacity(members.len());
+                // The position in `kept` of the member with each key.
+                let mut positions: HashMap<String, usize> = HashMap::with_capacity(members.len());
+                for (key, value) in members.drain(..) {
+                    match positions.get(&key) {
+                        Some(&i) if policy == DuplicateKeys::LastWins => kept[i].1 = value,
+                        Some(_) => {}
+                        None => {
+                            positions.insert(key.clone(), kept.len());
+                            kept.push((key, value));
+                        }
+                    }
+                }
+                *members = kept;
+            }
+            for (_, value) in members {
+                resolve(value, policy);
+            }
+        }
+        Value::Array(elements) => {
+            for element in elements {
+                resolve(element, policy);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+
+    use super::*;
+    use crate::backend;
+    use crate::diagnostic::Severity;
+    use crate::parser::Parser;
+    use crate::render::render_diagnostics;
+
+    const INPUT: &str = r#"{"a": 1, "b": {"x": [], "x": {}}, "\u0061": 2, "a": 3}"#;
+
+    #[test]
+    fn reports_both_occurrences() {
+        let root = Parser::new(INPUT).parse().syntax();
+        assert!(check(&root, DuplicateKeys::KeepAll).is_empty());
+        expect![[r#"
+            warning: duplicate key `x`
+             --> 1:25
+              |
+            1 | {"a": 1, "b": {"x": [], "x": {}}, "\u0061": 2, "a": 3}
+              |                         ^^^
+              |                --- first defined here
+
+            warning: duplicate key `a`
+             --> 1:35
+              |
+            1 | {"a": 1, "b": {"x": [], "x": {}}, "\u0061": 2, "a": 3}
+              |                                   ^^^^^^^^
+              |  --- first defined here
+
+            warning: duplicate key `a`
+             --> 1:48
+              |
+            1 | {"a": 1, "b": {"x": [], "x": {}}, "\u0061": 2, "a": 3}
+              |                                                ^^^
+              |  --- first defined here
+        "#]]
+        .assert_eq(&render_diagnostics(
+            INPUT,
+            &check(&root, DuplicateKeys::Warn),
+        ));
+        assert!(check(&root, DuplicateKeys::Error)
+            .iter()
+            .all(|d| d.severity == Severity::Error));
+    }
+
+    #[test]
+    fn many_keys_are_resolved_in_linear_time() {
+        let members = (0..50_000)
+            .map(|i| (format!("k{}", i % 25_000), Value::Bool(i >= 25_000)))
+            .collect();
+        let mut value = Value::Object(members);
+        assert!(has_duplicates(&value));
+        resolve(&mut value, DuplicateKeys::LastWins);
+        let Value::Object(members) = &value else {
+            unreachable!()
+        };
+        assert_eq!(members.len(), 25_000);
+        assert!(members.iter().all(|(_, value)| *value == Value::Bool(true)));
+        assert!(!has_duplicates(&value));
+    }
+
+    #[test]
+    fn every_backend_applies_the_policy() {
+        let value = |text| {
+            backend::by_name("rowan")
+                .unwrap()
+                .parse_value(text)
+                .unwrap()
+        };
+        let all = value(r#"{"a": 1, "b": {"x": [], "x": {}}, "a": 2, "a": 3}"#);
+        for backend in backend::all() {
+            let parse = |policy| {
+                backend
+                    .parse_value_with(INPUT, policy)
+                    .map(|(value, diagnostics)| (value, diagnostics.len()))
+                    .map_err(|errors| errors.len())
+            };
+            let name = backend.name();
+            assert_eq!(parse(DuplicateKeys::Error), Err(3), "{}", name);
+            assert_eq!(parse(DuplicateKeys::Warn), Ok((all.clone(), 3)), "{}", name);
+            assert_eq!(
+                parse(DuplicateKeys::FirstWins),
+                Ok((value(r#"{"a": 1, "b": {"x": []}}"#), 0)),
+                "{}",
+                name
+            );
+            assert_eq!(
+                parse(DuplicateKeys::LastWins),
+                Ok((value(r#"{"a": 3, "b": {"x": {}}}"#), 0)),
+                "{}",
+                name
+            );
+            assert_eq!(
+                parse(DuplicateKeys::KeepAll),
+                Ok((all.clone(), 0)),
+                "{}",
+                name
+            );
+        }
+    }
+}
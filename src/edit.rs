@@ -0,0 +1,65 @@
+//! Changes to a document, expressed as edits to its text so an editor can
+//! apply them and keep everything else as it was.
+
+use rowan::{TextRange, TextSize};
+
+/// Replaces the text in `range` with `new_text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: TextRange,
+    pub new_text: String,
+}
+
+impl TextEdit {
+    pub fn replace(range: TextRange, new_text: impl Into<String>) -> Self {
+        Self {
+            range,
+            new_text: new_text.into(),
+        }
+    }
+
+    pub fn insert(offset: TextSize, new_text: impl Into<String>) -> Self {
+        Self::replace(TextRange::empty(offset), new_text)
+    }
+
+    pub fn delete(range: TextRange) -> Self {
+        Self::replace(range, "")
+    }
+}
+
+/// Applies `edits` to `text`. The edits are ranges of the original text
+/// and must not overlap; insertions at the same offset are applied in
+/// order.
+pub fn apply(text: &str, edits: &[TextEdit]) -> String {
+    let mut edits: Vec<_> = edits.iter().collect();
+    edits.sort_by_key(|edit| (edit.range.start(), edit.range.end()));
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for edit in edits {
+        let start = usize::from(edit.range.start());
+        assert!(start >= last, "overlapping edits at {:?}", edit.range);
+        out.push_str(&text[last..start]);
+        out.push_str(&edit.new_text);
+        last = usize::from(edit.range.end());
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_edits_in_order() {
+        let range = |start: u32, end: u32| TextRange::new(start.into(), end.into());
+        let edits = [
+            TextEdit::replace(range(6, 11), "there"),
+            TextEdit::insert(0.into(), "> "),
+            TextEdit::delete(range(5, 6)),
+            TextEdit::insert(11.into(), "!"),
+            TextEdit::insert(11.into(), "?"),
+        ];
+        assert_eq!(apply("hello world", &edits), "> hellothere!?");
+    }
+}
@@ -0,0 +1,110 @@
+use std::borrow::Cow;
+
+/// Decodes the escapes of a string token's contents, i.e. the text between
+/// the quotes. Returns the input unchanged when it contains no escapes.
+///
+/// Any input is accepted: a `\u` escape that is a lone surrogate or lacks
+/// four hex digits decodes to U+FFFD.
+pub fn unescape(raw: &str) -> Cow<'_, str> {
+    if !raw.contains('\\') {
+        return Cow::Borrowed(raw);
+    }
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('b') => out.push('\x08'),
+            Some('f') => out.push('\x0c'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('u') => {
+                let code = match hex4(&mut chars) {
+                    Some(high @ 0xD800..=0xDBFF) => {
+                        let mut low_chars = chars.clone();
+                        let low = match (low_chars.next(), low_chars.next()) {
+                            (Some('\\'), Some('u')) => hex4(&mut low_chars),
+                            _ => None,
+                        };
+                        match low {
+                            Some(low @ 0xDC00..=0xDFFF) => {
+                                chars = low_chars;
+                                0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                            }
+                            _ => 0xFFFD,
+                        }
+                    }
+                    Some(code) => code,
+                    None => 0xFFFD,
+                };
+                out.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+            }
+            Some(c) => out.push(c),
+            None => {}
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Escapes `text` for use between the quotes of a string token. Only the
+/// characters JSON requires to be escaped are, so the result is the
+/// shortest valid spelling.
+pub fn escape(text: &str) -> Cow<'_, str> {
+    if !text.chars().any(|c| c == '"' || c == '\\' || c < ' ') {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len() + 2);
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\x08' => out.push_str("\\b"),
+            '\x0c' => out.push_str("\\f"),
+            c if c < ' ' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Reads four hex digits, advancing `chars` only if all four are there.
+fn hex4(chars: &mut std::str::Chars<'_>) -> Option<u32> {
+    let mut digits = chars.clone();
+    let mut code = 0;
+    for _ in 0..4 {
+        let c = digits.next().filter(char::is_ascii_hexdigit)?;
+        code = code * 16 + c.to_digit(16)?;
+    }
+    *chars = digits;
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unescapes() {
+        assert!(matches!(unescape("plain"), Cow::Borrowed("plain")));
+        assert_eq!(unescape(r#"a\"b\\c\/\n\t"#), "a\"b\\c/\n\t");
+        assert_eq!(unescape(r"\u00e9\ud83d\ude00"), "é😀");
+        assert_eq!(unescape(r"\ud83d!"), "\u{FFFD}!");
+        assert_eq!(unescape(r"\ud800\udc€€"), "\u{FFFD}\u{FFFD}dc€€");
+        assert_eq!(unescape(r"\uzzzz\u12"), "\u{FFFD}zzzz\u{FFFD}12");
+    }
+
+    #[test]
+    fn escapes() {
+        assert!(matches!(escape("plain é"), Cow::Borrowed("plain é")));
+        let text = "a\"b\\c/\n\t\u{1}";
+        assert_eq!(escape(text), r#"a\"b\\c/\n\t\u0001"#);
+        assert_eq!(unescape(&escape(text)), text);
+    }
+}
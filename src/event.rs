@@ -0,0 +1,305 @@
+//! A pull parser that validates the input and reports it as a flat stream of
+//! events, without building a tree and without recursion.
+
+use rowan::{TextRange, TextSize};
+
+use crate::diagnostic::Diagnostic;
+use crate::lexer::{Lexer, SyntaxKind};
+use crate::options::{string_too_long, Limit, ParseOptions, COMMENT_ERROR};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event<'a> {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    /// A member name as written in the input, without the quotes.
+    Key(&'a str),
+    /// A string as written in the input, without the quotes.
+    String(&'a str),
+    /// A number as written in the input.
+    Number(&'a str),
+    Boolean(bool),
+    Null,
+}
+
+/// What the parser accepts as the next token.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expect {
+    Value,
+    ValueOrClose,
+    Key,
+    KeyOrClose,
+    Colon,
+    CommaOrClose,
+    End,
+}
+
+impl Expect {
+    fn message(self) -> &'static str {
+        match self {
+            Expect::Value => "expected value",
+            Expect::ValueOrClose => "expected value or `]`",
+            Expect::Key => "expected string key",
+            Expect::KeyOrClose => "expected string key or `}`",
+            Expect::Colon => "expected `:`",
+            Expect::CommaOrClose => "expected `,` or closing delimiter",
+            Expect::End => "expected end of input",
+        }
+    }
+}
+
+pub struct EventParser<'a> {
+    input: &'a str,
+    lexer: Lexer<'a>,
+    offset: TextSize,
+    expect: Expect,
+    /// Whether each open container is an object.
+    open: Vec<bool>,
+    /// The member count of each open object.
+    members: Vec<usize>,
+    nodes: usize,
+    /// The range of the previous token if it was a comma.
+    comma: Option<TextRange>,
+    options: ParseOptions,
+    failed: bool,
+}
+
+impl<'a> EventParser<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self::with_options(input, ParseOptions::default())
+    }
+
+    pub fn with_options(input: &'a str, options: ParseOptions) -> Self {
+        Self {
+            input,
+            lexer: Lexer::new(input),
+            offset: 0.into(),
+            expect: Expect::Value,
+            open: vec![],
+            members: vec![],
+            nodes: 0,
+            comma: None,
+            options,
+            failed: false,
+        }
+    }
+
+    /// The number of containers currently open.
+    pub fn depth(&self) -> usize {
+        self.open.len()
+    }
+
+    /// Advances the state machine by one token, returning the event it
+    /// completes, if any, or the error message for an unexpected token.
+    fn step(&mut self, kind: SyntaxKind, text: &'a str) -> Result<Option<Event<'a>>, &'static str> {
+        if kind == SyntaxKind::Comment && !self.options.dialect.allows_comments() {
+            return Err(COMMENT_ERROR);
+        }
+        if kind.is_trivia() {
+            return Ok(None);
+        }
+        let in_object = self.open.last().copied();
+        let event = match (self.expect, kind) {
+            (Expect::Value | Expect::ValueOrClose, SyntaxKind::LeftBrace) => {
+                self.open.push(true);
+                self.expect = Expect::KeyOrClose;
+                return Ok(Some(Event::StartObject));
+            }
+            (Expect::Value | Expect::ValueOrClose, SyntaxKind::LeftBracket) => {
+                self.open.push(false);
+                self.expect = Expect::ValueOrClose;
+                return Ok(Some(Event::StartArray));
+            }
+            (Expect::Value | Expect::ValueOrClose, SyntaxKind::String) => {
+                Event::String(&text[1..text.len() - 1])
+            }
+            (Expect::Value | Expect::ValueOrClose, SyntaxKind::Number) => Event::Number(text),
+            (Expect::Value | Expect::ValueOrClose, SyntaxKind::True) => Event::Boolean(true),
+            (Expect::Value | Expect::ValueOrClose, SyntaxKind::False) => Event::Boolean(false),
+            (Expect::Value | Expect::ValueOrClose, SyntaxKind::Null) => Event::Null,
+            (Expect::ValueOrClose | Expect::CommaOrClose, SyntaxKind::RightBracket)
+                if in_object == Some(false) =>
+            {
+                self.open.pop();
+                Event::EndArray
+            }
+            (Expect::KeyOrClose | Expect::CommaOrClose, SyntaxKind::RightBrace)
+                if in_object == Some(true) =>
+            {
+                self.open.pop();
+                Event::EndObject
+            }
+            (Expect::Key | Expect::KeyOrClose, SyntaxKind::String) => {
+                self.expect = Expect::Colon;
+                return Ok(Some(Event::Key(&text[1..text.len() - 1])));
+            }
+            (Expect::Colon, SyntaxKind::Colon) => {
+                self.expect = Expect::Value;
+                return Ok(None);
+            }
+            (Expect::CommaOrClose, SyntaxKind::Comma) => {
+                let trailing = self.options.dialect.allows_trailing_commas();
+                self.expect = match (in_object == Some(true), trailing) {
+                    (true, true) => Expect::KeyOrClose,
+                    (true, false) => Expect::Key,
+                    (false, true) => Expect::ValueOrClose,
+                    (false, false) => Expect::Value,
+                };
+                return Ok(None);
+            }
+            (expect, _) => return Err(expect.message()),
+        };
+        // A scalar or a whole container is complete.
+        self.expect = if self.open.is_empty() {
+            Expect::End
+        } else {
+            Expect::CommaOrClose
+        };
+        Ok(Some(event))
+    }
+
+    /// Counts `event` against the limits, before it is handed out.
+    fn check_limits(&mut self, event: &Event<'a>, text: &str) -> Result<(), Limit> {
+        match event {
+            Event::StartObject | Event::StartArray if self.open.len() > self.options.max_depth => {
+                return Err(Limit::Depth)
+            }
+            Event::Key(_) => {
+                let count = self.members.last_mut().unwrap();
+                *count += 1;
+                if *count > self.options.max_members {
+                    return Err(Limit::Members);
+                }
+            }
+            Event::EndObject => {
+                self.members.pop();
+            }
+            _ => {}
+        }
+        if *event == Event::StartObject {
+            self.members.push(0);
+        }
+        if matches!(event, Event::Key(_) | Event::String(_)) && string_too_long(text, &self.options)
+        {
+            return Err(Limit::StringLen);
+        }
+        if !matches!(event, Event::Key(_) | Event::EndObject | Event::EndArray) {
+            self.nodes += 1;
+            if self.nodes > self.options.max_nodes {
+                return Err(Limit::Nodes);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for EventParser<'a> {
+    type Item = Result<(Event<'a>, TextRange), Diagnostic>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        // Nothing has been consumed only on the first call.
+        if self.offset == 0.into() && self.input.len() > self.options.max_bytes {
+            self.failed = true;
+            let excess = TextRange::new(
+                TextSize::from(self.options.max_bytes as u32),
+                TextSize::of(self.input),
+            );
+            return Some(Err(Diagnostic::new(
+                Limit::Bytes.message(&self.options),
+                excess,
+            )));
+        }
+        loop {
+            let (kind, text) = match self.lexer.next() {
+                Some(token) => token,
+                None if self.expect == Expect::End => return None,
+                None => {
+                    self.failed = true;
+                    let range = TextRange::empty(self.offset);
+                    return Some(Err(Diagnostic::new(self.expect.message(), range)));
+                }
+            };
+            let range = TextRange::at(self.offset, TextSize::of(text));
+            self.offset += TextSize::of(text);
+            let comma = match kind {
+                SyntaxKind::Whitespace | SyntaxKind::Comment => self.comma,
+                SyntaxKind::Comma => Some(range),
+                _ => None,
+            };
+            let previous_comma = std::mem::replace(&mut self.comma, comma);
+            match self.step(kind, text) {
+                Ok(Some(event)) => {
+                    if let Err(limit) = self.check_limits(&event, text) {
+                        self.failed = true;
+                        let message = limit.message(&self.options);
+                        return Some(Err(Diagnostic::new(message, range)));
+                    }
+                    return Some(Ok((event, range)));
+                }
+                Ok(None) => {}
+                Err(message) => {
+                    self.failed = true;
+                    let closes = match self.open.last() {
+                        Some(true) => SyntaxKind::RightBrace,
+                        _ => SyntaxKind::RightBracket,
+                    };
+                    if let Some(comma) = previous_comma.filter(|_| kind == closes) {
+                        let message = "trailing commas are not allowed in JSON";
+                        return Some(Err(Diagnostic::new(message, comma)));
+                    }
+                    return Some(Err(Diagnostic::new(message, range)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(input: &str) -> Vec<Result<Event<'_>, String>> {
+        EventParser::new(input)
+            .map(|it| it.map(|(event, _)| event).map_err(|e| e.message))
+            .collect()
+    }
+
+    #[test]
+    fn emits_events() {
+        assert_eq!(
+            events(r#" {"a": [1, true, null], "b": {}} "#),
+            [
+                Ok(Event::StartObject),
+                Ok(Event::Key("a")),
+                Ok(Event::StartArray),
+                Ok(Event::Number("1")),
+                Ok(Event::Boolean(true)),
+                Ok(Event::Null),
+                Ok(Event::EndArray),
+                Ok(Event::Key("b")),
+                Ok(Event::StartObject),
+                Ok(Event::EndObject),
+                Ok(Event::EndObject),
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_at_first_error() {
+        assert_eq!(
+            events("[1, }"),
+            [
+                Ok(Event::StartArray),
+                Ok(Event::Number("1")),
+                Err("expected value".to_string()),
+            ]
+        );
+        assert_eq!(events(r#"{"a" 1}"#)[2], Err("expected `:`".to_string()));
+        assert_eq!(events("[]]")[2], Err("expected end of input".to_string()));
+        assert_eq!(events("")[0], Err("expected value".to_string()));
+    }
+}
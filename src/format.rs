@@ -0,0 +1,214 @@
+//! Formats a syntax tree by rewriting only its whitespace.
+//!
+//! Unlike [`printer`](crate::printer), which prints a decoded value, this
+//! works on the tokens themselves, so numbers keep their spelling and
+//! comments stay where they were: a comment that started its own line
+//! still does, and one that followed a token on the same line still does.
+//! The tree must be free of errors.
+
+use crate::lexer::SyntaxKind;
+use crate::syntax::{SyntaxNode, SyntaxToken};
+
+/// Two-space indentation with one item per line, and empty containers
+/// kept on one line.
+pub fn format(root: &SyntaxNode) -> String {
+    let tokens = tokens(root);
+    let mut f = Formatter {
+        out: String::new(),
+        depth: 0,
+        pending: Pending::None,
+    };
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        match token.kind {
+            SyntaxKind::LeftBrace | SyntaxKind::LeftBracket => {
+                f.flush();
+                f.out.push_str(token.text());
+                if tokens.get(i + 1).is_some_and(|next| is_close(next.kind)) {
+                    // An empty container, nothing between the delimiters.
+                    f.out.push_str(tokens[i + 1].text());
+                    i += 1;
+                } else {
+                    f.depth += 1;
+                    f.pending = Pending::Newline;
+                }
+            }
+            SyntaxKind::RightBrace | SyntaxKind::RightBracket => {
+                f.depth -= 1;
+                f.pending = Pending::Newline;
+                f.flush();
+                f.out.push_str(token.text());
+            }
+            SyntaxKind::Comma => {
+                f.out.push(',');
+                f.pending = Pending::Newline;
+            }
+            SyntaxKind::Colon => f.out.push_str(": "),
+            SyntaxKind::Comment => {
+                let line_comment = token.text().starts_with("//");
+                // A `//` comment after a comma trails the item before it, a
+                // `/* */` comment there leads the item after it.
+                if token.own_line || (f.pending == Pending::Newline && !line_comment) {
+                    f.pending = Pending::Newline;
+                    f.flush();
+                } else if !f.out.is_empty() && !f.out.ends_with(' ') {
+                    f.out.push(' ');
+                }
+                f.out.push_str(token.text());
+                f.pending = if line_comment || token.own_line {
+                    Pending::Newline
+                } else {
+                    Pending::Space
+                };
+            }
+            _ => {
+                f.flush();
+                f.out.push_str(token.text());
+            }
+        }
+        i += 1;
+    }
+    f.out
+}
+
+/// The document without any whitespace, comments or trailing commas, so
+/// the result is strict JSON.
+pub fn minify(root: &SyntaxNode) -> String {
+    let tokens = tokens(root);
+    let mut out = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let trailing_comma = token.kind == SyntaxKind::Comma
+            && tokens.get(i + 1).is_some_and(|next| is_close(next.kind));
+        if token.kind != SyntaxKind::Comment && !trailing_comma {
+            out.push_str(token.text());
+        }
+    }
+    out
+}
+
+struct Token {
+    kind: SyntaxKind,
+    token: SyntaxToken,
+    /// Whether a line break separates the token from the one before it.
+    own_line: bool,
+}
+
+impl Token {
+    fn text(&self) -> &str {
+        self.token.text()
+    }
+}
+
+/// The tokens other than whitespace, in order.
+fn tokens(root: &SyntaxNode) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut own_line = false;
+    for token in root
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+    {
+        if token.kind() == SyntaxKind::Whitespace {
+            own_line |= token.text().contains(['\n', '\r']);
+            continue;
+        }
+        tokens.push(Token {
+            kind: token.kind(),
+            token,
+            own_line,
+        });
+        own_line = false;
+    }
+    tokens
+}
+
+fn is_close(kind: SyntaxKind) -> bool {
+    matches!(kind, SyntaxKind::RightBrace | SyntaxKind::RightBracket)
+}
+
+/// What goes in front of the next token.
+#[derive(Clone, Copy, PartialEq)]
+enum Pending {
+    None,
+    Space,
+    Newline,
+}
+
+struct Formatter {
+    out: String,
+    depth: usize,
+    pending: Pending,
+}
+
+impl Formatter {
+    fn flush(&mut self) {
+        match self.pending {
+            Pending::Newline if !self.out.is_empty() => {
+                self.out.push('\n');
+                self.out.push_str(&"  ".repeat(self.depth));
+            }
+            Pending::Space => self.out.push(' '),
+            _ => {}
+        }
+        self.pending = Pending::None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::options::{Dialect, ParseOptions};
+    use crate::parser::Parser;
+    use crate::printer::{print, Style};
+    use crate::strategies::{layout, value};
+
+    fn parse(text: &str) -> SyntaxNode {
+        let jsonc = ParseOptions {
+            dialect: Dialect::Jsonc,
+            ..ParseOptions::default()
+        };
+        let parse = Parser::new(text).options(jsonc).parse();
+        assert!(parse.ok(), "{:?}", parse.errors);
+        parse.syntax()
+    }
+
+    #[test]
+    fn keeps_comments_in_place() {
+        let input = r#"// settings
+{"editor": { "tabSize":2, // spaces
+  /* on its own line */
+  "rulers" : [ 80,1.50e2, ], "empty": [ ], "x": /* inline */ null }
+  }"#;
+        let formatted = format(&parse(input));
+        expect![[r#"
+            // settings
+            {
+              "editor": {
+                "tabSize": 2, // spaces
+                /* on its own line */
+                "rulers": [
+                  80,
+                  1.50e2,
+                ],
+                "empty": [],
+                "x": /* inline */ null
+              }
+            }"#]]
+        .assert_eq(&formatted);
+        assert_eq!(format(&parse(&formatted)), formatted);
+        expect![[r#"{"editor":{"tabSize":2,"rulers":[80,1.50e2],"empty":[],"x":null}}"#]]
+            .assert_eq(&minify(&parse(input)));
+    }
+
+    proptest! {
+        #[test]
+        fn formats_like_the_printer((value, text) in value().prop_flat_map(layout)) {
+            let root = parse(&text);
+            prop_assert_eq!(format(&root), print(&value, Style::Pretty));
+            prop_assert_eq!(minify(&root), print(&value, Style::Minified));
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! Classifies tokens for syntax highlighting.
+//!
+//! The lexer has a single `String` kind, so [`highlight`] looks at where a
+//! string sits in the tree to tell an object key from a string value. It
+//! works on trees with errors too: what the parser could not place is
+//! tagged [`HighlightTag::Error`].
+
+use rowan::TextRange;
+
+use crate::lexer::SyntaxKind;
+use crate::syntax::{SyntaxNode, SyntaxToken};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HighlightTag {
+    Key,
+    String,
+    Number,
+    Boolean,
+    Null,
+    /// Braces, brackets, colons and commas.
+    Punctuation,
+    Comment,
+    /// A token the lexer did not recognize or the parser could not place.
+    Error,
+}
+
+impl HighlightTag {
+    /// A lowercase name, used as the CSS class by [`html`].
+    pub fn name(self) -> &'static str {
+        match self {
+            HighlightTag::Key => "key",
+            HighlightTag::String => "string",
+            HighlightTag::Number => "number",
+            HighlightTag::Boolean => "boolean",
+            HighlightTag::Null => "null",
+            HighlightTag::Punctuation => "punctuation",
+            HighlightTag::Comment => "comment",
+            HighlightTag::Error => "error",
+        }
+    }
+
+    fn ansi(self) -> &'static str {
+        match self {
+            HighlightTag::Key => "\x1b[34m",
+            HighlightTag::String => "\x1b[32m",
+            HighlightTag::Number => "\x1b[36m",
+            HighlightTag::Boolean | HighlightTag::Null => "\x1b[35m",
+            HighlightTag::Punctuation => "",
+            HighlightTag::Comment => "\x1b[2m",
+            HighlightTag::Error => "\x1b[4;31m",
+        }
+    }
+}
+
+/// The tag of every token other than whitespace, in order. The lexer
+/// makes one error token per unrecognized character, adjacent ones are
+/// merged into one range.
+pub fn highlight(root: &SyntaxNode) -> Vec<(TextRange, HighlightTag)> {
+    let mut tags: Vec<(TextRange, HighlightTag)> = vec![];
+    for token in root
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+    {
+        let Some(tag) = tag(&token) else { continue };
+        let range = token.text_range();
+        match tags.last_mut() {
+            Some((last, HighlightTag::Error))
+                if tag == HighlightTag::Error && last.end() == range.start() =>
+            {
+                *last = last.cover(range)
+            }
+            _ => tags.push((range, tag)),
+        }
+    }
+    tags
+}
+
+/// The document as HTML, each token in a `<span>` whose class is its
+/// tag's [`name`](HighlightTag::name), prefixed with `json-`.
+pub fn html(root: &SyntaxNode) -> String {
+    render(root, |out, tag, text| {
+        let escaped = text
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;");
+        match tag {
+            Some(tag) => out.push_str(&format!(
+                "<span class=\"json-{}\">{}</span>",
+                tag.name(),
+                escaped
+            )),
+            None => out.push_str(&escaped),
+        }
+    })
+}
+
+/// The document colored with ANSI escape codes for a terminal.
+pub fn ansi(root: &SyntaxNode) -> String {
+    render(root, |out, tag, text| match tag.map(HighlightTag::ansi) {
+        Some(color) if !color.is_empty() => {
+            out.push_str(color);
+            out.push_str(text);
+            out.push_str("\x1b[0m");
+        }
+        _ => out.push_str(text),
+    })
+}
+
+fn render(root: &SyntaxNode, mut f: impl FnMut(&mut String, Option<HighlightTag>, &str)) -> String {
+    let mut out = String::new();
+    for token in root
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+    {
+        f(&mut out, tag(&token), token.text());
+    }
+    out
+}
+
+fn tag(token: &SyntaxToken) -> Option<HighlightTag> {
+    if token
+        .parent()
+        .is_some_and(|parent| parent.kind() == SyntaxKind::Error)
+    {
+        return match token.kind() {
+            SyntaxKind::Whitespace => None,
+            SyntaxKind::Comment => Some(HighlightTag::Comment),
+            _ => Some(HighlightTag::Error),
+        };
+    }
+    let tag = match token.kind() {
+        SyntaxKind::String if is_key(token) => HighlightTag::Key,
+        SyntaxKind::String => HighlightTag::String,
+        SyntaxKind::Number => HighlightTag::Number,
+        SyntaxKind::True | SyntaxKind::False => HighlightTag::Boolean,
+        SyntaxKind::Null => HighlightTag::Null,
+        SyntaxKind::LeftBrace
+        | SyntaxKind::RightBrace
+        | SyntaxKind::LeftBracket
+        | SyntaxKind::RightBracket
+        | SyntaxKind::Colon
+        | SyntaxKind::Comma => HighlightTag::Punctuation,
+        SyntaxKind::Comment => HighlightTag::Comment,
+        SyntaxKind::Whitespace => return None,
+        _ => HighlightTag::Error,
+    };
+    Some(tag)
+}
+
+/// Whether the string `token` is an object key: it follows the object's
+/// `{` or a comma, or a colon follows it. Either is enough, so a key stays
+/// a key while a member is half typed.
+fn is_key(token: &SyntaxToken) -> bool {
+    if token.parent().map(|parent| parent.kind()) != Some(SyntaxKind::Object) {
+        return false;
+    }
+    let previous = std::iter::successors(token.prev_sibling_or_token(), |element| {
+        element.prev_sibling_or_token()
+    })
+    .find(|element| !element.kind().is_trivia());
+    let next = std::iter::successors(token.next_sibling_or_token(), |element| {
+        element.next_sibling_or_token()
+    })
+    .find(|element| !element.kind().is_trivia());
+    matches!(
+        previous.map(|element| element.kind()),
+        Some(SyntaxKind::LeftBrace | SyntaxKind::Comma)
+    ) || next.is_some_and(|element| element.kind() == SyntaxKind::Colon)
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::options::{Dialect, ParseOptions};
+    use crate::parser::Parser;
+    use crate::strategies::{layout, value};
+    use crate::value::Value;
+
+    fn parse(text: &str) -> SyntaxNode {
+        let jsonc = ParseOptions {
+            dialect: Dialect::Jsonc,
+            ..ParseOptions::default()
+        };
+        Parser::new(text).options(jsonc).parse().syntax()
+    }
+
+    fn check(text: &str) -> String {
+        highlight(&parse(text))
+            .into_iter()
+            .map(|(range, tag)| format!("{:?} {:?} {}\n", range, tag, &text[range]))
+            .collect()
+    }
+
+    #[test]
+    fn tells_keys_from_values() {
+        expect![[r#"
+            0..1 Punctuation {
+            1..4 Key "a"
+            4..5 Punctuation :
+            6..9 String "b"
+            9..10 Punctuation ,
+            11..21 Comment /* note */
+            22..25 Key "c"
+            25..26 Punctuation :
+            27..28 Punctuation [
+            28..33 Boolean false
+            33..34 Punctuation ,
+            35..39 Null null
+            39..40 Punctuation ,
+            41..45 Number -1e3
+            45..46 Punctuation ,
+            47..48 Punctuation {
+            48..51 Key "d"
+            51..52 Punctuation :
+            52..55 String "e"
+            55..56 Punctuation }
+            56..57 Punctuation ]
+            57..58 Punctuation }
+        "#]]
+        .assert_eq(&check(
+            r#"{"a": "b", /* note */ "c": [false, null, -1e3, {"d":"e"}]}"#,
+        ));
+    }
+
+    #[test]
+    fn tags_errors_and_half_typed_members() {
+        expect![[r#"
+            0..1 Punctuation {
+            1..4 Key "a"
+            5..8 Error tru
+            8..9 Punctuation ,
+            10..13 Key "b"
+            13..14 Punctuation }
+            14..15 Error ]
+        "#]]
+        .assert_eq(&check(r#"{"a" tru, "b"}]"#));
+    }
+
+    #[test]
+    fn renders_html_and_ansi() {
+        let root = parse("{\"<a>\": [1, true]} // &");
+        expect![[r#"<span class="json-punctuation">{</span><span class="json-key">&quot;&lt;a&gt;&quot;</span><span class="json-punctuation">:</span> <span class="json-punctuation">[</span><span class="json-number">1</span><span class="json-punctuation">,</span> <span class="json-boolean">true</span><span class="json-punctuation">]</span><span class="json-punctuation">}</span> <span class="json-comment">// &amp;</span>"#]]
+            .assert_eq(&html(&root));
+        expect![[r#"{\u{1b}[34m\"<a>\"\u{1b}[0m: [\u{1b}[36m1\u{1b}[0m, \u{1b}[35mtrue\u{1b}[0m]} \u{1b}[2m// &\u{1b}[0m"#]]
+            .assert_eq(&ansi(&root).escape_debug().to_string());
+    }
+
+    fn members(value: &Value) -> usize {
+        match value {
+            Value::Array(elements) => elements.iter().map(members).sum(),
+            Value::Object(entries) => entries.iter().map(|(_, value)| 1 + members(value)).sum(),
+            _ => 0,
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn tags_one_key_per_member((value, text) in value().prop_flat_map(layout)) {
+            let tags = highlight(&parse(&text));
+            let keys = tags.iter().filter(|(_, tag)| *tag == HighlightTag::Key).count();
+            prop_assert_eq!(keys, members(&value));
+            prop_assert!(!tags.iter().any(|(_, tag)| *tag == HighlightTag::Error));
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use logos::Logos;
 use num_derive::{FromPrimitive, ToPrimitive};
 
+use crate::simd::SimdLexer;
+
 #[derive(Logos, Debug, PartialEq, FromPrimitive, ToPrimitive, Clone, Copy)]
 pub enum SyntaxKind {
     #[token("{")]
@@ -30,7 +32,9 @@ pub enum SyntaxKind {
     #[token("null")]
     Null,
 
-    #[regex(r#""([^"\\]|\\u[0-9a-f][0-9a-f][0-9a-f][0-9a-f]|\\["\\/bfnrt])*""#)]
+    #[regex(
+        r#""([^"\\\x00-\x1f]|\\u[0-9a-fA-F][0-9a-fA-F][0-9a-fA-F][0-9a-fA-F]|\\["\\/bfnrt])*""#
+    )]
     String,
     // #[regex(r#"\."#)]
     // Text1,
@@ -41,9 +45,15 @@ pub enum SyntaxKind {
     // it can be named anything you wish.
     // We can also use this variant to define whitespace,
     // or any other matches we wish to skip.
-    #[regex(r"[ \t\n\f]+")]
+    #[regex(r"[ \t\r\n]+")]
     Whitespace,
 
+    /// A `//` or `/* */` comment. Always lexed, but only the
+    /// [JSONC](crate::options::Dialect::Jsonc) dialect accepts it.
+    #[regex(r"//[^\r\n]*")]
+    #[regex(r"/\*[^*]*\*+([^/*][^*]*\*+)*/")]
+    Comment,
+
     #[error]
     Error,
 
@@ -53,21 +63,50 @@ pub enum SyntaxKind {
     Object,
 }
 
+impl SyntaxKind {
+    /// Whether the token carries no meaning: whitespace or a comment.
+    pub fn is_trivia(self) -> bool {
+        matches!(self, SyntaxKind::Whitespace | SyntaxKind::Comment)
+    }
+}
+
 impl From<SyntaxKind> for rowan::SyntaxKind {
     fn from(kind: SyntaxKind) -> Self {
         Self(kind as u16)
     }
 }
 
-pub(crate) struct Lexer<'a> {
-    inner: logos::Lexer<'a, SyntaxKind>,
+/// Selects how a [`Lexer`] finds token boundaries. Both backends produce the
+/// same token stream.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum LexerBackend {
+    /// The regex automaton generated by logos.
+    #[default]
+    Logos,
+    /// SIMD structural classification, see [`crate::simd`].
+    Simd,
+}
+
+pub struct Lexer<'a> {
+    inner: Inner<'a>,
+}
+
+enum Inner<'a> {
+    Logos(logos::Lexer<'a, SyntaxKind>),
+    Simd(SimdLexer<'a>),
 }
 
 impl<'a> Lexer<'a> {
-    pub(crate) fn new(input: &'a str) -> Self {
-        Self {
-            inner: SyntaxKind::lexer(input),
-        }
+    pub fn new(input: &'a str) -> Self {
+        Self::with_backend(input, LexerBackend::default())
+    }
+
+    pub fn with_backend(input: &'a str, backend: LexerBackend) -> Self {
+        let inner = match backend {
+            LexerBackend::Logos => Inner::Logos(SyntaxKind::lexer(input)),
+            LexerBackend::Simd => Inner::Simd(SimdLexer::new(input)),
+        };
+        Self { inner }
     }
 }
 
@@ -75,9 +114,12 @@ impl<'a> Iterator for Lexer<'a> {
     type Item = (SyntaxKind, &'a str);
 
     fn next(&mut self) -> Option<Self::Item> {
-        let kind = self.inner.next()?;
-        let text = self.inner.slice();
-
-        Some((kind, text))
+        match &mut self.inner {
+            Inner::Logos(lexer) => {
+                let kind = lexer.next()?;
+                Some((kind, lexer.slice()))
+            }
+            Inner::Simd(lexer) => lexer.next(),
+        }
     }
 }
@@ -1,5 +1,37 @@
+pub mod backend;
+pub mod binary;
 pub mod chumsky;
+pub mod compress;
+pub mod diagnostic;
+pub mod differential;
+pub mod document;
+pub mod duplicates;
+pub mod edit;
+pub mod escape;
+pub mod event;
+pub mod format;
+pub mod highlight;
 pub mod lexer;
+pub mod line_index;
+pub mod lint;
+pub mod navigation;
+pub mod ndjson;
+pub mod nom;
+pub mod options;
+pub mod parallel;
 pub mod parser;
+pub mod printer;
+pub mod quickfix;
 pub mod recursive;
+pub mod rename;
+pub mod render;
+pub mod selection;
+pub mod simd;
+pub mod sort;
+#[cfg(test)]
+mod strategies;
 pub mod syntax;
+pub mod tape;
+pub mod toml;
+pub mod value;
+pub mod yaml;
@@ -0,0 +1,255 @@
+//! Conversion between the tree's byte offsets and line/column positions.
+//!
+//! Lines end at `\n`, `\r\n` or a lone `\r`, the same terminators the
+//! Language Server Protocol recognizes. Columns count UTF-8 bytes in
+//! [`LineCol`] and UTF-16 code units or code points in [`WideLineCol`], so
+//! editors that index either way get positions that point at the same text.
+
+use rowan::{TextRange, TextSize};
+
+/// A 0-based line and a 0-based column counted in UTF-8 bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LineCol {
+    pub line: u32,
+    pub col: u32,
+}
+
+/// A 0-based line and a 0-based column counted in some [`WideEncoding`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WideLineCol {
+    pub line: u32,
+    pub col: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WideEncoding {
+    /// UTF-16 code units, what LSP clients use by default.
+    Utf16,
+    /// Unicode code points.
+    Utf32,
+}
+
+impl WideEncoding {
+    fn len(self, c: char) -> u32 {
+        match self {
+            WideEncoding::Utf16 => c.len_utf16() as u32,
+            WideEncoding::Utf32 => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineIndex {
+    /// The offset each line starts at.
+    starts: Vec<TextSize>,
+    /// The offset each line's terminator starts at, or the end of the input.
+    ends: Vec<TextSize>,
+    /// The non-ASCII characters of each line, by UTF-8 column.
+    wide_chars: Vec<Vec<(u32, char)>>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let mut index = LineIndex {
+            starts: vec![0.into()],
+            ends: vec![],
+            wide_chars: vec![vec![]],
+        };
+        let mut chars = text.char_indices().peekable();
+        while let Some((offset, c)) = chars.next() {
+            let line_start = u32::from(*index.starts.last().unwrap());
+            match c {
+                '\n' | '\r' => {
+                    let mut next = offset + 1;
+                    if c == '\r' && chars.peek().map(|&(_, c)| c) == Some('\n') {
+                        chars.next();
+                        next += 1;
+                    }
+                    index.ends.push(TextSize::from(offset as u32));
+                    index.starts.push(TextSize::from(next as u32));
+                    index.wide_chars.push(vec![]);
+                }
+                c if !c.is_ascii() => index
+                    .wide_chars
+                    .last_mut()
+                    .unwrap()
+                    .push((offset as u32 - line_start, c)),
+                _ => {}
+            }
+        }
+        index.ends.push(TextSize::of(text));
+        index
+    }
+
+    pub fn line_count(&self) -> u32 {
+        self.starts.len() as u32
+    }
+
+    /// The position of `offset`. An offset inside a `\r\n` pair or past the
+    /// end of the input is clamped to the end of its line.
+    pub fn line_col(&self, offset: TextSize) -> LineCol {
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+        let offset = offset.min(self.ends[line]);
+        LineCol {
+            line: line as u32,
+            col: u32::from(offset - self.starts[line]),
+        }
+    }
+
+    /// The offset of `line_col`, or `None` if the line does not exist or
+    /// is shorter than the column.
+    pub fn offset(&self, line_col: LineCol) -> Option<TextSize> {
+        let line = line_col.line as usize;
+        let offset = *self.starts.get(line)? + TextSize::from(line_col.col);
+        (offset <= self.ends[line]).then_some(offset)
+    }
+
+    /// The range of `line` without its terminator.
+    pub fn line_range(&self, line: u32) -> Option<TextRange> {
+        let line = line as usize;
+        Some(TextRange::new(*self.starts.get(line)?, self.ends[line]))
+    }
+
+    /// The wide position of `line_col`. A column inside a multi-byte
+    /// character is moved to the start of its character.
+    pub fn to_wide(&self, encoding: WideEncoding, line_col: LineCol) -> WideLineCol {
+        let mut col = line_col.col;
+        for &(start, c) in self.line_wide_chars(line_col.line) {
+            if start >= line_col.col {
+                break;
+            }
+            if start + c.len_utf8() as u32 > line_col.col {
+                col -= line_col.col - start;
+                break;
+            }
+            col -= c.len_utf8() as u32 - encoding.len(c);
+        }
+        WideLineCol {
+            line: line_col.line,
+            col,
+        }
+    }
+
+    /// The UTF-8 position of `wide`. A column inside a surrogate pair is
+    /// moved to the start of its character.
+    pub fn to_utf8(&self, encoding: WideEncoding, wide: WideLineCol) -> LineCol {
+        let mut col = wide.col;
+        for &(start, c) in self.line_wide_chars(wide.line) {
+            let wide_start = start - (col - wide.col);
+            if wide_start >= wide.col {
+                break;
+            }
+            if wide_start + encoding.len(c) > wide.col {
+                col = start;
+                break;
+            }
+            col += c.len_utf8() as u32 - encoding.len(c);
+        }
+        LineCol {
+            line: wide.line,
+            col,
+        }
+    }
+
+    fn line_wide_chars(&self, line: u32) -> &[(u32, char)] {
+        self.wide_chars
+            .get(line as usize)
+            .map_or(&[], |chars| chars.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handles_every_line_terminator() {
+        let index = LineIndex::new("a\nbc\r\nd\re");
+        assert_eq!(index.line_count(), 4);
+        let positions: Vec<_> = (0..=11)
+            .map(|offset| {
+                let LineCol { line, col } = index.line_col(TextSize::from(offset));
+                (line, col)
+            })
+            .collect();
+        assert_eq!(
+            positions,
+            [
+                (0, 0),
+                (0, 1),
+                (1, 0),
+                (1, 1),
+                (1, 2),
+                // Between `\r` and `\n`.
+                (1, 2),
+                (2, 0),
+                (2, 1),
+                (3, 0),
+                (3, 1),
+                // Past the end.
+                (3, 1),
+                (3, 1),
+            ]
+        );
+        assert_eq!(index.offset(LineCol { line: 1, col: 2 }), Some(4.into()));
+        assert_eq!(index.offset(LineCol { line: 1, col: 3 }), None);
+        assert_eq!(index.offset(LineCol { line: 3, col: 1 }), Some(9.into()));
+        assert_eq!(index.offset(LineCol { line: 4, col: 0 }), None);
+    }
+
+    #[test]
+    fn converts_wide_columns() {
+        // `é` is 2 bytes and 1 UTF-16 unit, `😀` 4 bytes and 2 units.
+        let text = "x\n\"é😀\": 1";
+        let index = LineIndex::new(text);
+        let value = TextSize::from(text.find('1').unwrap() as u32);
+        let line_col = index.line_col(value);
+        assert_eq!(line_col, LineCol { line: 1, col: 10 });
+        for (encoding, col) in [(WideEncoding::Utf16, 7), (WideEncoding::Utf32, 6)] {
+            let wide = index.to_wide(encoding, line_col);
+            assert_eq!(wide, WideLineCol { line: 1, col });
+            assert_eq!(index.to_utf8(encoding, wide), line_col);
+        }
+        // Inside the surrogate pair of `😀`.
+        let inside = WideLineCol { line: 1, col: 3 };
+        assert_eq!(
+            index.to_utf8(WideEncoding::Utf16, inside),
+            LineCol { line: 1, col: 3 }
+        );
+        // Inside the UTF-8 bytes of `é` and `😀`.
+        for (col, wide) in [(2, 1), (4, 2), (6, 2)] {
+            for encoding in [WideEncoding::Utf16, WideEncoding::Utf32] {
+                let inside = LineCol { line: 1, col };
+                assert_eq!(
+                    index.to_wide(encoding, inside),
+                    WideLineCol { line: 1, col: wide }
+                );
+            }
+        }
+        let emoji = LineIndex::new("😀");
+        let inside = LineCol { line: 0, col: 1 };
+        assert_eq!(
+            emoji.to_wide(WideEncoding::Utf16, inside),
+            WideLineCol { line: 0, col: 0 }
+        );
+    }
+
+    #[test]
+    fn round_trips_every_char_boundary() {
+        let text = "{\r\n  \"ключ\": \"𝄞 ♫\",\r  \"k\": [1]\n}";
+        let index = LineIndex::new(text);
+        // An offset inside a `\r\n` pair maps back to the `\r`.
+        let boundaries = text
+            .char_indices()
+            .filter(|&(offset, c)| c != '\n' || !text[..offset].ends_with('\r'));
+        for (offset, _) in boundaries {
+            let offset = TextSize::from(offset as u32);
+            let line_col = index.line_col(offset);
+            assert_eq!(index.offset(line_col), Some(offset));
+            for encoding in [WideEncoding::Utf16, WideEncoding::Utf32] {
+                let wide = index.to_wide(encoding, line_col);
+                assert_eq!(index.to_utf8(encoding, wide), line_col);
+            }
+        }
+    }
+}
@@ -0,0 +1,1119 @@
+//! Style and portability rules checked on top of the parser's diagnostics.
+//!
+//! A [`Rule`] looks at the tree and reports [`Lint`]s, with the edits that
+//! fix them in their diagnostic's [`fix`](Diagnostic::fix) when the rule
+//! knows them. A [`Linter`] runs a set of rules at the severities it was
+//! configured with, and drops what they report under the paths a rule is
+//! ignored at. The configuration is a JSON file:
+//!
+//! ```json
+//! {
+//!   "rules": {
+//!     "key-case": {"severity": "error", "style": "snake_case"},
+//!     "max-depth": {"max": 4},
+//!     "empty-keys": "off"
+//!   },
+//!   "ignore": {
+//!     "$.legacy": ["key-case"]
+//!   }
+//! }
+//! ```
+//!
+//! Rules left out keep their default severity and options. An ignored path
+//! covers everything under it, so `$` ignores a rule everywhere.
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use rowan::{NodeOrToken, TextRange, TextSize};
+
+use crate::backend::element_to_value;
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::duplicates::{self, DuplicateKeys};
+use crate::edit::TextEdit;
+use crate::lexer::SyntaxKind;
+use crate::navigation::Segment;
+use crate::options::{Dialect, ParseOptions};
+use crate::parser::Parser;
+use crate::syntax::{elements, members, SyntaxElement, SyntaxNode, SyntaxToken, ValueKind};
+use crate::value::{parse_path, PathSegment, Value};
+
+/// A problem a rule found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    /// The name of the rule that found it.
+    pub rule: &'static str,
+    pub diagnostic: Diagnostic,
+}
+
+pub trait Rule {
+    /// The name the configuration refers to the rule by, such as
+    /// `max-depth`.
+    fn name(&self) -> &'static str;
+
+    /// The severity the rule reports at unless configured, or `None` if it
+    /// is off by default.
+    fn default_severity(&self) -> Option<Severity>;
+
+    /// Sets the option `key` from the rule's entry in the configuration.
+    fn configure(&mut self, key: &str, _value: &Value) -> Result<(), String> {
+        Err(format!("`{}` has no option `{}`", self.name(), key))
+    }
+
+    /// Checks a tree, which may have syntax errors.
+    fn check(&self, root: &SyntaxNode) -> Vec<Lint>;
+}
+
+/// Every built-in rule, with its default options.
+pub fn rules() -> Vec<Box<dyn Rule>> {
+    vec![
+        Box::new(NoDuplicateKeys),
+        Box::new(NoEmptyKeys),
+        Box::new(MaxDepth::default()),
+        Box::new(KeyCase::default()),
+        Box::new(NoFloatIntegers),
+        Box::new(NoUnsafeNumbers),
+        Box::new(Indent::default()),
+    ]
+}
+
+/// Runs rules and applies their configuration.
+pub struct Linter {
+    rules: Vec<(Box<dyn Rule>, Severity)>,
+    /// Paths and the names of the rules ignored under them.
+    ignored: Vec<(Vec<PathSegment<'static>>, Vec<String>)>,
+}
+
+impl Default for Linter {
+    /// The built-in rules that are on by default.
+    fn default() -> Self {
+        let rules = rules()
+            .into_iter()
+            .filter_map(|rule| {
+                let severity = rule.default_severity()?;
+                Some((rule, severity))
+            })
+            .collect();
+        Self {
+            rules,
+            ignored: vec![],
+        }
+    }
+}
+
+impl Linter {
+    /// The built-in rules configured by the JSON, or JSONC, configuration
+    /// `text`.
+    pub fn from_config(text: &str) -> Result<Linter, Diagnostic> {
+        let options = ParseOptions {
+            dialect: Dialect::Jsonc,
+            ..ParseOptions::default()
+        };
+        let parse = Parser::new(text).options(options).parse();
+        if let Some(error) = parse.errors.first() {
+            return Err(error.clone());
+        }
+        let root = parse.syntax();
+        let mut rules: Vec<_> = rules()
+            .into_iter()
+            .map(|rule| {
+                let severity = rule.default_severity();
+                (rule, severity)
+            })
+            .collect();
+        let mut ignored = vec![];
+        let config = match root
+            .children_with_tokens()
+            .find(|element| !element.kind().is_trivia())
+        {
+            Some(config) => object(&config)?,
+            None => return Err(Diagnostic::new("expected an object", root.text_range())),
+        };
+        for member in members(&config) {
+            let value = member.value.clone().unwrap();
+            match member.name().as_str() {
+                "rules" => {
+                    for entry in members(&object(&value)?) {
+                        let name = entry.name();
+                        let Some((rule, severity)) =
+                            rules.iter_mut().find(|(rule, _)| rule.name() == name)
+                        else {
+                            let message = format!("unknown rule `{}`", name);
+                            return Err(Diagnostic::new(message, entry.key.text_range()));
+                        };
+                        configure(rule.as_mut(), severity, &entry.value.unwrap())?;
+                    }
+                }
+                "ignore" => {
+                    for entry in members(&object(&value)?) {
+                        let path = entry.name();
+                        let Some(segments) = parse_path(&path) else {
+                            let message = format!("invalid path `{}`", path);
+                            return Err(Diagnostic::new(message, entry.key.text_range()));
+                        };
+                        let names = rule_names(&rules, &entry.value.unwrap())?;
+                        ignored.push((segments.into_iter().map(owned).collect(), names));
+                    }
+                }
+                key => {
+                    let message = format!("unknown key `{}`", key);
+                    return Err(Diagnostic::new(message, member.key.text_range()));
+                }
+            }
+        }
+        let rules = rules
+            .into_iter()
+            .filter_map(|(rule, severity)| Some((rule, severity?)))
+            .collect();
+        Ok(Linter { rules, ignored })
+    }
+
+    /// Adds `rule`, or replaces the rule with its name.
+    pub fn with_rule(mut self, rule: impl Rule + 'static, severity: Severity) -> Self {
+        self.rules
+            .retain(|(existing, _)| existing.name() != rule.name());
+        self.rules.push((Box::new(rule), severity));
+        self
+    }
+
+    /// Runs every rule on `root`, in document order.
+    pub fn lint(&self, root: &SyntaxNode) -> Vec<Lint> {
+        let mut lints = vec![];
+        let mut paths = None;
+        for (rule, severity) in &self.rules {
+            let patterns: Vec<_> = self
+                .ignored
+                .iter()
+                .filter(|(_, names)| names.iter().any(|name| name == rule.name()))
+                .map(|(pattern, _)| pattern)
+                .collect();
+            for mut lint in rule.check(root) {
+                let ignored = !patterns.is_empty() && {
+                    let paths = paths.get_or_insert_with(|| Paths::new(root));
+                    let path = paths.at(lint.diagnostic.range.start());
+                    patterns.iter().any(|pattern| covers(pattern, &path))
+                };
+                if !ignored {
+                    lint.diagnostic.severity = *severity;
+                    lints.push(lint);
+                }
+            }
+        }
+        lints.sort_by_key(|lint| lint.diagnostic.range.start());
+        lints
+    }
+}
+
+fn object(element: &SyntaxElement) -> Result<SyntaxNode, Diagnostic> {
+    element
+        .as_node()
+        .filter(|node| node.kind() == SyntaxKind::Object)
+        .cloned()
+        .ok_or_else(|| Diagnostic::new("expected an object", element.text_range()))
+}
+
+/// Applies a rule's entry in the configuration: a severity, or an object
+/// of options with an optional `severity` member. Options turn on a rule
+/// that is off by default, as a warning.
+fn configure(
+    rule: &mut dyn Rule,
+    severity: &mut Option<Severity>,
+    entry: &SyntaxElement,
+) -> Result<(), Diagnostic> {
+    if entry.kind() == SyntaxKind::String {
+        *severity = parse_severity(entry)?;
+        return Ok(());
+    }
+    let options = object(entry)?;
+    *severity = severity.or(Some(Severity::Warning));
+    for option in members(&options) {
+        let value = option.value.clone().unwrap();
+        match option.name().as_str() {
+            "severity" => *severity = parse_severity(&value)?,
+            key => rule
+                .configure(key, &element_to_value(value))
+                .map_err(|message| Diagnostic::new(message, option.range()))?,
+        }
+    }
+    Ok(())
+}
+
+fn parse_severity(element: &SyntaxElement) -> Result<Option<Severity>, Diagnostic> {
+    match element_to_value(element.clone()).as_str() {
+        Some("error") => Ok(Some(Severity::Error)),
+        Some("warning") => Ok(Some(Severity::Warning)),
+        Some("off") => Ok(None),
+        _ => Err(Diagnostic::new(
+            "expected `error`, `warning` or `off`",
+            element.text_range(),
+        )),
+    }
+}
+
+/// The names in an array of rule names.
+fn rule_names(
+    rules: &[(Box<dyn Rule>, Option<Severity>)],
+    element: &SyntaxElement,
+) -> Result<Vec<String>, Diagnostic> {
+    let array = element
+        .as_node()
+        .filter(|node| node.kind() == SyntaxKind::Array)
+        .ok_or_else(|| Diagnostic::new("expected an array of rules", element.text_range()))?;
+    elements(array)
+        .into_iter()
+        .map(|element| match element_to_value(element.clone()) {
+            Value::String(name) if rules.iter().any(|(rule, _)| rule.name() == name) => Ok(name),
+            Value::String(name) => Err(Diagnostic::new(
+                format!("unknown rule `{}`", name),
+                element.text_range(),
+            )),
+            _ => Err(Diagnostic::new("expected a rule", element.text_range())),
+        })
+        .collect()
+}
+
+fn owned(segment: PathSegment<'_>) -> PathSegment<'static> {
+    match segment {
+        PathSegment::Name(name) => PathSegment::Name(Cow::Owned(name.into_owned())),
+        PathSegment::Wildcard => PathSegment::Wildcard,
+    }
+}
+
+/// Whether `path` is at or under the path `pattern` matches.
+fn covers(pattern: &[PathSegment], path: &[&Segment]) -> bool {
+    pattern.len() <= path.len()
+        && pattern
+            .iter()
+            .zip(path)
+            .all(|(pattern, segment)| match (pattern, segment) {
+                (PathSegment::Wildcard, _) => true,
+                (PathSegment::Name(name), Segment::Key(key)) => name == key,
+                (PathSegment::Name(name), Segment::Index(i)) => *name == i.to_string(),
+            })
+}
+
+/// The path to every value in a tree, so the path at an offset can be
+/// found without walking the tree again, as [`breadcrumbs`] would.
+///
+/// [`breadcrumbs`]: crate::navigation::breadcrumbs
+struct Paths {
+    /// In document order: what a segment covers (the whole member for a
+    /// key), the segment and the index of the entry it is under.
+    entries: Vec<(TextRange, Segment, Option<usize>)>,
+}
+
+impl Paths {
+    fn new(root: &SyntaxNode) -> Paths {
+        let mut entries = vec![];
+        let mut stack = vec![];
+        let mut value = root
+            .children_with_tokens()
+            .find(|element| !element.kind().is_trivia());
+        let mut parent = None;
+        loop {
+            if let Some(NodeOrToken::Node(node)) = value.take() {
+                let children: Vec<_> = match node.kind() {
+                    SyntaxKind::Object => members(&node)
+                        .into_iter()
+                        .filter_map(|member| {
+                            let range = member.range();
+                            Some((range, Segment::Key(member.name()), member.value?))
+                        })
+                        .collect(),
+                    SyntaxKind::Array => elements(&node)
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, element)| (element.text_range(), Segment::Index(i), element))
+                        .collect(),
+                    _ => vec![],
+                };
+                stack.extend(
+                    children
+                        .into_iter()
+                        .filter(|(.., value)| ValueKind::of(value).is_some())
+                        .map(|child| (child, parent))
+                        .rev(),
+                );
+            }
+            let Some(((range, segment, child), under)) = stack.pop() else {
+                break;
+            };
+            entries.push((range, segment, under));
+            parent = Some(entries.len() - 1);
+            value = Some(child);
+        }
+        Paths { entries }
+    }
+
+    /// The path to the innermost value at `offset`, from the root down.
+    fn at(&self, offset: TextSize) -> Vec<&Segment> {
+        // Entries start in order and nest, so the one that starts last at
+        // or before `offset` is either the innermost one containing it or
+        // under it.
+        let mut current = self
+            .entries
+            .partition_point(|(range, ..)| range.start() <= offset)
+            .checked_sub(1);
+        while let Some(i) = current {
+            if self.entries[i].0.contains_inclusive(offset) {
+                break;
+            }
+            current = self.entries[i].2;
+        }
+        let mut path = vec![];
+        while let Some(i) = current {
+            path.push(&self.entries[i].1);
+            current = self.entries[i].2;
+        }
+        path.reverse();
+        path
+    }
+}
+
+fn lint(rule: &dyn Rule, message: impl Into<String>, range: TextRange) -> Lint {
+    Lint {
+        rule: rule.name(),
+        diagnostic: Diagnostic::warning(message, range),
+    }
+}
+
+/// `duplicate-keys`: an object repeats a key, which parsers disagree on.
+pub struct NoDuplicateKeys;
+
+impl Rule for NoDuplicateKeys {
+    fn name(&self) -> &'static str {
+        "duplicate-keys"
+    }
+
+    fn default_severity(&self) -> Option<Severity> {
+        Some(Severity::Error)
+    }
+
+    fn check(&self, root: &SyntaxNode) -> Vec<Lint> {
+        duplicates::check(root, DuplicateKeys::Warn)
+            .into_iter()
+            .map(|diagnostic| Lint {
+                rule: self.name(),
+                diagnostic,
+            })
+            .collect()
+    }
+}
+
+/// `empty-keys`: a member's key is `""`.
+pub struct NoEmptyKeys;
+
+impl Rule for NoEmptyKeys {
+    fn name(&self) -> &'static str {
+        "empty-keys"
+    }
+
+    fn default_severity(&self) -> Option<Severity> {
+        Some(Severity::Warning)
+    }
+
+    fn check(&self, root: &SyntaxNode) -> Vec<Lint> {
+        objects(root)
+            .flat_map(|object| members(&object))
+            .filter(|member| member.name().is_empty())
+            .map(|member| lint(self, "empty key", member.key.text_range()))
+            .collect()
+    }
+}
+
+/// `max-depth`: arrays and objects are nested more than `max` levels deep.
+/// Only the outermost container past the limit is reported.
+pub struct MaxDepth {
+    pub max: usize,
+}
+
+impl Default for MaxDepth {
+    fn default() -> Self {
+        Self { max: 16 }
+    }
+}
+
+impl Rule for MaxDepth {
+    fn name(&self) -> &'static str {
+        "max-depth"
+    }
+
+    fn default_severity(&self) -> Option<Severity> {
+        Some(Severity::Warning)
+    }
+
+    fn configure(&mut self, key: &str, value: &Value) -> Result<(), String> {
+        match (key, value) {
+            ("max", Value::Number(n)) if n.as_u64().is_some() => {
+                self.max = n.as_u64().unwrap() as usize;
+                Ok(())
+            }
+            ("max", _) => Err("expected a whole number".to_string()),
+            _ => Err(format!("`{}` has no option `{}`", self.name(), key)),
+        }
+    }
+
+    fn check(&self, root: &SyntaxNode) -> Vec<Lint> {
+        let depth = |node: &SyntaxNode| node.ancestors().filter(is_container).count();
+        root.descendants()
+            .filter(|node| is_container(node) && depth(node) == self.max + 1)
+            .map(|node| {
+                let message = format!("nesting exceeds {} levels", self.max);
+                let range = node.first_token().unwrap().text_range();
+                lint(self, message, range)
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    #[default]
+    Camel,
+    Snake,
+}
+
+impl Case {
+    fn name(self) -> &'static str {
+        match self {
+            Case::Camel => "camelCase",
+            Case::Snake => "snake_case",
+        }
+    }
+
+    /// `key` in this case, split into words at `_`, `-` and where the case
+    /// changes, as in `HTTPServer`.
+    fn convert(self, key: &str) -> String {
+        let chars: Vec<char> = key.chars().collect();
+        let mut words: Vec<String> = vec![String::new()];
+        for (i, &c) in chars.iter().enumerate() {
+            if c == '_' || c == '-' {
+                words.push(String::new());
+                continue;
+            }
+            let previous = i.checked_sub(1).map(|i| chars[i]);
+            let next = chars.get(i + 1);
+            let boundary = c.is_ascii_uppercase()
+                && match previous {
+                    Some(p) if p.is_ascii_lowercase() || p.is_ascii_digit() => true,
+                    Some(p) => p.is_ascii_uppercase() && next.is_some_and(char::is_ascii_lowercase),
+                    None => false,
+                };
+            if boundary {
+                words.push(String::new());
+            }
+            words.last_mut().unwrap().push(c.to_ascii_lowercase());
+        }
+        words.retain(|word| !word.is_empty());
+        match self {
+            Case::Snake => words.join("_"),
+            Case::Camel => {
+                let mut out = String::new();
+                for (i, word) in words.iter().enumerate() {
+                    let mut chars = word.chars();
+                    if i > 0 {
+                        out.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+                    }
+                    out.extend(chars);
+                }
+                out
+            }
+        }
+    }
+}
+
+/// `key-case`: a key is not written in `style`. Only keys of ASCII letters,
+/// digits, `_` and `-` that start with a letter are checked, so keys such
+/// as `$schema` or `_id` are left alone. The fix renames the key unless the
+/// object already has a member with the new name.
+#[derive(Default)]
+pub struct KeyCase {
+    pub style: Case,
+}
+
+impl Rule for KeyCase {
+    fn name(&self) -> &'static str {
+        "key-case"
+    }
+
+    fn default_severity(&self) -> Option<Severity> {
+        None
+    }
+
+    fn configure(&mut self, key: &str, value: &Value) -> Result<(), String> {
+        match (key, value.as_str()) {
+            ("style", Some("camelCase")) => self.style = Case::Camel,
+            ("style", Some("snake_case")) => self.style = Case::Snake,
+            ("style", _) => return Err("expected `camelCase` or `snake_case`".to_string()),
+            _ => return Err(format!("`{}` has no option `{}`", self.name(), key)),
+        }
+        Ok(())
+    }
+
+    fn check(&self, root: &SyntaxNode) -> Vec<Lint> {
+        let mut lints = vec![];
+        for object in objects(root) {
+            let members = members(&object);
+            // Names already used, including the ones fixes rename keys to.
+            let mut taken: HashSet<_> = members.iter().map(|member| member.name()).collect();
+            for member in &members {
+                let name = member.name();
+                let checked = name.starts_with(|c: char| c.is_ascii_alphabetic())
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+                let converted = self.style.convert(&name);
+                if !checked || converted == name {
+                    continue;
+                }
+                let message = format!("key `{}` is not {}", name, self.style.name());
+                let mut lint = lint(self, message, member.key.text_range());
+                if !taken.contains(&converted) {
+                    let key = format!("\"{}\"", converted);
+                    lint.diagnostic.fix = vec![TextEdit::replace(member.key.text_range(), key)];
+                    taken.insert(converted);
+                }
+                lints.push(lint);
+            }
+        }
+        lints
+    }
+}
+
+/// `float-integers`: an integer is written with a fraction or exponent, as
+/// in `1.0` or `1e3`. The fix writes it as an integer when that is no
+/// longer than 16 digits.
+pub struct NoFloatIntegers;
+
+impl Rule for NoFloatIntegers {
+    fn name(&self) -> &'static str {
+        "float-integers"
+    }
+
+    fn default_severity(&self) -> Option<Severity> {
+        None
+    }
+
+    fn check(&self, root: &SyntaxNode) -> Vec<Lint> {
+        numbers(root)
+            .filter(|token| token.text().contains(['.', 'e', 'E']))
+            .filter_map(|token| {
+                let integer = integer(token.text())?;
+                let message = format!("`{}` is the integer `{}`", token.text(), integer);
+                let mut lint = lint(self, message, token.text_range());
+                if integer.trim_start_matches('-').len() <= 16 {
+                    lint.diagnostic.fix = vec![TextEdit::replace(token.text_range(), integer)];
+                }
+                Some(lint)
+            })
+            .collect()
+    }
+}
+
+/// `unsafe-numbers`: an integer is beyond ±2^53 - 1, where JavaScript
+/// numbers can no longer represent every integer, or a number overflows
+/// them altogether.
+pub struct NoUnsafeNumbers;
+
+impl Rule for NoUnsafeNumbers {
+    fn name(&self) -> &'static str {
+        "unsafe-numbers"
+    }
+
+    fn default_severity(&self) -> Option<Severity> {
+        Some(Severity::Warning)
+    }
+
+    fn check(&self, root: &SyntaxNode) -> Vec<Lint> {
+        const MAX_SAFE_INTEGER: &str = "9007199254740991";
+        numbers(root)
+            .filter_map(|token| {
+                let text = token.text();
+                let message = match integer(text) {
+                    Some(integer) => {
+                        let digits = integer.trim_start_matches('-');
+                        let unsafe_ =
+                            (digits.len(), digits) > (MAX_SAFE_INTEGER.len(), MAX_SAFE_INTEGER);
+                        if !unsafe_ {
+                            return None;
+                        }
+                        format!("`{}` loses precision as a JavaScript number", text)
+                    }
+                    None if text.parse::<f64>().is_ok_and(f64::is_infinite) => {
+                        format!("`{}` is too large for a JavaScript number", text)
+                    }
+                    None => return None,
+                };
+                Some(lint(self, message, token.text_range()))
+            })
+            .collect()
+    }
+}
+
+/// `indent`: a line is not indented by one `unit` per level of nesting,
+/// counted from the line its container starts on. The unit is detected from
+/// the first indented line unless configured, as a number of spaces or
+/// `"tab"`.
+#[derive(Default)]
+pub struct Indent {
+    pub unit: Option<String>,
+}
+
+impl Rule for Indent {
+    fn name(&self) -> &'static str {
+        "indent"
+    }
+
+    fn default_severity(&self) -> Option<Severity> {
+        Some(Severity::Warning)
+    }
+
+    fn configure(&mut self, key: &str, value: &Value) -> Result<(), String> {
+        match (key, value) {
+            ("unit", Value::String(tab)) if tab == "tab" => self.unit = Some("\t".to_string()),
+            ("unit", Value::Number(n)) if n.as_u64().is_some_and(|n| n <= 16) => {
+                self.unit = Some(" ".repeat(n.as_u64().unwrap() as usize))
+            }
+            ("unit", _) => return Err("expected a number of spaces or `tab`".to_string()),
+            _ => return Err(format!("`{}` has no option `{}`", self.name(), key)),
+        }
+        Ok(())
+    }
+
+    fn check(&self, root: &SyntaxNode) -> Vec<Lint> {
+        let mut lints = vec![];
+        let mut unit = self.unit.clone();
+        // The level of the line each open container starts on.
+        let mut open = vec![];
+        let mut line = 0;
+        let mut previous: Option<SyntaxToken> = None;
+        let tokens = root
+            .descendants_with_tokens()
+            .filter_map(|element| element.into_token());
+        for token in tokens {
+            let kind = token.kind();
+            if kind == SyntaxKind::Whitespace {
+                previous = Some(token);
+                continue;
+            }
+            if let Some(indent) = indentation(previous.as_ref()) {
+                let closes = matches!(kind, SyntaxKind::RightBrace | SyntaxKind::RightBracket);
+                line = match (open.last(), closes) {
+                    (Some(&level), true) => level,
+                    (Some(&level), false) => level + 1,
+                    (None, _) => 0,
+                };
+                let actual = previous.as_ref().map_or("", |ws| {
+                    let start = indent.start() - ws.text_range().start();
+                    &ws.text()[TextRange::at(start, indent.len())]
+                });
+                if unit.is_none() && line == 1 && !actual.is_empty() {
+                    unit = Some(actual.to_string());
+                }
+                let expected = unit.as_ref().map(|unit| unit.repeat(line));
+                if let Some(expected) = expected.filter(|expected| expected != actual) {
+                    let message = format!(
+                        "indentation is {} instead of {}",
+                        describe(actual),
+                        describe(&expected)
+                    );
+                    let mut lint = lint(self, message, token.text_range());
+                    lint.diagnostic.fix = vec![TextEdit::replace(indent, expected)];
+                    lints.push(lint);
+                }
+            }
+            if token.parent().as_ref().is_some_and(is_container) {
+                match kind {
+                    SyntaxKind::LeftBrace | SyntaxKind::LeftBracket => open.push(line),
+                    SyntaxKind::RightBrace | SyntaxKind::RightBracket => {
+                        open.pop();
+                    }
+                    _ => {}
+                }
+            }
+            previous = Some(token);
+        }
+        lints
+    }
+}
+
+/// The indentation before a token that starts a line, after the token
+/// before it.
+fn indentation(previous: Option<&SyntaxToken>) -> Option<TextRange> {
+    let Some(previous) = previous else {
+        return Some(TextRange::empty(TextSize::from(0)));
+    };
+    let range = previous.text_range();
+    if previous.kind() != SyntaxKind::Whitespace {
+        return None;
+    }
+    match previous.text().rfind('\n') {
+        Some(newline) => Some(TextRange::new(
+            range.start() + TextSize::from(newline as u32 + 1),
+            range.end(),
+        )),
+        None if range.start() == TextSize::from(0) => Some(range),
+        None => None,
+    }
+}
+
+fn describe(indentation: &str) -> String {
+    let tabs = indentation.matches('\t').count();
+    let spaces = indentation.len() - tabs;
+    let count = |n: usize, what: &str| match n {
+        1 => format!("1 {}", what),
+        n => format!("{} {}s", n, what),
+    };
+    match (tabs, spaces) {
+        (0, 0) => "none".to_string(),
+        (0, spaces) => count(spaces, "space"),
+        (tabs, 0) => count(tabs, "tab"),
+        (tabs, spaces) => format!("{} and {}", count(tabs, "tab"), count(spaces, "space")),
+    }
+}
+
+fn is_container(node: &SyntaxNode) -> bool {
+    matches!(node.kind(), SyntaxKind::Object | SyntaxKind::Array)
+}
+
+fn objects(root: &SyntaxNode) -> impl Iterator<Item = SyntaxNode> {
+    root.descendants()
+        .filter(|node| node.kind() == SyntaxKind::Object)
+}
+
+fn numbers(root: &SyntaxNode) -> impl Iterator<Item = SyntaxToken> {
+    root.descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| token.kind() == SyntaxKind::Number)
+}
+
+/// The integer a number is equal to, written without a fraction or
+/// exponent, or `None` if it is not an integer or would take more than a
+/// few hundred digits to write out.
+fn integer(text: &str) -> Option<String> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(text) => (true, text),
+        None => (false, text),
+    };
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(e) => (&text[..e], text[e + 1..].parse::<i64>().ok()?),
+        None => (text, 0),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", whole, fraction);
+    let point = (whole.len() as i64).checked_add(exponent)?;
+    let integer = if point > 400 {
+        return None;
+    } else if point <= 0 {
+        match digits.bytes().all(|digit| digit == b'0') {
+            true => String::new(),
+            false => return None,
+        }
+    } else if point as usize >= digits.len() {
+        digits.clone() + &"0".repeat(point as usize - digits.len())
+    } else {
+        let (integer, rest) = digits.split_at(point as usize);
+        if !rest.bytes().all(|digit| digit == b'0') {
+            return None;
+        }
+        integer.to_string()
+    };
+    let integer = integer.trim_start_matches('0');
+    Some(match (integer, negative) {
+        ("", _) => "0".to_string(),
+        (integer, true) => format!("-{}", integer),
+        (integer, false) => integer.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::{expect, Expect};
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::backend::syntax_to_value;
+    use crate::edit::apply;
+    use crate::render::render_diagnostics;
+    use crate::strategies::{layout, value};
+
+    fn check(config: &str, text: &str, expected: Expect) {
+        let linter = Linter::from_config(config).unwrap();
+        let root = Parser::new(text).parse().syntax();
+        let lints = linter.lint(&root);
+        let diagnostics: Vec<_> = lints
+            .iter()
+            .map(|lint| Diagnostic {
+                message: format!("{} [{}]", lint.diagnostic.message, lint.rule),
+                ..lint.diagnostic.clone()
+            })
+            .collect();
+        let fixes: Vec<_> = lints
+            .iter()
+            .flat_map(|lint| lint.diagnostic.fix.clone())
+            .collect();
+        let actual = format!(
+            "{}--- fixed\n{}",
+            render_diagnostics(text, &diagnostics),
+            apply(text, &fixes)
+        );
+        expected.assert_eq(&actual);
+    }
+
+    #[test]
+    fn fixes_keys_to_distinct_names() {
+        check(
+            r#"{"rules": {"key-case": "warning"}}"#,
+            r#"{"foo_bar": 1, "foo-bar": 2}"#,
+            expect![[r#"
+                warning: key `foo_bar` is not camelCase [key-case]
+                 --> 1:2
+                  |
+                1 | {"foo_bar": 1, "foo-bar": 2}
+                  |  ^^^^^^^^^
+
+                warning: key `foo-bar` is not camelCase [key-case]
+                 --> 1:16
+                  |
+                1 | {"foo_bar": 1, "foo-bar": 2}
+                  |                ^^^^^^^^^
+                --- fixed
+                {"fooBar": 1, "foo-bar": 2}"#]],
+        );
+    }
+
+    #[test]
+    fn reports_keys_and_numbers() {
+        check(
+            r#"{
+              // Every rule about keys and numbers, at their defaults.
+              "rules": {"key-case": "warning", "float-integers": "warning"}
+            }"#,
+            r#"{"fooBar": 1.0, "foo_bar": 2e3, "Id": 12345678901234567890, "": 1e400, "id": 0, "id": -0.5e1}"#,
+            expect![[r#"
+                warning: `1.0` is the integer `1` [float-integers]
+                 --> 1:12
+                  |
+                1 | {"fooBar": 1.0, "foo_bar": 2e3, "Id": 12345678901234567890, "": 1e400, "id": 0, "id": -0.5e1}
+                  |            ^^^
+
+                warning: key `foo_bar` is not camelCase [key-case]
+                 --> 1:17
+                  |
+                1 | {"fooBar": 1.0, "foo_bar": 2e3, "Id": 12345678901234567890, "": 1e400, "id": 0, "id": -0.5e1}
+                  |                 ^^^^^^^^^
+
+                warning: `2e3` is the integer `2000` [float-integers]
+                 --> 1:28
+                  |
+                1 | {"fooBar": 1.0, "foo_bar": 2e3, "Id": 12345678901234567890, "": 1e400, "id": 0, "id": -0.5e1}
+                  |                            ^^^
+
+                warning: key `Id` is not camelCase [key-case]
+                 --> 1:33
+                  |
+                1 | {"fooBar": 1.0, "foo_bar": 2e3, "Id": 12345678901234567890, "": 1e400, "id": 0, "id": -0.5e1}
+                  |                                 ^^^^
+
+                warning: `12345678901234567890` loses precision as a JavaScript number [unsafe-numbers]
+                 --> 1:39
+                  |
+                1 | {"fooBar": 1.0, "foo_bar": 2e3, "Id": 12345678901234567890, "": 1e400, "id": 0, "id": -0.5e1}
+                  |                                       ^^^^^^^^^^^^^^^^^^^^
+
+                warning: empty key [empty-keys]
+                 --> 1:61
+                  |
+                1 | {"fooBar": 1.0, "foo_bar": 2e3, "Id": 12345678901234567890, "": 1e400, "id": 0, "id": -0.5e1}
+                  |                                                             ^^
+
+                warning: `1e400` is too large for a JavaScript number [unsafe-numbers]
+                 --> 1:65
+                  |
+                1 | {"fooBar": 1.0, "foo_bar": 2e3, "Id": 12345678901234567890, "": 1e400, "id": 0, "id": -0.5e1}
+                  |                                                                 ^^^^^
+
+                error: duplicate key `id` [duplicate-keys]
+                 --> 1:81
+                  |
+                1 | {"fooBar": 1.0, "foo_bar": 2e3, "Id": 12345678901234567890, "": 1e400, "id": 0, "id": -0.5e1}
+                  |                                                                                 ^^^^
+                  |                                                                        ---- first defined here
+
+                warning: `-0.5e1` is the integer `-5` [float-integers]
+                 --> 1:87
+                  |
+                1 | {"fooBar": 1.0, "foo_bar": 2e3, "Id": 12345678901234567890, "": 1e400, "id": 0, "id": -0.5e1}
+                  |                                                                                       ^^^^^^
+                --- fixed
+                {"fooBar": 1, "foo_bar": 2000, "Id": 12345678901234567890, "": 1e400, "id": 0, "id": -5}"#]],
+        );
+    }
+
+    #[test]
+    fn checks_nesting_and_indentation() {
+        check(
+            r#"{
+              "rules": {
+                "key-case": {"style": "snake_case"},
+                "max-depth": {"severity": "error", "max": 2},
+                "indent": {"unit": 2}
+              },
+              "ignore": {"$.legacy[*]": ["key-case"], "$.deep": ["max-depth"]}
+            }"#,
+            "{\n  \"userName\": [{\n    \"HTTPServer\": [[]]\n  }],\n   \"legacy\": [{\"oldName\": 1}],\n\t\"deep\": [[]]\n }",
+            expect![[r#"
+                warning: key `userName` is not snake_case [key-case]
+                 --> 2:3
+                  |
+                2 |   "userName": [{
+                  |   ^^^^^^^^^^
+
+                error: nesting exceeds 2 levels [max-depth]
+                 --> 2:16
+                  |
+                2 |   "userName": [{
+                  |                ^
+
+                warning: key `HTTPServer` is not snake_case [key-case]
+                 --> 3:5
+                  |
+                3 |     "HTTPServer": [[]]
+                  |     ^^^^^^^^^^^^
+
+                warning: indentation is 3 spaces instead of 2 spaces [indent]
+                 --> 5:4
+                  |
+                5 |    "legacy": [{"oldName": 1}],
+                  |    ^^^^^^^^
+
+                error: nesting exceeds 2 levels [max-depth]
+                 --> 5:15
+                  |
+                5 |    "legacy": [{"oldName": 1}],
+                  |               ^
+
+                warning: indentation is 1 tab instead of 2 spaces [indent]
+                 --> 6:2
+                  |
+                6 | 	"deep": [[]]
+                  | 	^^^^^^
+
+                warning: indentation is 1 space instead of none [indent]
+                 --> 7:2
+                  |
+                7 |  }
+                  |  ^
+                --- fixed
+                {
+                  "user_name": [{
+                    "http_server": [[]]
+                  }],
+                  "legacy": [{"oldName": 1}],
+                  "deep": [[]]
+                }"#]],
+        );
+        check(
+            "{}",
+            "[\n    [1,\n      2],\n  3\n]",
+            expect![[r#"
+                warning: indentation is 6 spaces instead of 8 spaces [indent]
+                 --> 3:7
+                  |
+                3 |       2],
+                  |       ^
+
+                warning: indentation is 2 spaces instead of 4 spaces [indent]
+                 --> 4:3
+                  |
+                4 |   3
+                  |   ^
+                --- fixed
+                [
+                    [1,
+                        2],
+                    3
+                ]"#]],
+        );
+    }
+
+    #[test]
+    fn huge_exponents_are_not_integers() {
+        check(
+            r#"{"rules": {"float-integers": "warning", "unsafe-numbers": "off"}}"#,
+            "[1e9223372036854775807, 1e-9223372036854775808]",
+            expect![[r#"
+                --- fixed
+                [1e9223372036854775807, 1e-9223372036854775808]"#]],
+        );
+    }
+
+    #[test]
+    fn many_lints_are_linted_in_linear_time() {
+        let text = format!(
+            "{{\"legacy\": {{{0}}}, \"current\": {{{0}}}}}",
+            (0..20_000)
+                .map(|i| format!("\"key_{}\": 1e30", i))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        let root = Parser::new(&text).parse().syntax();
+        let config = r#"{"rules": {"key-case": "warning"}, "ignore": {"$.legacy": ["key-case"]}}"#;
+        let lints = Linter::from_config(config).unwrap().lint(&root);
+        let count = |rule| lints.iter().filter(|lint| lint.rule == rule).count();
+        assert_eq!(count("key-case"), 20_000);
+        assert_eq!(count("unsafe-numbers"), 40_000);
+    }
+
+    #[test]
+    fn rejects_bad_configuration() {
+        let error = |config: &str| {
+            let diagnostic = Linter::from_config(config).err().unwrap();
+            format!("{} at {:?}", diagnostic.message, diagnostic.range)
+        };
+        expect![[r#"unknown rule `no-tabs` at 11..20"#]]
+            .assert_eq(&error(r#"{"rules": {"no-tabs": "error"}}"#));
+        expect![[r#"expected `error`, `warning` or `off` at 21..25"#]]
+            .assert_eq(&error(r#"{"rules": {"indent": "on"}}"#));
+        expect![[r#"`indent` has no option `width` at 22..32"#]]
+            .assert_eq(&error(r#"{"rules": {"indent": {"width": 2}}}"#));
+        expect![[r#"expected a whole number at 25..34"#]]
+            .assert_eq(&error(r#"{"rules": {"max-depth": {"max": -1}}}"#));
+        expect![[r#"invalid path `$[0` at 12..17"#]]
+            .assert_eq(&error(r#"{"ignore": {"$[0": ["indent"]}}"#));
+        expect![[r#"invalid path `["\ud800\udc€€"]` at 12..38"#]].assert_eq(&error(
+            r#"{"ignore": {"[\"\\ud800\\udc€€\"]": ["key-case"]}}"#,
+        ));
+        expect![[r#"expected a rule at 28..29"#]]
+            .assert_eq(&error(r#"{"ignore": {"$": ["indent", 1]}}"#));
+        expect![[r#"unknown key `rule` at 1..7"#]].assert_eq(&error(r#"{"rule": {}}"#));
+        expect![[r#"expected an object at 0..2"#]].assert_eq(&error("[]"));
+    }
+
+    proptest! {
+        #[test]
+        fn float_integer_fixes_keep_values((value, text) in value().prop_flat_map(layout)) {
+            let root = Parser::new(&text).parse().syntax();
+            let fixes: Vec<_> = NoFloatIntegers
+                .check(&root)
+                .into_iter()
+                .flat_map(|lint| lint.diagnostic.fix)
+                .collect();
+            let fixed = apply(&text, &fixes);
+            let actual = syntax_to_value(&Parser::new(&fixed).parse().syntax());
+            prop_assert_eq!(actual, Some(value));
+        }
+
+        #[test]
+        fn paths_match_breadcrumbs((_, text) in value().prop_flat_map(layout)) {
+            let root = Parser::new(&text).parse().syntax();
+            let paths = Paths::new(&root);
+            for offset in 0..=text.len() {
+                let offset = TextSize::from(offset as u32);
+                let crumbs = crate::navigation::breadcrumbs(&root, offset);
+                let expected: Vec<_> = crumbs.iter().map(|crumb| &crumb.segment).collect();
+                prop_assert_eq!(paths.at(offset), expected);
+            }
+        }
+    }
+}
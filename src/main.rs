@@ -1,261 +1,800 @@
-use chumsky::Parser;
-use json_pop::{parse_str, value::Value};
-use mimalloc_rust::*;
-use rowan_json::recursive;
-use rowan_json::{
-    chumsky::parser as chumsky_parser, parser::Parser as RowanJsonParser, syntax::SyntaxNode,
-};
-use std::time::Instant;
+//! The `rowan-json` command-line tool.
 
-#[global_allocator]
-static GLOBAL_MIMALLOC: GlobalMiMalloc = GlobalMiMalloc;
-fn main() {
-    let string = include_str!("../assets/large.json");
-    rowan_traverse(string);
-}
-
-fn rowan_traverse(string: &str) {
-    let start = Instant::now();
-    let parse = RowanJsonParser::new(string);
-    let res = parse.parse();
-    let mut _root = rowan_json::syntax::SyntaxNode::new_root(res.green_node);
-    println!("rowan {:?}", start.elapsed());
-    let iter = _root.preorder();
-    let now = Instant::now();
-    for event in iter {
-        if let rowan::WalkEvent::Leave(_) = event {}
-    }
-    traverse(_root.clone());
-    println!("traverse rowan {:?}", now.elapsed());
-    let start = Instant::now();
-    let mut _res = format!("{}", _root);
-    // let _res = format!("{}", root);
-
-    println!("stringify rowan {:?}", start.elapsed());
-
-    let start = Instant::now();
-    let mut _res = parse_str(string).unwrap();
-    // let _res = format!("{}", root);
-    println!("parse lr {:?}", start.elapsed());
-
-    let start = Instant::now();
-    traverse_lr(&mut _res);
-    println!("traverse_lr {:?}", start.elapsed());
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Read, Write as _};
+use std::path::PathBuf;
+use std::process::ExitCode;
 
-    let start = Instant::now();
-    let _string_lr = format!("{}", _res);
-    println!("stringify {:?}", start.elapsed());
+use clap::{Parser as _, Subcommand, ValueEnum};
+use rowan_json::backend::syntax_to_value;
+use rowan_json::binary::{decode_with, encode, transcode, Format, TranscodeError};
+use rowan_json::diagnostic::{Diagnostic, Severity};
+use rowan_json::format::{format, minify};
+use rowan_json::line_index::{LineIndex, WideEncoding};
+use rowan_json::lint::Linter;
+use rowan_json::options::{Dialect, ParseOptions};
+use rowan_json::parser::{Parse, Parser};
+use rowan_json::printer::{print, Style};
+use rowan_json::quickfix;
+use rowan_json::sort::{sort_keys, KeyOrder};
+use rowan_json::{toml, yaml};
 
-    let start = Instant::now();
-    json(string).unwrap();
-    println!("nom {:?}", start.elapsed());
-
-    let start = Instant::now();
-    let mut parser = recursive::Parser::new(string);
-    let mut _res = parser.parse();
-    println!("recursive {:?}", start.elapsed());
+#[derive(clap::Parser)]
+#[command(name = "rowan-json", about = "Check, format and query JSON documents")]
+struct Cli {
+    /// The flavor of JSON the input is written in.
+    #[arg(long, value_enum, global = true, default_value_t = DialectArg::Json)]
+    dialect: DialectArg,
+    #[command(subcommand)]
+    command: Command,
+}
 
-    let start = Instant::now();
-    traverse_recursive(&mut _res);
-    println!("recursive traverse {:?}", start.elapsed());
+#[derive(Subcommand)]
+enum Command {
+    /// Report syntax errors as `file:line:col` diagnostics.
+    Check {
+        /// Rewrite the files with the fixes for missing commas, unclosed
+        /// brackets and the like, and report the errors left.
+        #[arg(long)]
+        fix: bool,
+        files: Vec<PathBuf>,
+    },
+    /// Reformat with two-space indentation, keeping comments.
+    Fmt {
+        /// Only report the inputs that are not formatted.
+        #[arg(long, conflicts_with = "write")]
+        check: bool,
+        /// Rewrite the files in place instead of printing them.
+        #[arg(long)]
+        write: bool,
+        /// Sort the members of every object by key.
+        #[arg(long)]
+        sort_keys: bool,
+        files: Vec<PathBuf>,
+    },
+    /// Report style and portability problems, configured by a JSON file.
+    Lint {
+        /// The configuration, with the default rules if there is none.
+        #[arg(long)]
+        config: Option<PathBuf>,
+        files: Vec<PathBuf>,
+    },
+    /// Strip all whitespace and comments.
+    Minify { files: Vec<PathBuf> },
+    /// Print the value at a JSON pointer (`/a/0`) or path (`$.a[0]`).
+    Query { query: String, files: Vec<PathBuf> },
+    /// Dump the concrete syntax tree.
+    Tree { files: Vec<PathBuf> },
+    /// Convert between JSON, CBOR, MessagePack, YAML and TOML.
+    Convert {
+        /// The format to write.
+        #[arg(long, value_enum)]
+        to: FormatArg,
+        /// The format of the input.
+        #[arg(long, value_enum, default_value_t = FormatArg::Json)]
+        from: FormatArg,
+        files: Vec<PathBuf>,
+    },
+}
 
-    let start = Instant::now();
-    let _string = format!("{}", _res);
-    // println!("{}", _string);
-    println!("recursive stringify {:?}", start.elapsed());
+#[derive(Clone, Copy, ValueEnum)]
+enum DialectArg {
+    Json,
+    Jsonc,
+}
 
-    let start = Instant::now();
-    let res = chumsky_parser();
-    let (ast, err) = res.parse_recovery(string);
-    // println!("{:#?}", ast.unwrap());
-    println!("chumsky {:?}", start.elapsed());
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum FormatArg {
+    Json,
+    Cbor,
+    Msgpack,
+    Yaml,
+    Toml,
 }
 
-fn traverse_lr(value: &mut Value) {
-    match value {
-        Value::Number(_) => {}
-        Value::String(_) => {}
-        Value::Object(v) => {
-            v.iter_mut().for_each(|item| {
-                traverse_lr(&mut item.1);
-            });
-            // v.push(("key", Value::Bool(false)));
-        }
-        Value::Bool(_) => {}
-        Value::Null => {}
-        Value::Array(value) => {
-            value.iter_mut().for_each(|v| {
-                traverse_lr(v);
-            });
-            // value.push(Value::String("array"));
+impl FormatArg {
+    fn binary(self) -> Option<Format> {
+        match self {
+            FormatArg::Json | FormatArg::Yaml | FormatArg::Toml => None,
+            FormatArg::Cbor => Some(Format::Cbor),
+            FormatArg::Msgpack => Some(Format::MessagePack),
         }
     }
 }
 
-fn traverse_recursive(value: &mut recursive::Value) {
-    match value {
-        recursive::Value::String(_) => (),
-        recursive::Value::Boolean(_) => (),
-        recursive::Value::Null => (),
-        recursive::Value::Number(_) => (),
-        recursive::Value::Object(v) => v
-            .iter_mut()
-            .for_each(|item| traverse_recursive(&mut item.1)),
-        recursive::Value::Array(value) => {
-            value.iter_mut().for_each(|v| {
-                traverse_recursive(v);
-            });
+impl From<DialectArg> for Dialect {
+    fn from(dialect: DialectArg) -> Self {
+        match dialect {
+            DialectArg::Json => Dialect::Json,
+            DialectArg::Jsonc => Dialect::Jsonc,
         }
     }
 }
 
-use nom::{
-    branch::alt,
-    bytes::complete::{tag, take},
-    character::complete::{anychar, char, multispace0, none_of},
-    combinator::{map, map_opt, map_res, value, verify},
-    error::ParseError,
-    multi::{fold_many0, separated_list0},
-    number::complete::double,
-    sequence::{delimited, preceded, separated_pair},
-    IResult, Parser as NomParser,
-};
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let output = run(&cli, &mut io::stdin());
+    let _ = io::stdout().write_all(&output.stdout);
+    eprint!("{}", output.stderr);
+    if output.failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
 
-use std::collections::HashMap;
+/// What a command printed, and whether it failed.
+#[derive(Default)]
+struct Output {
+    stdout: Vec<u8>,
+    stderr: String,
+    failed: bool,
+}
 
-#[derive(Debug, PartialEq, Clone)]
-pub enum JsonValue {
-    Null,
-    Bool(bool),
-    Str(String),
-    Num(f64),
-    Array(Vec<JsonValue>),
-    Object(HashMap<String, JsonValue>),
+impl Output {
+    fn error(&mut self, message: impl std::fmt::Display) {
+        let _ = writeln!(self.stderr, "error: {}", message);
+        self.failed = true;
+    }
 }
 
-fn boolean(input: &str) -> IResult<&str, bool> {
-    alt((value(false, tag("false")), value(true, tag("true"))))(input)
+/// A document read from a file or from stdin.
+struct Input {
+    path: Option<PathBuf>,
+    bytes: Vec<u8>,
 }
 
-fn u16_hex(input: &str) -> IResult<&str, u16> {
-    map_res(take(4usize), |s| u16::from_str_radix(s, 16))(input)
+impl Input {
+    fn name(&self) -> String {
+        self.path
+            .as_ref()
+            .map_or("<stdin>".to_string(), |path| path.display().to_string())
+    }
 }
 
-fn unicode_escape(input: &str) -> IResult<&str, char> {
-    map_opt(
-        alt((
-            // Not a surrogate
-            map(verify(u16_hex, |cp| !(0xD800..0xE000).contains(cp)), |cp| {
-                cp as u32
-            }),
-            // See https://en.wikipedia.org/wiki/UTF-16#Code_points_from_U+010000_to_U+10FFFF for details
-            map(
-                verify(
-                    separated_pair(u16_hex, tag("\\u"), u16_hex),
-                    |(high, low)| (0xD800..0xDC00).contains(high) && (0xDC00..0xE000).contains(low),
-                ),
-                |(high, low)| {
-                    let high_ten = (high as u32) - 0xD800;
-                    let low_ten = (low as u32) - 0xDC00;
-                    (high_ten << 10) + low_ten + 0x10000
-                },
-            ),
-        )),
-        // Could probably be replaced with .unwrap() or _unchecked due to the verify checks
-        std::char::from_u32,
-    )(input)
+fn run(cli: &Cli, stdin: &mut dyn Read) -> Output {
+    let mut out = Output::default();
+    let options = ParseOptions {
+        dialect: cli.dialect.into(),
+        ..ParseOptions::default()
+    };
+    let files = match &cli.command {
+        Command::Check { files, .. }
+        | Command::Fmt { files, .. }
+        | Command::Lint { files, .. }
+        | Command::Minify { files }
+        | Command::Query { files, .. }
+        | Command::Tree { files }
+        | Command::Convert { files, .. } => files,
+    };
+    let linter = match &cli.command {
+        Command::Lint {
+            config: Some(path), ..
+        } => match load_config(path, &mut out) {
+            Some(linter) => linter,
+            None => return out,
+        },
+        _ => Linter::default(),
+    };
+    for input in read_inputs(files, stdin, &mut out) {
+        if let Command::Convert { to, from, .. } = cli.command {
+            convert(&mut out, &input, options, from, to);
+            continue;
+        }
+        let text = match std::str::from_utf8(&input.bytes) {
+            Ok(text) => text,
+            Err(_) => {
+                out.error(format_args!("{}: not valid UTF-8", input.name()));
+                continue;
+            }
+        };
+        let parse = Parser::new(text).options(options).parse();
+        match &cli.command {
+            Command::Check { fix, .. } => {
+                let fixed = match (&input.path, fix) {
+                    (_, false) => None,
+                    (Some(path), true) => {
+                        let fixed = quickfix::fix(text, options);
+                        if fixed != text {
+                            if let Err(err) = fs::write(path, &fixed) {
+                                out.error(format_args!("{}: {}", input.name(), err));
+                                continue;
+                            }
+                        }
+                        Some(fixed)
+                    }
+                    (None, true) => {
+                        out.error("--fix needs a file, not stdin");
+                        continue;
+                    }
+                };
+                let (text, parse) = match &fixed {
+                    Some(fixed) => (fixed.as_str(), Parser::new(fixed).options(options).parse()),
+                    None => (text, parse),
+                };
+                let report = report(&input, text, &parse.errors);
+                out.stdout.extend_from_slice(report.as_bytes());
+                out.failed |= !parse.ok();
+            }
+            Command::Tree { .. } => {
+                let _ = write!(out.stdout, "{:#?}", parse.syntax());
+                fail_on_errors(&mut out, &input, text, &parse);
+            }
+            _ if fail_on_errors(&mut out, &input, text, &parse) => {}
+            Command::Fmt {
+                check,
+                write,
+                sort_keys: sort,
+                ..
+            } => {
+                let mut root = parse.syntax();
+                if *sort {
+                    root = sort_keys(&root, &KeyOrder::Alphabetical, true);
+                }
+                let formatted = format!("{}\n", format(&root));
+                match (&input.path, check, write) {
+                    (_, true, _) if formatted != text => {
+                        out.error(format_args!("{} is not formatted", input.name()))
+                    }
+                    (_, true, _) => {}
+                    (Some(path), _, true) => {
+                        if formatted != text {
+                            if let Err(err) = fs::write(path, formatted) {
+                                out.error(format_args!("{}: {}", input.name(), err));
+                            }
+                        }
+                    }
+                    (None, _, true) => out.error("--write needs a file, not stdin"),
+                    _ => out.stdout.extend_from_slice(formatted.as_bytes()),
+                }
+            }
+            Command::Lint { .. } => {
+                let lints = linter.lint(&parse.syntax());
+                let diagnostics: Vec<_> = lints
+                    .iter()
+                    .map(|lint| Diagnostic {
+                        message: format!("{} [{}]", lint.diagnostic.message, lint.rule),
+                        ..lint.diagnostic.clone()
+                    })
+                    .collect();
+                let report = report(&input, text, &diagnostics);
+                out.stdout.extend_from_slice(report.as_bytes());
+                out.failed |= diagnostics.iter().any(|d| d.severity == Severity::Error);
+            }
+            Command::Minify { .. } => {
+                let _ = writeln!(out.stdout, "{}", minify(&parse.syntax()));
+            }
+            Command::Query { query, .. } => {
+                let value = syntax_to_value(&parse.syntax());
+                let found = value.as_ref().and_then(|value| {
+                    if query.is_empty() || query.starts_with('/') {
+                        value.pointer(query)
+                    } else {
+                        value.path(query)
+                    }
+                });
+                match found {
+                    Some(found) => {
+                        let _ = writeln!(out.stdout, "{}", print(found, Style::Pretty));
+                    }
+                    None => out.error(format_args!("{}: nothing at `{}`", input.name(), query)),
+                }
+            }
+            Command::Convert { .. } => unreachable!(),
+        }
+    }
+    out
 }
 
-fn character(input: &str) -> IResult<&str, char> {
-    let (input, c) = none_of("\"")(input)?;
-    if c == '\\' {
-        alt((
-            map_res(anychar, |c| {
-                Ok(match c {
-                    '"' | '\\' | '/' => c,
-                    'b' => '\x08',
-                    'f' => '\x0C',
-                    'n' => '\n',
-                    'r' => '\r',
-                    't' => '\t',
-                    _ => return Err(()),
-                })
-            }),
-            preceded(char('u'), unicode_escape),
-        ))(input)
-    } else {
-        Ok((input, c))
+/// Writes `input` in the format `to`. JSON goes straight to a binary
+/// format without building a value.
+fn convert(out: &mut Output, input: &Input, options: ParseOptions, from: FormatArg, to: FormatArg) {
+    let value = match from.binary() {
+        Some(format) => match decode_with(&input.bytes, format, options) {
+            Ok(value) => value,
+            Err(err) => return out.error(format_args!("{}: {}", input.name(), err)),
+        },
+        None => {
+            let text = match std::str::from_utf8(&input.bytes) {
+                Ok(text) => text,
+                Err(_) => return out.error(format_args!("{}: not valid UTF-8", input.name())),
+            };
+            let parsed = match from {
+                FormatArg::Yaml => yaml::parse(text),
+                FormatArg::Toml => toml::parse(text),
+                _ => {
+                    if let Some(format) = to.binary() {
+                        let mut bytes = vec![];
+                        return match transcode(text, options, format, &mut bytes) {
+                            Ok(()) => out.stdout.extend_from_slice(&bytes),
+                            Err(TranscodeError::Syntax(diagnostic)) => {
+                                out.stderr += &report(input, text, &[diagnostic]);
+                                out.failed = true;
+                            }
+                            Err(TranscodeError::Io(err)) => {
+                                out.error(format_args!("{}: {}", input.name(), err))
+                            }
+                        };
+                    }
+                    let parse = Parser::new(text).options(options).parse();
+                    if fail_on_errors(out, input, text, &parse) {
+                        return;
+                    }
+                    Ok(syntax_to_value(&parse.syntax()).unwrap())
+                }
+            };
+            match parsed {
+                Ok(value) => value,
+                Err(diagnostic) => {
+                    out.stderr += &report(input, text, &[diagnostic]);
+                    out.failed = true;
+                    return;
+                }
+            }
+        }
+    };
+    let written = match to {
+        FormatArg::Yaml => yaml::emit(&value).map_err(|err| err.to_string()),
+        FormatArg::Toml => toml::emit(&value).map_err(|err| err.to_string()),
+        _ => match to.binary() {
+            Some(format) => match encode(&value, format) {
+                Ok(bytes) => return out.stdout.extend_from_slice(&bytes),
+                Err(err) => Err(err.to_string()),
+            },
+            None => Ok(print(&value, Style::Pretty) + "\n"),
+        },
+    };
+    match written {
+        Ok(text) => out.stdout.extend_from_slice(text.as_bytes()),
+        Err(err) => out.error(format_args!("{}: {}", input.name(), err)),
     }
 }
 
-fn string(input: &str) -> IResult<&str, String> {
-    delimited(
-        char('"'),
-        fold_many0(character, String::new, |mut string, c| {
-            string.push(c);
-            string
-        }),
-        char('"'),
-    )(input)
+/// Reads the lint configuration at `path`, reporting what is wrong with it.
+fn load_config(path: &PathBuf, out: &mut Output) -> Option<Linter> {
+    let input = read_inputs(std::slice::from_ref(path), &mut io::empty(), out).pop()?;
+    let Ok(text) = std::str::from_utf8(&input.bytes) else {
+        out.error(format_args!("{}: not valid UTF-8", input.name()));
+        return None;
+    };
+    match Linter::from_config(text) {
+        Ok(linter) => Some(linter),
+        Err(diagnostic) => {
+            out.stderr += &report(&input, text, &[diagnostic]);
+            out.failed = true;
+            None
+        }
+    }
 }
 
-fn ws<'a, O, E: ParseError<&'a str>, F: NomParser<&'a str, O, E>>(
-    f: F,
-) -> impl NomParser<&'a str, O, E> {
-    delimited(multispace0, f, multispace0)
+/// Reads every file in `files`, or stdin if there are none.
+fn read_inputs(files: &[PathBuf], stdin: &mut dyn Read, out: &mut Output) -> Vec<Input> {
+    if files.is_empty() {
+        let mut bytes = vec![];
+        return match stdin.read_to_end(&mut bytes) {
+            Ok(_) => vec![Input { path: None, bytes }],
+            Err(err) => {
+                out.error(format_args!("<stdin>: {}", err));
+                vec![]
+            }
+        };
+    }
+    let mut inputs = vec![];
+    for path in files {
+        match fs::read(path) {
+            Ok(bytes) => inputs.push(Input {
+                path: Some(path.clone()),
+                bytes,
+            }),
+            Err(err) => out.error(format_args!("{}: {}", path.display(), err)),
+        }
+    }
+    inputs
 }
 
-fn array(input: &str) -> IResult<&str, Vec<JsonValue>> {
-    delimited(
-        char('['),
-        ws(separated_list0(ws(char(',')), json_value)),
-        char(']'),
-    )(input)
+/// Reports the syntax errors of `parse` on stderr, returning whether there
+/// were any.
+fn fail_on_errors(out: &mut Output, input: &Input, text: &str, parse: &Parse) -> bool {
+    if parse.ok() {
+        return false;
+    }
+    out.stderr.push_str(&report(input, text, &parse.errors));
+    out.failed = true;
+    true
 }
 
-fn object(input: &str) -> IResult<&str, HashMap<String, JsonValue>> {
-    map(
-        delimited(
-            char('{'),
-            ws(separated_list0(
-                ws(char(',')),
-                separated_pair(string, ws(char(':')), json_value),
-            )),
-            char('}'),
-        ),
-        |key_values| key_values.into_iter().collect(),
-    )(input)
+/// One `file:line:col: severity: message` line per diagnostic, with
+/// 1-based lines and columns counted in characters.
+fn report(input: &Input, text: &str, diagnostics: &[Diagnostic]) -> String {
+    let index = LineIndex::new(text);
+    let mut report = String::new();
+    for diagnostic in diagnostics {
+        let position = index.to_wide(
+            WideEncoding::Utf32,
+            index.line_col(diagnostic.range.start()),
+        );
+        let severity = match diagnostic.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let _ = writeln!(
+            report,
+            "{}:{}:{}: {}: {}",
+            input.name(),
+            position.line + 1,
+            position.col + 1,
+            severity,
+            diagnostic.message
+        );
+    }
+    report
 }
 
-fn json_value(input: &str) -> IResult<&str, JsonValue> {
-    use JsonValue::*;
+#[cfg(test)]
+mod tests {
+    use expect_test::{expect, Expect};
 
-    alt((
-        value(Null, tag("null")),
-        map(boolean, Bool),
-        map(string, Str),
-        map(double, Num),
-        map(array, Array),
-        map(object, Object),
-    ))(input)
-}
+    use super::*;
 
-fn json(input: &str) -> IResult<&str, JsonValue> {
-    ws(json_value).parse(input)
-}
+    fn check(args: &[&str], stdin: &str, expected: Expect) {
+        let cli = Cli::try_parse_from(["rowan-json"].iter().chain(args)).unwrap();
+        let output = run(&cli, &mut stdin.as_bytes());
+        let actual = format!(
+            "failed: {}\n--- stdout\n{}--- stderr\n{}",
+            output.failed,
+            String::from_utf8_lossy(&output.stdout),
+            output.stderr
+        );
+        expected.assert_eq(&actual);
+    }
+
+    #[test]
+    fn check_reports_positions() {
+        check(
+            &["check"],
+            "{\n  \"é\": [1 2],\n  // note\n}",
+            expect![[r#"
+                failed: true
+                --- stdout
+                <stdin>:2:11: error: expected `,`
+                <stdin>:2:13: error: trailing commas are not allowed in JSON
+                <stdin>:3:3: error: comments are not allowed in JSON
+                --- stderr
+            "#]],
+        );
+        check(
+            &["check", "--dialect", "jsonc"],
+            "{\"a\": 1, // note\n}",
+            expect![[r#"
+                failed: false
+                --- stdout
+                --- stderr
+            "#]],
+        );
+    }
+
+    #[test]
+    fn check_fixes_files() {
+        let path = std::env::temp_dir().join(format!("rowan-json-fix-{}.json", std::process::id()));
+        fs::write(&path, "{a: [1 2,], 'b': NaN, \"c\": {\"d\": true\n").unwrap();
+        let file = path.to_str().unwrap();
+        check(
+            &["check", "--fix", file],
+            "",
+            expect![[r#"
+                failed: false
+                --- stdout
+                --- stderr
+            "#]],
+        );
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "{\"a\": [1, 2], \"b\": null, \"c\": {\"d\": true}}\n"
+        );
+        fs::write(&path, "[1, x]").unwrap();
+        let cli = Cli::try_parse_from(["rowan-json", "check", "--fix", file]).unwrap();
+        let output = run(&cli, &mut io::empty());
+        assert!(output.failed);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[1, x]");
+        fs::remove_file(&path).unwrap();
+        check(
+            &["check", "--fix"],
+            "[1 2]",
+            expect![[r#"
+                failed: true
+                --- stdout
+                --- stderr
+                error: --fix needs a file, not stdin
+            "#]],
+        );
+    }
+
+    #[test]
+    fn fmt_and_minify() {
+        let input = "{\"a\": [1, 2.50], /* b */ \"b\": {}}";
+        check(
+            &["fmt", "--dialect", "jsonc"],
+            input,
+            expect![[r#"
+                failed: false
+                --- stdout
+                {
+                  "a": [
+                    1,
+                    2.50
+                  ],
+                  /* b */ "b": {}
+                }
+                --- stderr
+            "#]],
+        );
+        check(
+            &["fmt", "--sort-keys", "--dialect", "jsonc"],
+            "{\"b\": {\"d\": 1, \"c\": 2}, // b\n\"a\": []}",
+            expect![[r#"
+                failed: false
+                --- stdout
+                {
+                  "a": [],
+                  "b": {
+                    "c": 2,
+                    "d": 1
+                  } // b
+                }
+                --- stderr
+            "#]],
+        );
+        check(
+            &["fmt", "--check"],
+            "[1]",
+            expect![[r#"
+                failed: true
+                --- stdout
+                --- stderr
+                error: <stdin> is not formatted
+            "#]],
+        );
+        check(
+            &["minify", "--dialect", "jsonc"],
+            input,
+            expect![[r#"
+                failed: false
+                --- stdout
+                {"a":[1,2.50],"b":{}}
+                --- stderr
+            "#]],
+        );
+        check(
+            &["minify"],
+            "[1,",
+            expect![[r#"
+                failed: true
+                --- stdout
+                --- stderr
+                <stdin>:1:4: error: expected value
+                <stdin>:1:4: error: expected `]`
+            "#]],
+        );
+    }
+
+    #[test]
+    fn fmt_writes_files() {
+        let path = std::env::temp_dir().join(format!("rowan-json-{}.json", std::process::id()));
+        fs::write(&path, "[1,2]").unwrap();
+        let file = path.to_str().unwrap();
+        check(
+            &["fmt", "--write", file],
+            "",
+            expect![[r#"
+                failed: false
+                --- stdout
+                --- stderr
+            "#]],
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[\n  1,\n  2\n]\n");
+        let cli = Cli::try_parse_from(["rowan-json", "fmt", "--check", file]).unwrap();
+        assert!(!run(&cli, &mut io::empty()).failed);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn lint_with_config() {
+        let input = "{\"a_b\": 1.0, \"c\": {\"a_b\": 2}, \"c\": 99999999999999999}";
+        check(
+            &["lint"],
+            input,
+            expect![[r#"
+                failed: true
+                --- stdout
+                <stdin>:1:31: error: duplicate key `c` [duplicate-keys]
+                <stdin>:1:36: warning: `99999999999999999` loses precision as a JavaScript number [unsafe-numbers]
+                --- stderr
+            "#]],
+        );
+        let path =
+            std::env::temp_dir().join(format!("rowan-json-lint-{}.json", std::process::id()));
+        let config = path.to_str().unwrap();
+        fs::write(
+            &path,
+            r#"{"rules": {"duplicate-keys": "warning", "key-case": {}}, "ignore": {"$.c": ["key-case"]}}"#,
+        )
+        .unwrap();
+        check(
+            &["lint", "--config", config],
+            input,
+            expect![[r#"
+                failed: false
+                --- stdout
+                <stdin>:1:2: warning: key `a_b` is not camelCase [key-case]
+                <stdin>:1:31: warning: duplicate key `c` [duplicate-keys]
+                <stdin>:1:36: warning: `99999999999999999` loses precision as a JavaScript number [unsafe-numbers]
+                --- stderr
+            "#]],
+        );
+        fs::write(&path, r#"{"rules": {"key-case": {"style": "kebab-case"}}}"#).unwrap();
+        let cli = Cli::try_parse_from(["rowan-json", "lint", "--config", config]).unwrap();
+        let output = run(&cli, &mut input.as_bytes());
+        assert_eq!(
+            output.stderr,
+            format!(
+                "{}:1:25: error: expected `camelCase` or `snake_case`\n",
+                config
+            )
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn query_and_tree() {
+        let input = r#"{"a": [{"b": "x"}, true]}"#;
+        check(
+            &["query", "/a/0"],
+            input,
+            expect![[r#"
+                failed: false
+                --- stdout
+                {
+                  "b": "x"
+                }
+                --- stderr
+            "#]],
+        );
+        check(
+            &["query", "$.a[1]"],
+            input,
+            expect![[r#"
+                failed: false
+                --- stdout
+                true
+                --- stderr
+            "#]],
+        );
+        check(
+            &["query", "a.2"],
+            input,
+            expect![[r#"
+                failed: true
+                --- stdout
+                --- stderr
+                error: <stdin>: nothing at `a.2`
+            "#]],
+        );
+        check(
+            &["tree"],
+            "[1]",
+            expect![[r#"
+                failed: false
+                --- stdout
+                Root@0..3
+                  Array@0..3
+                    LeftBracket@0..1 "["
+                    Number@1..2 "1"
+                    RightBracket@2..3 "]"
+                --- stderr
+            "#]],
+        );
+    }
+
+    #[test]
+    fn convert_between_formats() {
+        let convert = |args: &[&str], stdin: &[u8]| {
+            let cli = Cli::try_parse_from(["rowan-json", "convert"].iter().chain(args)).unwrap();
+            let output = run(&cli, &mut &stdin[..]);
+            assert!(!output.failed, "{}", output.stderr);
+            output.stdout
+        };
+        let input_text = r#"{"a": [1, -2.5, "x"], "b": null}"#;
+        let input = input_text.as_bytes();
+        let cbor = convert(&["--to", "cbor"], input);
+        assert_eq!(cbor, b"\xa2\x61a\x83\x01\xfa\xc0\x20\x00\x00\x61x\x61b\xf6");
+        let msgpack = convert(&["--from", "cbor", "--to", "msgpack"], &cbor);
+        let json = convert(&["--from", "msgpack", "--to", "json"], &msgpack);
+        expect![[r#"
+            {
+              "a": [
+                1,
+                -2.5,
+                "x"
+              ],
+              "b": null
+            }
+        "#]]
+        .assert_eq(&String::from_utf8(json).unwrap());
 
-// fn std_float(input: &[u8]) -> IResult<&[u8], f64, (&[u8], ErrorKind)> {
-//     match recognize_float(input) {
-//         Err(e) => Err(e),
-//         Ok((i, s)) => match s.parse_to() {
-//             Some(n) => Ok((i, n)),
-//             None => Err(Err::Error((i, ErrorKind::Float))),
-//         },
-//     }
-// }
+        check(
+            &["convert", "--to", "cbor"],
+            "[1,",
+            expect![[r#"
+                failed: true
+                --- stdout
+                --- stderr
+                <stdin>:1:4: error: expected value
+            "#]],
+        );
+        let yaml = convert(&["--to", "yaml"], input);
+        expect![[r#"
+            a:
+              - 1
+              - -2.5
+              - x
+            b: null
+        "#]]
+        .assert_eq(&String::from_utf8(yaml).unwrap());
+        let toml = convert(
+            &["--from", "yaml", "--to", "toml"],
+            b"a: [1, -2.5, x]\nb: {c: true}\n",
+        );
+        expect![[r#"
+            a = [1, -2.5, "x"]
 
-fn traverse(root: SyntaxNode) {
-    for child in root.children() {
-        traverse(child);
+            [b]
+            c = true
+        "#]]
+        .assert_eq(&String::from_utf8(toml.clone()).unwrap());
+        assert_eq!(
+            convert(&["--from", "toml", "--to", "cbor"], &toml),
+            convert(
+                &["--from", "yaml", "--to", "cbor"],
+                b"{a: [1, -2.5, x], b: {c: true}}"
+            )
+        );
+        check(
+            &["convert", "--to", "toml"],
+            input_text,
+            expect![[r#"
+            failed: true
+            --- stdout
+            --- stderr
+            error: <stdin>: `null` at `/b` has no TOML equivalent
+        "#]],
+        );
+        check(
+            &["convert", "--from", "toml", "--to", "json"],
+            "a = 1979-05-27",
+            expect![[r#"
+                failed: true
+                --- stdout
+                --- stderr
+                <stdin>:1:5: error: TOML datetimes have no JSON equivalent
+            "#]],
+        );
+        check(
+            &["convert", "--from", "yaml", "--to", "json"],
+            "a: !x 1",
+            expect![[r#"
+                failed: true
+                --- stdout
+                --- stderr
+                <stdin>:1:4: error: YAML tags are not supported
+            "#]],
+        );
+        let cli = Cli::try_parse_from(["rowan-json", "convert", "--from", "cbor", "--to", "json"])
+            .unwrap();
+        let output = run(&cli, &mut &b"\x82\x01"[..]);
+        assert_eq!(
+            output.stderr,
+            "error: <stdin>: unexpected end of input at byte 2\n"
+        );
     }
 }
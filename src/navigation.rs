@@ -0,0 +1,291 @@
+//! The structure of a document as editors show it: folding ranges, an
+//! outline of keys and the breadcrumbs at a position.
+//!
+//! All three work on trees with errors, leaving out what the parser could
+//! not make sense of.
+
+use rowan::{NodeOrToken, TextRange, TextSize};
+
+use crate::escape::unescape;
+use crate::lexer::SyntaxKind;
+use crate::syntax::{elements, members, SyntaxElement, SyntaxNode, ValueKind};
+
+/// A container that spans more than one line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoldingRange {
+    /// From the opening delimiter to the closing one.
+    pub range: TextRange,
+    /// [`Object`](ValueKind::Object) or [`Array`](ValueKind::Array).
+    pub kind: ValueKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutlineItem {
+    /// The key of a member, or the identity or index of an array element.
+    pub label: String,
+    pub kind: ValueKind,
+    /// The value.
+    pub range: TextRange,
+    /// The key, for members.
+    pub key_range: Option<TextRange>,
+    pub children: Vec<OutlineItem>,
+}
+
+/// A step on the path from the root to a value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breadcrumb {
+    pub segment: Segment,
+    pub kind: ValueKind,
+    /// The value the segment leads to.
+    pub range: TextRange,
+}
+
+/// Every object and array that spans more than one line, outermost first.
+pub fn folding_ranges(root: &SyntaxNode) -> Vec<FoldingRange> {
+    root.descendants()
+        .filter(|node| node.text().contains_char('\n'))
+        .filter_map(|node| {
+            let kind = ValueKind::of(&NodeOrToken::Node(node.clone()))?;
+            let range = node.text_range();
+            Some(FoldingRange { range, kind })
+        })
+        .filter(|folding| matches!(folding.kind, ValueKind::Object | ValueKind::Array))
+        .collect()
+}
+
+/// The members and elements under the root value. An array element is
+/// labeled with the value of its `identity` member if it is an object with
+/// a string or number there, and with its index otherwise.
+pub fn outline(root: &SyntaxNode, identity: Option<&str>) -> Vec<OutlineItem> {
+    root_value(root).map_or(vec![], |value| children(&value, identity))
+}
+
+/// The path from the root to the innermost value at `offset`. A member is
+/// on the path from the start of its key, and every value up to its end.
+pub fn breadcrumbs(root: &SyntaxNode, offset: TextSize) -> Vec<Breadcrumb> {
+    let mut crumbs = vec![];
+    let mut current = root_value(root);
+    while let Some(NodeOrToken::Node(node)) = current.take() {
+        let next = match node.kind() {
+            SyntaxKind::Object => members(&node)
+                .into_iter()
+                .find(|member| member.range().contains_inclusive(offset))
+                .and_then(|member| Some((Segment::Key(member.name()), member.value?))),
+            SyntaxKind::Array => elements(&node)
+                .into_iter()
+                .enumerate()
+                .find(|(_, element)| element.text_range().contains_inclusive(offset))
+                .map(|(i, element)| (Segment::Index(i), element)),
+            _ => None,
+        };
+        let Some((segment, value)) = next else { break };
+        let Some(kind) = ValueKind::of(&value) else {
+            break;
+        };
+        crumbs.push(Breadcrumb {
+            segment,
+            kind,
+            range: value.text_range(),
+        });
+        current = Some(value);
+    }
+    crumbs
+}
+
+fn root_value(root: &SyntaxNode) -> Option<SyntaxElement> {
+    root.children_with_tokens()
+        .find(|element| !element.kind().is_trivia())
+}
+
+fn children(value: &SyntaxElement, identity: Option<&str>) -> Vec<OutlineItem> {
+    let Some(node) = value.as_node() else {
+        return vec![];
+    };
+    match node.kind() {
+        SyntaxKind::Object => members(node)
+            .into_iter()
+            .filter_map(|member| {
+                let key_range = Some(member.key.text_range());
+                item(member.name(), key_range, &member.value?, identity)
+            })
+            .collect(),
+        SyntaxKind::Array => elements(node)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, element)| {
+                let label = identity
+                    .and_then(|identity| identity_of(&element, identity))
+                    .unwrap_or_else(|| i.to_string());
+                item(label, None, &element, identity)
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn item(
+    label: String,
+    key_range: Option<TextRange>,
+    value: &SyntaxElement,
+    identity: Option<&str>,
+) -> Option<OutlineItem> {
+    Some(OutlineItem {
+        label,
+        kind: ValueKind::of(value)?,
+        range: value.text_range(),
+        key_range,
+        children: children(value, identity),
+    })
+}
+
+/// The value of the `identity` member of `element`, if it is a string or
+/// a number.
+fn identity_of(element: &SyntaxElement, identity: &str) -> Option<String> {
+    let object = element
+        .as_node()
+        .filter(|node| node.kind() == SyntaxKind::Object)?;
+    let value = members(object)
+        .into_iter()
+        .find(|member| member.name() == identity)?
+        .value?
+        .into_token()?;
+    let text = value.text();
+    match value.kind() {
+        SyntaxKind::String => Some(unescape(&text[1..text.len() - 1]).into_owned()),
+        SyntaxKind::Number => Some(text.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+
+    use expect_test::expect;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::parser::Parser;
+    use crate::strategies::{layout, value};
+    use crate::value::Value;
+
+    const TEXT: &str = r#"{
+  "name": "app",
+  "jobs": [
+    {"id": "build", "steps": [1, 2]},
+    {"id": 7},
+    {"steps": {}}
+  ],
+  "broken": ,
+  "empty": []
+}"#;
+
+    fn dump(out: &mut String, items: &[OutlineItem], depth: usize) {
+        for item in items {
+            let _ = writeln!(
+                out,
+                "{:indent$}{} {:?} {:?} {:?}",
+                "",
+                item.label,
+                item.kind,
+                item.range,
+                item.key_range,
+                indent = depth * 2
+            );
+            dump(out, &item.children, depth + 1);
+        }
+    }
+
+    #[test]
+    fn outlines_keys_and_elements() {
+        let root = Parser::new(TEXT).parse().syntax();
+        let mut out = String::new();
+        dump(&mut out, &outline(&root, Some("id")), 0);
+        expect![[r#"
+            name String 12..17 Some(4..10)
+            jobs Array 29..105 Some(21..27)
+              build Object 35..67 None
+                id String 42..49 Some(36..40)
+                steps Array 60..66 Some(51..58)
+                  0 Number 61..62 None
+                  1 Number 64..65 None
+              7 Object 73..82 None
+                id Number 80..81 Some(74..78)
+              2 Object 88..101 None
+                steps Object 98..100 Some(89..96)
+            empty Array 132..134 Some(123..130)
+        "#]]
+        .assert_eq(&out);
+        expect![[r#"
+            [
+                FoldingRange {
+                    range: 0..136,
+                    kind: Object,
+                },
+                FoldingRange {
+                    range: 29..105,
+                    kind: Array,
+                },
+            ]
+        "#]]
+        .assert_debug_eq(&folding_ranges(&root));
+    }
+
+    #[test]
+    fn finds_breadcrumbs() {
+        let root = Parser::new(TEXT).parse().syntax();
+        let crumbs = |needle: &str| {
+            let offset = TextSize::from(TEXT.find(needle).unwrap() as u32);
+            let crumbs: Vec<_> = breadcrumbs(&root, offset)
+                .into_iter()
+                .map(|crumb| match crumb.segment {
+                    Segment::Key(key) => format!("{} {:?}", key, crumb.kind),
+                    Segment::Index(i) => format!("{} {:?}", i, crumb.kind),
+                })
+                .collect();
+            crumbs.join(" > ")
+        };
+        expect![[r#"jobs Array > 0 Object > steps Array > 1 Number"#]].assert_eq(&crumbs("2]"));
+        expect![[r#"jobs Array > 1 Object > id Number"#]].assert_eq(&crumbs(r#""id": 7"#));
+        expect![[r#"name String"#]].assert_eq(&crumbs(r#""name""#));
+        expect![[r#"name String"#]].assert_eq(&crumbs(","));
+        expect![[""]].assert_eq(&crumbs("{\n"));
+    }
+
+    fn labels(value: &Value) -> Vec<(String, Vec<String>)> {
+        let children = |value: &Value| labels(value).into_iter().map(|(label, _)| label).collect();
+        match value {
+            Value::Object(members) => members
+                .iter()
+                .map(|(key, value)| (key.clone(), children(value)))
+                .collect(),
+            Value::Array(elements) => elements
+                .iter()
+                .enumerate()
+                .map(|(i, value)| (i.to_string(), children(value)))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn outlines_every_member((value, text) in value().prop_flat_map(layout)) {
+            let items = outline(&Parser::new(&text).parse().syntax(), None);
+            let actual: Vec<_> = items
+                .iter()
+                .map(|item| {
+                    let children = item.children.iter().map(|child| child.label.clone()).collect();
+                    (item.label.clone(), children)
+                })
+                .collect();
+            prop_assert_eq!(actual, labels(&value));
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Newline-delimited JSON (JSON Lines): one document per line.
+//!
+//! Every non-blank line is parsed on its own, so a malformed line only
+//! produces a diagnostic for that line and the following lines are still
+//! parsed. Diagnostic ranges are relative to the start of the line; use
+//! [`Line::offset`] to map them back into the whole input.
+
+use rayon::prelude::*;
+
+use crate::backend::{JsonBackend, Rowan};
+use crate::diagnostic::Diagnostic;
+use crate::parser::{Parse, Parser};
+use crate::value::Value;
+
+#[derive(Debug, Clone)]
+pub struct Line<'a, T> {
+    /// 1-based line number.
+    pub number: usize,
+    /// Byte offset of the start of the line in the input.
+    pub offset: usize,
+    pub text: &'a str,
+    pub output: T,
+}
+
+/// Yields the non-blank lines of `input` without their line terminator.
+pub fn lines(input: &str) -> impl Iterator<Item = Line<'_, ()>> {
+    let mut offset = 0;
+    input
+        .split_inclusive('\n')
+        .enumerate()
+        .filter_map(move |(index, raw)| {
+            let start = offset;
+            offset += raw.len();
+            let text = raw.strip_suffix('\n').unwrap_or(raw);
+            let text = text.strip_suffix('\r').unwrap_or(text);
+            if text.trim().is_empty() {
+                return None;
+            }
+            Some(Line {
+                number: index + 1,
+                offset: start,
+                text,
+                output: (),
+            })
+        })
+}
+
+/// Parses every line into a lossless tree with its diagnostics.
+pub fn parse(input: &str) -> impl Iterator<Item = Line<'_, Parse>> {
+    lines(input).map(|line| line.map(|text| Parser::new(text).parse()))
+}
+
+/// Parses every line into a [`Value`], reporting the first error of a line.
+pub fn values(input: &str) -> impl Iterator<Item = Line<'_, Result<Value, Diagnostic>>> {
+    lines(input).map(|line| line.map(value))
+}
+
+/// Like [`parse`], but parses the lines on the rayon thread pool.
+pub fn par_parse(input: &str) -> Vec<Line<'_, Parse>> {
+    let lines: Vec<_> = lines(input).collect();
+    lines
+        .into_par_iter()
+        .map(|line| line.map(|text| Parser::new(text).parse()))
+        .collect()
+}
+
+/// Like [`values`], but parses the lines on the rayon thread pool.
+pub fn par_values(input: &str) -> Vec<Line<'_, Result<Value, Diagnostic>>> {
+    let lines: Vec<_> = lines(input).collect();
+    lines.into_par_iter().map(|line| line.map(value)).collect()
+}
+
+fn value(text: &str) -> Result<Value, Diagnostic> {
+    Rowan
+        .parse_value(text)
+        .map_err(|mut errors| errors.swap_remove(0))
+}
+
+impl<'a> Line<'a, ()> {
+    fn map<T>(self, f: impl FnOnce(&'a str) -> T) -> Line<'a, T> {
+        Line {
+            number: self.number,
+            offset: self.offset,
+            text: self.text,
+            output: f(self.text),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "{\"a\": 1}\r\n\n[1, 2\n\"ok\"\n";
+
+    #[test]
+    fn malformed_line_does_not_stop_parsing() {
+        let parsed: Vec<_> = parse(INPUT).collect();
+        let summary: Vec<_> = parsed
+            .iter()
+            .map(|line| (line.number, line.offset, line.output.errors.len()))
+            .collect();
+        assert_eq!(summary, [(1, 0, 0), (3, 11, 1), (4, 17, 0)]);
+        assert_eq!(parsed[1].output.syntax().to_string(), "[1, 2");
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        let sequential: Vec<_> = values(INPUT).map(|line| line.output).collect();
+        let parallel: Vec<_> = par_values(INPUT)
+            .into_iter()
+            .map(|line| line.output)
+            .collect();
+        assert_eq!(sequential, parallel);
+        assert_eq!(sequential[2], Ok(Value::String("ok".to_string())));
+
+        let trees: Vec<_> = par_parse(INPUT)
+            .into_iter()
+            .map(|line| line.output.green_node)
+            .collect();
+        let expected: Vec<_> = parse(INPUT).map(|line| line.output.green_node).collect();
+        assert_eq!(trees, expected);
+    }
+}
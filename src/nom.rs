@@ -0,0 +1,153 @@
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take},
+    character::complete::{anychar, char, digit0, digit1, multispace0, one_of, satisfy},
+    combinator::{map, map_opt, map_res, opt, recognize, value, verify},
+    error::ParseError,
+    multi::{fold_many0, separated_list0},
+    sequence::{delimited, pair, preceded, separated_pair, tuple},
+    IResult, Parser,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Str(String),
+    Num(f64),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+fn boolean(input: &str) -> IResult<&str, bool> {
+    alt((value(false, tag("false")), value(true, tag("true"))))(input)
+}
+
+fn u16_hex(input: &str) -> IResult<&str, u16> {
+    // `from_str_radix` alone would also take a leading `+`.
+    map_res(
+        verify(take(4usize), |s: &str| {
+            s.chars().all(|c| c.is_ascii_hexdigit())
+        }),
+        |s| u16::from_str_radix(s, 16),
+    )(input)
+}
+
+fn unicode_escape(input: &str) -> IResult<&str, char> {
+    map_opt(
+        alt((
+            // See https://en.wikipedia.org/wiki/UTF-16#Code_points_from_U+010000_to_U+10FFFF for details
+            map(
+                verify(
+                    separated_pair(u16_hex, tag("\\u"), u16_hex),
+                    |(high, low)| (0xD800..0xDC00).contains(high) && (0xDC00..0xE000).contains(low),
+                ),
+                |(high, low)| {
+                    let high_ten = (high as u32) - 0xD800;
+                    let low_ten = (low as u32) - 0xDC00;
+                    (high_ten << 10) + low_ten + 0x10000
+                },
+            ),
+            // A lone surrogate decodes to U+FFFD, like `escape::unescape`.
+            map(u16_hex, |cp| {
+                if (0xD800..0xE000).contains(&cp) {
+                    0xFFFD
+                } else {
+                    cp as u32
+                }
+            }),
+        )),
+        // Could probably be replaced with .unwrap() or _unchecked due to the verify checks
+        std::char::from_u32,
+    )(input)
+}
+
+fn character(input: &str) -> IResult<&str, char> {
+    let (input, c) = verify(anychar, |&c| c != '"' && c >= ' ')(input)?;
+    if c == '\\' {
+        alt((
+            map_res(anychar, |c| {
+                Ok(match c {
+                    '"' | '\\' | '/' => c,
+                    'b' => '\x08',
+                    'f' => '\x0C',
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    _ => return Err(()),
+                })
+            }),
+            preceded(char('u'), unicode_escape),
+        ))(input)
+    } else {
+        Ok((input, c))
+    }
+}
+
+fn string(input: &str) -> IResult<&str, String> {
+    delimited(
+        char('"'),
+        fold_many0(character, String::new, |mut string, c| {
+            string.push(c);
+            string
+        }),
+        char('"'),
+    )(input)
+}
+
+/// A number in JSON's grammar; unlike `nom::number::complete::double`
+/// this rejects `1.`, `.5`, `+1`, `01`, `inf` and `nan`.
+fn number(input: &str) -> IResult<&str, f64> {
+    map_res(
+        recognize(tuple((
+            opt(char('-')),
+            alt((
+                tag("0"),
+                recognize(pair(satisfy(|c| c.is_ascii_digit() && c != '0'), digit0)),
+            )),
+            opt(pair(char('.'), digit1)),
+            opt(tuple((one_of("eE"), opt(one_of("+-")), digit1))),
+        ))),
+        str::parse,
+    )(input)
+}
+
+fn ws<'a, O, E: ParseError<&'a str>, F: Parser<&'a str, O, E>>(f: F) -> impl Parser<&'a str, O, E> {
+    delimited(multispace0, f, multispace0)
+}
+
+fn array(input: &str) -> IResult<&str, Vec<JsonValue>> {
+    delimited(
+        char('['),
+        ws(separated_list0(ws(char(',')), json_value)),
+        char(']'),
+    )(input)
+}
+
+fn object(input: &str) -> IResult<&str, Vec<(String, JsonValue)>> {
+    delimited(
+        char('{'),
+        ws(separated_list0(
+            ws(char(',')),
+            separated_pair(string, ws(char(':')), json_value),
+        )),
+        char('}'),
+    )(input)
+}
+
+fn json_value(input: &str) -> IResult<&str, JsonValue> {
+    use JsonValue::*;
+
+    alt((
+        value(Null, tag("null")),
+        map(boolean, Bool),
+        map(string, Str),
+        map(number, Num),
+        map(array, Array),
+        map(object, Object),
+    ))(input)
+}
+
+pub fn json(input: &str) -> IResult<&str, JsonValue> {
+    ws(json_value).parse(input)
+}
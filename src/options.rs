@@ -0,0 +1,337 @@
+//! How the parsers read their input: the [`Dialect`] they accept, and
+//! limits for parsing untrusted input.
+//!
+//! Every limit is checked while parsing, so a document that exceeds one is
+//! rejected with a diagnostic at the offending token before the parser does
+//! the work the limit guards against. [`parser::Parser`] and
+//! [`recursive::Parser`] recurse once per nesting level, so `max_depth` is
+//! what keeps them from overflowing the stack; the [`EventParser`], and the
+//! [`Tape`] and [`Document`] built on it, keep their open containers on an
+//! explicit stack and never recurse, so [`ParseOptions::unlimited`] is safe
+//! for them as far as the stack goes.
+//!
+//! [`parser::Parser`]: crate::parser::Parser
+//! [`recursive::Parser`]: crate::recursive::Parser
+//! [`EventParser`]: crate::event::EventParser
+//! [`Tape`]: crate::tape::Tape
+//! [`Document`]: crate::document::Document
+
+/// The flavor of JSON a parser accepts.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// RFC 8259 and nothing else.
+    #[default]
+    Json,
+    /// JSON with `//` and `/* */` comments and trailing commas, as in VS
+    /// Code's settings files.
+    Jsonc,
+}
+
+impl Dialect {
+    pub fn allows_comments(self) -> bool {
+        self == Dialect::Jsonc
+    }
+
+    pub fn allows_trailing_commas(self) -> bool {
+        self == Dialect::Jsonc
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub dialect: Dialect,
+    /// How many arrays and objects may be nested inside each other.
+    pub max_depth: usize,
+    /// The length of the whole input in bytes.
+    pub max_bytes: usize,
+    /// The length of a string or key in bytes, as written between the
+    /// quotes.
+    pub max_string_len: usize,
+    /// How many members a single object may have.
+    pub max_members: usize,
+    /// How many values the document may have in total, counting every
+    /// scalar, array and object but not keys.
+    pub max_nodes: usize,
+}
+
+impl ParseOptions {
+    /// The nesting depth [`Default`] allows, the same as serde_json's.
+    pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+    /// Strict JSON with no limits at all.
+    pub fn unlimited() -> Self {
+        Self {
+            dialect: Dialect::Json,
+            max_depth: usize::MAX,
+            max_bytes: usize::MAX,
+            max_string_len: usize::MAX,
+            max_members: usize::MAX,
+            max_nodes: usize::MAX,
+        }
+    }
+}
+
+impl Default for ParseOptions {
+    /// Strict JSON, limiting only the nesting depth so that the recursive
+    /// parsers cannot overflow the stack.
+    fn default() -> Self {
+        Self {
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            ..Self::unlimited()
+        }
+    }
+}
+
+/// A limit of [`ParseOptions`] that the input exceeds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Limit {
+    Depth,
+    Bytes,
+    StringLen,
+    Members,
+    Nodes,
+}
+
+impl Limit {
+    pub(crate) fn message(self, options: &ParseOptions) -> String {
+        match self {
+            Limit::Depth => format!("nesting exceeds the limit of {} levels", options.max_depth),
+            Limit::Bytes => format!("document exceeds the limit of {} bytes", options.max_bytes),
+            Limit::StringLen => {
+                format!(
+                    "string exceeds the limit of {} bytes",
+                    options.max_string_len
+                )
+            }
+            Limit::Members => {
+                format!(
+                    "object exceeds the limit of {} members",
+                    options.max_members
+                )
+            }
+            Limit::Nodes => format!("document exceeds the limit of {} values", options.max_nodes),
+        }
+    }
+}
+
+/// The error for a comment in a dialect without them.
+pub(crate) const COMMENT_ERROR: &str = "comments are not allowed in JSON";
+
+/// Whether the string token `text`, quotes included, is longer than the
+/// limit allows.
+pub(crate) fn string_too_long(text: &str, options: &ParseOptions) -> bool {
+    text.len().saturating_sub(2) > options.max_string_len
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::{expect, Expect};
+
+    use super::*;
+    use crate::backend::syntax_to_value;
+    use crate::event::EventParser;
+    use crate::parser::Parser;
+    use crate::recursive;
+    use crate::tape::Tape;
+
+    /// Checks that every parser reports the same first error for `input`.
+    fn check(input: &str, options: ParseOptions, expected: Expect) {
+        let parse = Parser::new(input).options(options).parse();
+        assert_eq!(parse.syntax().to_string(), input);
+        let rowan = parse.errors[0].clone();
+        let recursive = recursive::Parser::with_options(input, options)
+            .parse()
+            .unwrap_err();
+        let event = EventParser::with_options(input, options)
+            .find_map(Result::err)
+            .unwrap();
+        assert_eq!(recursive, rowan);
+        assert_eq!(event, rowan);
+        expected.assert_debug_eq(&parse.errors);
+    }
+
+    fn limits() -> ParseOptions {
+        ParseOptions {
+            max_depth: 2,
+            max_bytes: 32,
+            max_string_len: 3,
+            max_members: 2,
+            max_nodes: 6,
+            ..ParseOptions::default()
+        }
+    }
+
+    #[test]
+    fn accepts_input_within_the_limits() {
+        let input = r#"{"abc": ["xyz"], "b": 2}"#;
+        assert!(Parser::new(input).options(limits()).parse().ok());
+        assert!(recursive::Parser::with_options(input, limits())
+            .parse()
+            .is_ok());
+        assert!(Tape::parse_with(input, limits()).is_ok());
+    }
+
+    #[test]
+    fn reports_each_limit() {
+        check(
+            "[[[1]], 2]",
+            limits(),
+            expect![[r#"
+                [
+                    Diagnostic {
+                        severity: Error,
+                        message: "nesting exceeds the limit of 2 levels",
+                        range: 2..3,
+                        labels: [],
+                        fix: [],
+                    },
+                ]
+            "#]],
+        );
+        check(
+            &format!("[{}]", "1, ".repeat(12)),
+            limits(),
+            expect![[r#"
+                [
+                    Diagnostic {
+                        severity: Error,
+                        message: "document exceeds the limit of 32 bytes",
+                        range: 32..38,
+                        labels: [],
+                        fix: [],
+                    },
+                ]
+            "#]],
+        );
+        check(
+            r#"{"abc": "abcd"}"#,
+            limits(),
+            expect![[r#"
+                [
+                    Diagnostic {
+                        severity: Error,
+                        message: "string exceeds the limit of 3 bytes",
+                        range: 8..14,
+                        labels: [],
+                        fix: [],
+                    },
+                ]
+            "#]],
+        );
+        check(
+            r#"{"a": 1, "b": 2, "c": 3}"#,
+            limits(),
+            expect![[r#"
+                [
+                    Diagnostic {
+                        severity: Error,
+                        message: "object exceeds the limit of 2 members",
+                        range: 17..20,
+                        labels: [],
+                        fix: [],
+                    },
+                ]
+            "#]],
+        );
+        check(
+            "[1, [2, 3], 4, 5, 6]",
+            limits(),
+            expect![[r#"
+                [
+                    Diagnostic {
+                        severity: Error,
+                        message: "document exceeds the limit of 6 values",
+                        range: 15..16,
+                        labels: [],
+                        fix: [],
+                    },
+                ]
+            "#]],
+        );
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error_not_a_crash() {
+        let input = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+        check(
+            &input,
+            ParseOptions::default(),
+            expect![[r#"
+                [
+                    Diagnostic {
+                        severity: Error,
+                        message: "nesting exceeds the limit of 128 levels",
+                        range: 128..129,
+                        labels: [],
+                        fix: [],
+                    },
+                ]
+            "#]],
+        );
+        assert!(Tape::parse_with(&input, ParseOptions::unlimited()).is_ok());
+    }
+
+    #[test]
+    fn jsonc_allows_comments_and_trailing_commas() {
+        let input = "// settings\n{\"a\": [1, 2,], /* b */ \"b\": null,}\n";
+        let jsonc = ParseOptions {
+            dialect: Dialect::Jsonc,
+            ..ParseOptions::default()
+        };
+        let parse = Parser::new(input).options(jsonc).parse();
+        assert_eq!(parse.errors, vec![]);
+        let value = syntax_to_value(&parse.syntax()).unwrap();
+        assert_eq!(value.to_string(), r#"{"a":[1,2],"b":null}"#);
+        assert!(recursive::Parser::with_options(input, jsonc)
+            .parse()
+            .is_ok());
+        assert!(Tape::parse_with(input, jsonc).is_ok());
+
+        check(
+            input,
+            ParseOptions::default(),
+            expect![[r#"
+                [
+                    Diagnostic {
+                        severity: Error,
+                        message: "comments are not allowed in JSON",
+                        range: 0..11,
+                        labels: [],
+                        fix: [],
+                    },
+                    Diagnostic {
+                        severity: Error,
+                        message: "trailing commas are not allowed in JSON",
+                        range: 23..24,
+                        labels: [],
+                        fix: [
+                            TextEdit {
+                                range: 23..24,
+                                new_text: "",
+                            },
+                        ],
+                    },
+                    Diagnostic {
+                        severity: Error,
+                        message: "comments are not allowed in JSON",
+                        range: 27..34,
+                        labels: [],
+                        fix: [],
+                    },
+                    Diagnostic {
+                        severity: Error,
+                        message: "trailing commas are not allowed in JSON",
+                        range: 44..45,
+                        labels: [],
+                        fix: [
+                            TextEdit {
+                                range: 44..45,
+                                new_text: "",
+                            },
+                        ],
+                    },
+                ]
+            "#]],
+        );
+    }
+}
@@ -0,0 +1,410 @@
+//! Parallel parsing of large documents on the rayon thread pool.
+//!
+//! A structural pre-scan finds the top-level commas of every large array or
+//! object, the items between them are parsed on worker threads, and the
+//! resulting green nodes are stitched back together. Large items are split
+//! again recursively, so a document whose size is concentrated in a few deep
+//! members is still spread over the pool.
+//!
+//! The tree is the same one [`Parser`] builds for the same input. Whenever a
+//! worker sees a syntax error the whole input is re-parsed sequentially, so
+//! diagnostics and recovery are those of [`Parser`] as well. Each worker
+//! starts at the depth of its items, so it runs into `max_depth` where
+//! [`Parser`] would; limits a worker cannot see on its own, and comments,
+//! which the pre-scan does not skip, send the input to [`Parser`] directly.
+
+use rayon::prelude::*;
+use rowan::{GreenNode, GreenToken, Language, NodeOrToken};
+
+use crate::lexer::{Lexer, SyntaxKind};
+use crate::options::{string_too_long, ParseOptions};
+use crate::parser::{Parse, Parser};
+use crate::syntax::Json;
+
+type GreenElement = NodeOrToken<GreenNode, GreenToken>;
+
+/// Containers smaller than this are parsed on a single thread.
+const SPLIT_THRESHOLD: usize = 16 * 1024;
+
+pub fn parse(input: &str) -> Parse {
+    parse_with(input, ParseOptions::default())
+}
+
+pub fn parse_with(input: &str, options: ParseOptions) -> Parse {
+    match parse_root(input, &options) {
+        Some(green_node) => Parse {
+            green_node,
+            errors: vec![],
+        },
+        None => Parser::new(input).options(options).parse(),
+    }
+}
+
+fn parse_root(input: &str, options: &ParseOptions) -> Option<GreenNode> {
+    let (leading, element, trailing) = split_whitespace(input);
+    if element.len() < SPLIT_THRESHOLD || !splits_within_limits(input, options) {
+        return None;
+    }
+    let mut children = vec![];
+    children.extend(whitespace(leading));
+    children.push(NodeOrToken::Node(parse_container(element, options, 0)?));
+    children.extend(whitespace(trailing));
+    Some(GreenNode::new(
+        Json::kind_to_raw(SyntaxKind::Root),
+        children,
+    ))
+}
+
+/// Whether the workers can enforce `options` between them. Every value
+/// takes at least a byte, so `max_nodes` cannot be reached when it is at
+/// least the length of the input.
+fn splits_within_limits(input: &str, options: &ParseOptions) -> bool {
+    !options.dialect.allows_comments()
+        && input.len() <= options.max_bytes
+        && input.len() <= options.max_nodes
+}
+
+/// Parses the container `text` that `depth` other containers are nested in.
+fn parse_container(text: &str, options: &ParseOptions, depth: usize) -> Option<GreenNode> {
+    if depth >= options.max_depth {
+        return None;
+    }
+    let (kind, open, close) = match text.as_bytes()[0] {
+        b'[' => (
+            SyntaxKind::Array,
+            SyntaxKind::LeftBracket,
+            SyntaxKind::RightBracket,
+        ),
+        b'{' => (
+            SyntaxKind::Object,
+            SyntaxKind::LeftBrace,
+            SyntaxKind::RightBrace,
+        ),
+        _ => return None,
+    };
+    let items = split_items(text)?;
+    if items.len() == 1 && items[0].bytes().all(is_whitespace) {
+        // Empty containers are never worth splitting.
+        return None;
+    }
+    if kind == SyntaxKind::Object && items.len() > options.max_members {
+        return None;
+    }
+    let batches = batch_items(text, &items, kind)?;
+    let batches = batches
+        .into_par_iter()
+        .map(|batch| match batch {
+            Batch::Items(text) => parse_batch(text, kind, options, depth + 1),
+            Batch::Large(text) => parse_large_item(text, kind, options, depth + 1),
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let mut children = vec![token(open, &text[..1])];
+    for (index, batch) in batches.into_iter().enumerate() {
+        if index > 0 {
+            children.push(token(SyntaxKind::Comma, ","));
+        }
+        children.extend(batch);
+    }
+    children.push(token(close, &text[text.len() - 1..]));
+    Some(GreenNode::new(Json::kind_to_raw(kind), children))
+}
+
+enum Batch<'a> {
+    /// Consecutive small items, including the commas between them.
+    Items(&'a str),
+    /// A single item whose value is a container worth splitting on its own.
+    Large(&'a str),
+}
+
+/// Groups the items of a container into batches of roughly
+/// `SPLIT_THRESHOLD` bytes, so that each worker amortizes its parser setup.
+fn batch_items<'a>(
+    text: &'a str,
+    items: &[&'a str],
+    container: SyntaxKind,
+) -> Option<Vec<Batch<'a>>> {
+    let offset = |item: &str| item.as_ptr() as usize - text.as_ptr() as usize;
+    let mut batches = vec![];
+    let mut start: Option<usize> = None;
+    for item in items {
+        let (_, element, _) = split_whitespace(item);
+        let value_start = match container {
+            SyntaxKind::Object => member_value_start(element)?,
+            _ => 0,
+        };
+        let end = offset(item) + item.len();
+        let value = &element[value_start..];
+        // Large scalars have nothing to split and are batched like the rest.
+        if value.len() >= SPLIT_THRESHOLD && value.starts_with(['[', '{']) {
+            if let Some(start) = start.take() {
+                // The comma in front of the large item separates the batches.
+                batches.push(Batch::Items(&text[start..offset(item) - 1]));
+            }
+            batches.push(Batch::Large(item));
+            continue;
+        }
+        let batch_start = *start.get_or_insert(offset(item));
+        if end - batch_start >= SPLIT_THRESHOLD {
+            batches.push(Batch::Items(&text[batch_start..end]));
+            start = None;
+        }
+    }
+    if let Some(start) = start {
+        let last = items.last()?;
+        batches.push(Batch::Items(&text[start..offset(last) + last.len()]));
+    }
+    Some(batches)
+}
+
+/// Parses items that `depth` containers are nested in.
+fn parse_batch(
+    text: &str,
+    container: SyntaxKind,
+    options: &ParseOptions,
+    depth: usize,
+) -> Option<Vec<GreenElement>> {
+    let parser = Parser::new(text).options(ParseOptions {
+        max_depth: options.max_depth - depth,
+        ..*options
+    });
+    let parse = match container {
+        SyntaxKind::Object => parser.parse_with(|p| p.parse_items(Parser::parse_member)),
+        _ => parser.parse_with(|p| p.parse_items(Parser::parse_element)),
+    };
+    if !parse.errors.is_empty() {
+        return None;
+    }
+    Some(
+        parse
+            .green_node
+            .children()
+            .map(|it| it.to_owned())
+            .collect(),
+    )
+}
+
+/// Parses an item whose value is a large container by lexing the tokens in
+/// front of the value and recursing into the value itself.
+fn parse_large_item(
+    text: &str,
+    container: SyntaxKind,
+    options: &ParseOptions,
+    depth: usize,
+) -> Option<Vec<GreenElement>> {
+    let (leading, element, trailing) = split_whitespace(text);
+    let value_start = match container {
+        SyntaxKind::Object => member_value_start(element)?,
+        _ => 0,
+    };
+    let prefix = &text[..leading.len() + value_start];
+    let mut children = vec![];
+    for (kind, text) in Lexer::new(prefix) {
+        if kind == SyntaxKind::Error || kind == SyntaxKind::String && string_too_long(text, options)
+        {
+            return None;
+        }
+        children.push(token(kind, text));
+    }
+    let value = &element[value_start..];
+    children.push(NodeOrToken::Node(parse_container(value, options, depth)?));
+    children.extend(whitespace(trailing));
+    Some(children)
+}
+
+/// Returns the text between the top-level commas of the container `text`, or
+/// `None` if the container is not closed exactly at the end of `text`.
+fn split_items(text: &str) -> Option<Vec<&str>> {
+    let bytes = text.as_bytes();
+    let close = if bytes[0] == b'[' { b']' } else { b'}' };
+    let mut items = vec![];
+    let mut item_start = 1;
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, &byte) in bytes.iter().enumerate().skip(1) {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' if depth > 0 => depth -= 1,
+            b']' | b'}' => {
+                if byte != close || i != bytes.len() - 1 {
+                    return None;
+                }
+                items.push(&text[item_start..i]);
+                return Some(items);
+            }
+            b',' if depth == 0 => {
+                items.push(&text[item_start..i]);
+                item_start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Finds where the value of the member `text` starts, i.e. the byte after
+/// the key, the colon and any whitespace between them.
+fn member_value_start(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    if bytes.first() != Some(&b'"') {
+        return None;
+    }
+    let mut i = 1;
+    while i < bytes.len() && bytes[i] != b'"' {
+        i += if bytes[i] == b'\\' { 2 } else { 1 };
+    }
+    i += 1;
+    while i < bytes.len() && is_whitespace(bytes[i]) {
+        i += 1;
+    }
+    if bytes.get(i) != Some(&b':') {
+        return None;
+    }
+    i += 1;
+    while i < bytes.len() && is_whitespace(bytes[i]) {
+        i += 1;
+    }
+    Some(i)
+}
+
+fn split_whitespace(text: &str) -> (&str, &str, &str) {
+    let start = text.len() - text.trim_start_matches(|c| is_whitespace(c as u8)).len();
+    let end = text
+        .trim_end_matches(|c| is_whitespace(c as u8))
+        .len()
+        .max(start);
+    (&text[..start], &text[start..end], &text[end..])
+}
+
+/// Matches the lexer's `Whitespace` token.
+fn is_whitespace(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\r' | b'\n')
+}
+
+fn whitespace(text: &str) -> Option<GreenElement> {
+    (!text.is_empty()).then(|| token(SyntaxKind::Whitespace, text))
+}
+
+fn token(kind: SyntaxKind, text: &str) -> GreenElement {
+    NodeOrToken::Token(GreenToken::new(Json::kind_to_raw(kind), text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_same_as_sequential(input: &str) {
+        check_same_with_options(input, ParseOptions::default());
+    }
+
+    fn check_same_with_options(input: &str, options: ParseOptions) {
+        let sequential = Parser::new(input).options(options).parse();
+        let parallel = parse_with(input, options);
+        assert_eq!(parallel.green_node, sequential.green_node);
+        assert_eq!(parallel.errors, sequential.errors);
+    }
+
+    #[test]
+    fn assets_match_sequential_parser() {
+        let big = include_str!("../assets/big.json");
+        assert!(parse_root(big, &ParseOptions::default()).is_some());
+        check_same_as_sequential(big);
+        check_same_as_sequential(include_str!("../assets/200k.json"));
+        check_same_as_sequential(include_str!("../assets/large.json"));
+    }
+
+    #[test]
+    fn large_array_matches_sequential_parser() {
+        let item = r#" {"id": 1, "tags": ["a", "b\"]"], "nested": {"x": [1, 2]}}"#;
+        let input = format!("\n[{}\n]\n", vec![item; 2000].join(","));
+        check_same_as_sequential(&input);
+        assert!(parse_root(&input, &ParseOptions::default()).is_some());
+    }
+
+    #[test]
+    fn large_scalars_are_batched() {
+        let string = format!(r#""{}""#, "x".repeat(16 * 1024));
+        let input = format!(r#"[1, {0}, {{"a": {0}}}, 2]"#, string);
+        check_same_as_sequential(&input);
+        assert!(parse_root(&input, &ParseOptions::default()).is_some());
+    }
+
+    #[test]
+    fn malformed_input_falls_back() {
+        let item = r#"{"id": 1}"#;
+        let input = format!("[{} 2]", vec![item; 4000].join(","));
+        check_same_as_sequential(&input);
+        assert!(!parse(&input).errors.is_empty());
+    }
+
+    #[test]
+    fn deep_items_hit_the_depth_limit() {
+        let item = r#"{"id": 1, "tags": ["a", "b"]}"#;
+        let deep = format!("{}1{}", "[".repeat(129), "]".repeat(129));
+        let input = format!("[{},{}]", vec![item; 2000].join(","), deep);
+        check_same_as_sequential(&input);
+        assert!(!parse(&input).errors.is_empty());
+
+        // The same, with the deep element large enough to be split itself:
+        // the tags arrays in it are 129 levels down.
+        let large = format!("[{}]", vec![item; 1000].join(","));
+        let deep = format!("{}{}{}", "[".repeat(125), large, "]".repeat(125));
+        let input = format!("[{},{}]", vec![item; 2000].join(","), deep);
+        check_same_as_sequential(&input);
+        assert!(!parse(&input).errors.is_empty());
+
+        // One level less is within the limit and still split.
+        let deep = format!("{}{}{}", "[".repeat(124), large, "]".repeat(124));
+        let input = format!("[{},{}]", vec![item; 2000].join(","), deep);
+        check_same_as_sequential(&input);
+        assert!(parse_root(&input, &ParseOptions::default()).is_some());
+    }
+
+    #[test]
+    fn other_limits_match_sequential_parser() {
+        let item = r#"{"id": 1, "tags": ["a", "b"]}"#;
+        let input = format!(
+            "{{\"items\": [{}], \"long key\": 1}}",
+            vec![item; 2000].join(",")
+        );
+        let options = ParseOptions::default();
+        check_same_with_options(&input, options);
+        let limits = [
+            ParseOptions {
+                max_depth: 2,
+                ..options
+            },
+            ParseOptions {
+                max_bytes: 1000,
+                ..options
+            },
+            ParseOptions {
+                max_string_len: 4,
+                ..options
+            },
+            ParseOptions {
+                max_members: 1,
+                ..options
+            },
+            ParseOptions {
+                max_nodes: 5000,
+                ..options
+            },
+        ];
+        for options in limits {
+            check_same_with_options(&input, options);
+            assert!(!parse_with(&input, options).errors.is_empty());
+        }
+    }
+}
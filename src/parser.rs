@@ -1,125 +1,373 @@
 use std::iter::Peekable;
 
-use crate::lexer::{Lexer, SyntaxKind};
-use crate::syntax::Json;
-use rowan::{GreenNode, GreenNodeBuilder, Language};
+use crate::diagnostic::Diagnostic;
+use crate::edit::TextEdit;
+use crate::lexer::{Lexer, LexerBackend, SyntaxKind};
+use crate::options::{string_too_long, Limit, ParseOptions, COMMENT_ERROR};
+use crate::quickfix;
+use crate::syntax::{Json, SyntaxNode};
+use rowan::{GreenNode, GreenNodeBuilder, Language, TextRange, TextSize};
 
 pub struct Parser<'a> {
+    input: &'a str,
     lexer: Peekable<Lexer<'a>>,
     builder: GreenNodeBuilder<'static>,
+    offset: TextSize,
+    /// The end of the last token other than whitespace or a comment, where
+    /// a missing comma or delimiter goes.
+    last_end: TextSize,
+    /// Whether that token was skipped as an error, such as the quote of an
+    /// unterminated string, which a comma or delimiter after it would not
+    /// fix.
+    after_error: bool,
+    errors: Vec<Diagnostic>,
+    options: ParseOptions,
+    depth: usize,
+    nodes: usize,
+    /// Set once a limit is exceeded and the rest of the input was skipped.
+    aborted: bool,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(input: &'a str) -> Self {
+        Self::with_backend(input, LexerBackend::default())
+    }
+
+    pub fn with_backend(input: &'a str, backend: LexerBackend) -> Self {
         Self {
-            lexer: Lexer::new(input).peekable(),
+            input,
+            lexer: Lexer::with_backend(input, backend).peekable(),
             builder: GreenNodeBuilder::new(),
+            offset: 0.into(),
+            last_end: 0.into(),
+            after_error: false,
+            errors: vec![],
+            options: ParseOptions::default(),
+            depth: 0,
+            nodes: 0,
+            aborted: false,
         }
     }
 
-    pub fn parse(mut self) -> Parse {
+    /// Replaces the default limits. Once the input exceeds one, the rest of
+    /// it goes into a single `Error` node so the tree stays lossless.
+    pub fn options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn parse(self) -> Parse {
+        self.parse_with(Self::parse_element)
+    }
+
+    /// Parses the whole input with `item` under a `Root` node, reporting any
+    /// input left over once `item` returns.
+    pub(crate) fn parse_with(mut self, item: impl FnOnce(&mut Self)) -> Parse {
         self.builder.start_node(SyntaxKind::Root.into());
-        self.parse_element();
+        if self.input.len() > self.options.max_bytes {
+            let excess = TextRange::new(
+                TextSize::from(self.options.max_bytes as u32),
+                TextSize::of(self.input),
+            );
+            self.abort(Limit::Bytes, excess);
+        }
+        item(&mut self);
+        if self.peek().is_some() {
+            self.error("expected end of input");
+            self.start_node(SyntaxKind::Error);
+            while self.peek().is_some() {
+                self.bump();
+            }
+            self.finish_node();
+        }
         self.builder.finish_node();
         Parse {
             green_node: self.builder.finish(),
+            errors: self.errors,
         }
     }
 
     pub fn parse_element(&mut self) {
-        self.skip_whitespace();
-        if let Some(t) = self.peek() {
-            match t {
-                SyntaxKind::LeftBrace => self.parse_object(),
-                SyntaxKind::LeftBracket => self.parse_array(),
-                SyntaxKind::True => self.bump(),
-                SyntaxKind::False => self.bump(),
-                SyntaxKind::Null => self.bump(),
-                SyntaxKind::String => self.bump(),
-                SyntaxKind::Number => self.bump(),
-                // SyntaxKind::Whitespace => unreachable!(),
-                // SyntaxKind::Error => todo!(),
-                // SyntaxKind::RightBrace => unreachable!(),
-                // SyntaxKind::Colon => unreachable!(),
-                // SyntaxKind::Comma => unreachable!(),
-                // SyntaxKind::RightBracket => unreachable!(),
-                _ => {
-                    unreachable!()
-                }
+        self.skip_trivia();
+        if self.peek().is_some_and(is_value_start) {
+            self.nodes += 1;
+            if self.nodes > self.options.max_nodes {
+                self.abort_here(Limit::Nodes);
             }
         }
-        self.skip_whitespace();
-    }
-    pub fn parse_member(&mut self) {
-        self.skip_whitespace();
         match self.peek() {
-            Some(SyntaxKind::String) => {
-                self.bump();
+            Some(SyntaxKind::LeftBrace) => self.parse_object(),
+            Some(SyntaxKind::LeftBracket) => self.parse_array(),
+            Some(
+                SyntaxKind::True
+                | SyntaxKind::False
+                | SyntaxKind::Null
+                | SyntaxKind::String
+                | SyntaxKind::Number,
+            ) => self.bump(),
+            Some(SyntaxKind::RightBrace | SyntaxKind::RightBracket | SyntaxKind::Comma) | None => {
+                // Leave closing tokens to the enclosing container so it can recover.
+                self.error("expected value");
             }
-            None => todo!(),
-            _ => {
-                let res = self.lexer.next().unwrap();
-                panic!("{:?}", res);
+            Some(_) => {
+                let fix = quickfix::value(self.rest());
+                self.bump_error("expected value", fix);
             }
         }
-        self.skip_whitespace();
-        assert!(matches!(self.peek(), Some(SyntaxKind::Colon)));
-        self.bump();
-        self.parse_element();
+        self.skip_trivia();
     }
-    pub(crate) fn parse_array(&mut self) {
-        self.start_node(SyntaxKind::Array);
-        self.bump();
-        self.skip_whitespace();
-        if self.peek() != Some(SyntaxKind::RightBracket) {
-            self.parse_element();
-        }
-        self.skip_whitespace();
-        while let Some(SyntaxKind::Comma) = self.peek() {
-            self.bump();
-            self.parse_element();
+
+    pub fn parse_member(&mut self) {
+        self.skip_trivia();
+        match self.peek() {
+            Some(SyntaxKind::String) => self.bump(),
+            Some(
+                SyntaxKind::Colon
+                | SyntaxKind::Comma
+                | SyntaxKind::RightBrace
+                | SyntaxKind::RightBracket,
+            )
+            | None => self.error("expected string key"),
+            Some(_) => {
+                let fix = quickfix::key(self.rest());
+                self.bump_error("expected string key", fix);
+            }
         }
-        if self.peek() == Some(SyntaxKind::RightBracket) {
+        self.skip_trivia();
+        if self.peek() == Some(SyntaxKind::Colon) {
             self.bump();
-            self.finish_node();
         } else {
-            unimplemented!("should be ] to finish array") // TODO
+            self.error("expected `:`");
         }
+        self.parse_element();
+    }
+
+    pub(crate) fn parse_array(&mut self) {
+        self.parse_container(
+            SyntaxKind::Array,
+            SyntaxKind::RightBracket,
+            Self::parse_element,
+        );
     }
 
     pub(crate) fn parse_object(&mut self) {
-        self.start_node(SyntaxKind::Object);
-        self.bump();
-        self.skip_whitespace();
-        if self.peek() != Some(SyntaxKind::RightBrace) {
-            self.parse_member();
+        self.parse_container(
+            SyntaxKind::Object,
+            SyntaxKind::RightBrace,
+            Self::parse_member,
+        );
+    }
+
+    fn parse_container(
+        &mut self,
+        kind: SyntaxKind,
+        close: SyntaxKind,
+        item: impl FnMut(&mut Self),
+    ) {
+        if self.depth == self.options.max_depth {
+            self.abort_here(Limit::Depth);
+            return;
         }
+        self.depth += 1;
+        self.start_node(kind);
+        self.bump();
+        self.parse_list(close, item);
+        self.finish_node();
+        self.depth -= 1;
+    }
 
-        while let Some(SyntaxKind::Comma) = self.peek() {
-            self.bump();
-            self.parse_member();
+    /// Parses comma separated items up to and including `close`, reporting a
+    /// missing comma or delimiter instead of giving up on the container. The
+    /// opening delimiter must be the last token bumped.
+    fn parse_list(&mut self, close: SyntaxKind, mut item: impl FnMut(&mut Self)) {
+        let open = TextRange::at(self.offset - TextSize::from(1), 1.into());
+        let (open_text, close_text) = match close {
+            SyntaxKind::RightBracket => ('[', ']'),
+            _ => ('{', '}'),
+        };
+        let max_items = match close {
+            SyntaxKind::RightBrace => self.options.max_members,
+            _ => usize::MAX,
+        };
+        let mut items = 0;
+        let mut first = true;
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                Some(kind) if kind == close => {
+                    self.bump();
+                    return;
+                }
+                Some(SyntaxKind::RightBrace | SyntaxKind::RightBracket) | None => {
+                    let mut diagnostic = self
+                        .diagnostic(&format!("expected `{}`", close_text))
+                        .with_label(format!("unclosed `{}` opened here", open_text), open);
+                    if !self.after_error {
+                        let close = TextEdit::insert(self.last_end, close_text.to_string());
+                        diagnostic = diagnostic.with_fix(vec![close]);
+                    }
+                    self.push_error(diagnostic);
+                    return;
+                }
+                Some(SyntaxKind::Comma) if !first => {
+                    let comma = TextRange::at(self.offset, 1.into());
+                    self.bump();
+                    // Comments after the comma are reported first, but go after it.
+                    let position = self.errors.len();
+                    self.skip_trivia();
+                    if self.peek() == Some(close) {
+                        if !self.options.dialect.allows_trailing_commas() {
+                            let message = "trailing commas are not allowed in JSON";
+                            let diagnostic = Diagnostic::new(message, comma)
+                                .with_fix(vec![TextEdit::delete(comma)]);
+                            self.errors.insert(position, diagnostic);
+                        }
+                        continue;
+                    }
+                }
+                Some(_) if !first => {
+                    let mut diagnostic = self.diagnostic("expected `,`");
+                    if !self.after_error {
+                        diagnostic =
+                            diagnostic.with_fix(vec![TextEdit::insert(self.last_end, ",")]);
+                    }
+                    self.push_error(diagnostic);
+                }
+                Some(_) => {}
+            }
+            first = false;
+            items += 1;
+            if items > max_items {
+                self.skip_trivia();
+                self.abort_here(Limit::Members);
+                return;
+            }
+            item(self);
         }
-        if self.peek() == Some(SyntaxKind::RightBrace) {
+    }
+
+    /// Parses `item (, item)*` the way it appears between the delimiters of
+    /// an array or object, without the delimiters themselves.
+    pub(crate) fn parse_items(&mut self, mut item: impl FnMut(&mut Self)) {
+        item(self);
+        self.skip_trivia();
+        while self.peek() == Some(SyntaxKind::Comma) {
             self.bump();
-            self.finish_node();
-        } else {
-            unimplemented!("should be ] to finish array") // TODO
+            item(self);
+            self.skip_trivia();
         }
     }
-    pub fn skip_whitespace(&mut self) {
-        while let Some(SyntaxKind::Whitespace) = self.peek() {
+
+    /// Skips whitespace and comments, reporting comments unless the dialect
+    /// allows them.
+    pub fn skip_trivia(&mut self) {
+        while let Some(kind) = self.peek().filter(|kind| kind.is_trivia()) {
+            if kind == SyntaxKind::Comment && !self.options.dialect.allows_comments() {
+                self.error(COMMENT_ERROR);
+            }
             self.bump();
         }
     }
+
     fn peek(&mut self) -> Option<SyntaxKind> {
         self.lexer.peek().map(|(kind, _)| *kind)
     }
 
     fn bump(&mut self) {
+        if let Some((SyntaxKind::String, text)) = self.lexer.peek() {
+            if string_too_long(text, &self.options) {
+                self.abort_here(Limit::StringLen);
+                return;
+            }
+        }
         let (kind, text) = self.lexer.next().unwrap();
-
+        self.offset += TextSize::of(text);
+        if !kind.is_trivia() {
+            self.last_end = self.offset;
+            self.after_error = false;
+        }
         self.builder.token(Json::kind_to_raw(kind), text);
     }
+
+    /// Records an error at the current token and wraps the token in an
+    /// `Error` node, along with the rest of what `fix` covers and any stray
+    /// characters right after it. If `fix` is `Some((len, text))`, the
+    /// error covers the `len` bytes from the current token, and `text`, if
+    /// any, replaces them.
+    fn bump_error(&mut self, message: &str, fix: Option<(TextSize, Option<String>)>) {
+        let mut diagnostic = self.diagnostic(message);
+        let mut end = self.offset;
+        if let Some((len, text)) = fix {
+            let range = TextRange::at(self.offset, len);
+            diagnostic.range = range;
+            diagnostic.fix = text
+                .map(|text| vec![TextEdit::replace(range, text)])
+                .unwrap_or_default();
+            end = range.end();
+        }
+        self.push_error(diagnostic);
+        self.start_node(SyntaxKind::Error);
+        self.bump();
+        loop {
+            match self.peek() {
+                Some(kind) if self.offset < end || kind == SyntaxKind::Error => self.bump(),
+                _ => break,
+            }
+        }
+        self.finish_node();
+        self.after_error = true;
+    }
+
+    /// Records an error at the current token, or at the end of input.
+    fn error(&mut self, message: &str) {
+        let diagnostic = self.diagnostic(message);
+        self.push_error(diagnostic);
+    }
+
+    /// The input from the current token on.
+    fn rest(&self) -> &'a str {
+        &self.input[usize::from(self.offset)..]
+    }
+
+    /// Records `diagnostic` unless parsing was aborted, since every error
+    /// after that is only a consequence of the skipped input.
+    fn push_error(&mut self, diagnostic: Diagnostic) {
+        if !self.aborted {
+            self.errors.push(diagnostic);
+        }
+    }
+
+    /// Reports `limit` at the current token and skips the rest of the input.
+    fn abort_here(&mut self, limit: Limit) {
+        let range = self.diagnostic("").range;
+        self.abort(limit, range);
+    }
+
+    /// Reports `limit` at `range` and puts the rest of the input into an
+    /// `Error` node without lexing it, so every caller up the stack sees the
+    /// end of input and returns.
+    fn abort(&mut self, limit: Limit, range: TextRange) {
+        self.push_error(Diagnostic::new(limit.message(&self.options), range));
+        self.aborted = true;
+        let rest = self.rest();
+        if !rest.is_empty() {
+            self.start_node(SyntaxKind::Error);
+            self.builder
+                .token(Json::kind_to_raw(SyntaxKind::Error), rest);
+            self.finish_node();
+            self.offset = TextSize::of(self.input);
+        }
+        self.lexer = Lexer::new("").peekable();
+    }
+
+    fn diagnostic(&mut self, message: &str) -> Diagnostic {
+        let len = self
+            .lexer
+            .peek()
+            .map_or(0.into(), |(_, text)| TextSize::of(*text));
+        Diagnostic::new(message, TextRange::at(self.offset, len))
+    }
+
     fn start_node(&mut self, kind: SyntaxKind) {
         self.builder.start_node(Json::kind_to_raw(kind));
     }
@@ -129,15 +377,41 @@ impl<'a> Parser<'a> {
     }
 }
 
+fn is_value_start(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::LeftBrace
+            | SyntaxKind::LeftBracket
+            | SyntaxKind::True
+            | SyntaxKind::False
+            | SyntaxKind::Null
+            | SyntaxKind::String
+            | SyntaxKind::Number
+    )
+}
+
 pub struct Parse {
     pub green_node: GreenNode,
+    pub errors: Vec<Diagnostic>,
+}
+
+impl Parse {
+    pub fn syntax(&self) -> SyntaxNode {
+        SyntaxNode::new_root(self.green_node.clone())
+    }
+
+    pub fn ok(&self) -> bool {
+        self.errors.is_empty()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::syntax::SyntaxNode;
+    use crate::strategies::{layout, value};
     use expect_test::{expect, Expect};
+    use proptest::prelude::*;
+    use rowan::WalkEvent;
 
     fn check(input: &str, expected_tree: Expect) {
         let parse = Parser::new(input).parse();
@@ -149,6 +423,12 @@ mod tests {
         expected_tree.assert_eq(&actual_tree[0..actual_tree.len() - 1]);
     }
 
+    fn check_errors(input: &str, expected: Expect) {
+        let parse = Parser::new(input).parse();
+        assert_eq!(parse.syntax().to_string(), input);
+        expected.assert_debug_eq(&parse.errors);
+    }
+
     #[test]
     fn parse_nothing() {
         check(r#""#, expect![[r#"Root@0..0"#]]);
@@ -163,4 +443,117 @@ Root@0..3
   Number@0..3 "123""#]],
         );
     }
+
+    #[test]
+    fn recover_missing_comma_and_bracket() {
+        check_errors(
+            r#"[1 2, {"a" 3"#,
+            expect![[r#"
+                [
+                    Diagnostic {
+                        severity: Error,
+                        message: "expected `,`",
+                        range: 3..4,
+                        labels: [],
+                        fix: [
+                            TextEdit {
+                                range: 2..2,
+                                new_text: ",",
+                            },
+                        ],
+                    },
+                    Diagnostic {
+                        severity: Error,
+                        message: "expected `:`",
+                        range: 11..12,
+                        labels: [],
+                        fix: [],
+                    },
+                    Diagnostic {
+                        severity: Error,
+                        message: "expected `}`",
+                        range: 12..12,
+                        labels: [
+                            Label {
+                                message: "unclosed `{` opened here",
+                                range: 6..7,
+                            },
+                        ],
+                        fix: [
+                            TextEdit {
+                                range: 12..12,
+                                new_text: "}",
+                            },
+                        ],
+                    },
+                    Diagnostic {
+                        severity: Error,
+                        message: "expected `]`",
+                        range: 12..12,
+                        labels: [
+                            Label {
+                                message: "unclosed `[` opened here",
+                                range: 0..1,
+                            },
+                        ],
+                        fix: [
+                            TextEdit {
+                                range: 12..12,
+                                new_text: "]",
+                            },
+                        ],
+                    },
+                ]
+            "#]],
+        );
+    }
+
+    #[test]
+    fn recover_trailing_input() {
+        check(
+            r#"{} ]"#,
+            expect![[r#"
+                Root@0..4
+                  Object@0..2
+                    LeftBrace@0..1 "{"
+                    RightBrace@1..2 "}"
+                  Whitespace@2..3 " "
+                  Error@3..4
+                    RightBracket@3..4 "]""#]],
+        );
+    }
+
+    /// Every token starts where the previous one ended, every node spans
+    /// exactly its children and the root spans the whole input.
+    fn check_ranges(input: &str) -> Result<(), TestCaseError> {
+        let root = Parser::new(input).parse().syntax();
+        prop_assert_eq!(root.text_range(), TextRange::up_to(TextSize::of(input)));
+        let mut offset = TextSize::from(0);
+        for event in root.preorder_with_tokens() {
+            match event {
+                WalkEvent::Enter(element) => {
+                    prop_assert_eq!(element.text_range().start(), offset);
+                    if let Some(token) = element.as_token() {
+                        prop_assert!(!token.text().is_empty());
+                        offset = token.text_range().end();
+                    }
+                }
+                WalkEvent::Leave(element) => prop_assert_eq!(element.text_range().end(), offset),
+            }
+        }
+        prop_assert_eq!(offset, TextSize::of(input));
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn ranges_cover_valid_input((_, text) in value().prop_flat_map(layout)) {
+            check_ranges(&text)?;
+        }
+
+        #[test]
+        fn ranges_cover_any_input(text in "[\\[\\]{}:,\" a0-9.e-]{0,32}|.{0,16}") {
+            check_ranges(&text)?;
+        }
+    }
 }
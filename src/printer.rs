@@ -0,0 +1,232 @@
+//! Serializes a [`Value`] back into JSON text.
+
+use crate::escape::escape;
+use crate::value::{Number, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// Two-space indentation, one item per line.
+    Pretty,
+    /// No whitespace at all.
+    Minified,
+    /// Minified, with object members sorted and numbers spelled the way the
+    /// JSON Canonicalization Scheme (RFC 8785) does, so equal values print
+    /// identically.
+    Canonical,
+}
+
+pub fn print(value: &Value, style: Style) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value, style, 0);
+    out
+}
+
+fn write_value(out: &mut String, value: &Value, style: Style, depth: usize) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) if style == Style::Canonical => write_canonical_number(out, n),
+        Value::Number(n) => out.push_str(&n.to_string()),
+        Value::String(s) => write_string(out, s),
+        Value::Array(elements) => {
+            write_items(out, '[', ']', elements, style, depth, |out, element| {
+                write_value(out, element, style, depth + 1)
+            })
+        }
+        Value::Object(members) => {
+            let mut members: Vec<_> = members.iter().collect();
+            if style == Style::Canonical {
+                // RFC 8785 orders keys by their UTF-16 code units.
+                members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            }
+            write_items(
+                out,
+                '{',
+                '}',
+                &members,
+                style,
+                depth,
+                |out, (key, value)| {
+                    write_string(out, key);
+                    out.push_str(if style == Style::Pretty { ": " } else { ":" });
+                    write_value(out, value, style, depth + 1);
+                },
+            )
+        }
+    }
+}
+
+fn write_items<T>(
+    out: &mut String,
+    open: char,
+    close: char,
+    items: &[T],
+    style: Style,
+    depth: usize,
+    mut write_item: impl FnMut(&mut String, &T),
+) {
+    out.push(open);
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        if style == Style::Pretty {
+            newline(out, depth + 1);
+        }
+        write_item(out, item);
+    }
+    if style == Style::Pretty && !items.is_empty() {
+        newline(out, depth);
+    }
+    out.push(close);
+}
+
+fn newline(out: &mut String, depth: usize) {
+    out.push('\n');
+    out.push_str(&"  ".repeat(depth));
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    out.push_str(&escape(s));
+    out.push('"');
+}
+
+/// Writes `n` the way ECMAScript's `Number.prototype.toString` does: the
+/// shortest digits that read back as the same `f64`, in positional notation
+/// for exponents from -6 to 20 and as `d.ddde±x` outside of them.
+fn write_canonical_number(out: &mut String, n: &Number) {
+    if let Number::Large(text) = n {
+        out.push_str(text);
+        return;
+    }
+    let n = n.as_f64();
+    if !n.is_finite() {
+        out.push_str("null");
+        return;
+    }
+    if n == 0.0 {
+        out.push('0');
+        return;
+    }
+    // `{:e}` gives the shortest round-tripping digits, e.g. `-1.25e-7`.
+    let scientific = format!("{:e}", n);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    let digits = mantissa.replace('.', "");
+    out.push_str(sign);
+    // The decimal point goes after the first `point` digits.
+    let point = exponent + 1;
+    if (-5..=21).contains(&point) {
+        if point <= 0 {
+            out.push_str("0.");
+            out.push_str(&"0".repeat(-point as usize));
+            out.push_str(&digits);
+        } else if point as usize >= digits.len() {
+            out.push_str(&digits);
+            out.push_str(&"0".repeat(point as usize - digits.len()));
+        } else {
+            let (int, frac) = digits.split_at(point as usize);
+            out.push_str(int);
+            out.push('.');
+            out.push_str(frac);
+        }
+    } else {
+        let (first, rest) = digits.split_at(1);
+        out.push_str(first);
+        if !rest.is_empty() {
+            out.push('.');
+            out.push_str(rest);
+        }
+        out.push('e');
+        out.push(if exponent < 0 { '-' } else { '+' });
+        out.push_str(&exponent.abs().to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::backend;
+    use crate::strategies::{layout, value};
+
+    fn canonical(text: &str) -> String {
+        print(&Value::Number(Number::from_text(text)), Style::Canonical)
+    }
+
+    #[test]
+    fn prints_canonical_numbers() {
+        let cases = [
+            ("0", "0"),
+            ("-0.0", "0"),
+            ("1.0", "1"),
+            ("-12", "-12"),
+            ("1.5e2", "150"),
+            ("0.000001", "0.000001"),
+            ("0.0000001", "1e-7"),
+            ("-1.25e-7", "-1.25e-7"),
+            ("123456789012345678901", "123456789012345680000"),
+            ("1e21", "1e+21"),
+            ("4.5e300", "4.5e+300"),
+            ("18446744073709551615", "18446744073709552000"),
+            ("1e400", "1e400"),
+            ("-1E+400", "-1E+400"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(canonical(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn prints_every_style() {
+        let value = backend::by_name("rowan")
+            .unwrap()
+            .parse_value(r#"{"b": [1, 2.50, {}], "a": "é\n", "c": []}"#)
+            .unwrap();
+        expect![[r#"
+            {
+              "b": [
+                1,
+                2.5,
+                {}
+              ],
+              "a": "é\n",
+              "c": []
+            }"#]]
+        .assert_eq(&print(&value, Style::Pretty));
+        expect![[r#"{"b":[1,2.5,{}],"a":"é\n","c":[]}"#]]
+            .assert_eq(&print(&value, Style::Minified));
+        expect![[r#"{"a":"é\n","b":[1,2.5,{}],"c":[]}"#]]
+            .assert_eq(&print(&value, Style::Canonical));
+    }
+
+    proptest! {
+        #[test]
+        fn print_then_parse_is_identity((value, text) in value().prop_flat_map(layout)) {
+            for backend in backend::all() {
+                let parsed = backend.parse_value(&text);
+                prop_assert_eq!(parsed.as_ref(), Ok(&value), "{} on {:?}", backend.name(), text);
+                for style in [Style::Pretty, Style::Minified, Style::Canonical] {
+                    let printed = print(&value, style);
+                    let reparsed = backend.parse_value(&printed).unwrap();
+                    if style == Style::Canonical {
+                        // Members were reordered, so compare canonical forms.
+                        // Backends that read numbers as `f64` respell those
+                        // beyond its range, so both sides go through them.
+                        let parsed = parsed.as_ref().unwrap();
+                        prop_assert_eq!(print(&reparsed, style), print(parsed, style));
+                    } else {
+                        prop_assert_eq!(&reparsed, &value, "{} on {:?}", backend.name(), printed);
+                    }
+                }
+            }
+        }
+    }
+}
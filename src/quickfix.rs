@@ -0,0 +1,267 @@
+//! Fixes for the syntax errors people make most often, which the
+//! [`Parser`] attaches to its diagnostics: a missing comma, a trailing
+//! comma or an unclosed bracket, and JavaScript that is not JSON, such as
+//! bare keys, single quoted strings and `NaN`.
+
+use rowan::TextSize;
+
+use crate::edit::{apply, TextEdit};
+use crate::options::ParseOptions;
+use crate::parser::Parser;
+
+/// How many times [`fix`] parses again before it gives up on fixes that
+/// keep causing new errors.
+const MAX_ROUNDS: usize = 16;
+
+/// Applies the fixes for the syntax errors in `text` and returns the result,
+/// with any errors that have no fix left in it.
+///
+/// The fix of an error is dropped where it overlaps the fix of an earlier
+/// one, and the text is parsed again after each round, since an error often
+/// only follows from the one before it: in `{a: 1}` the parser reports `a`
+/// as a missing key and then as a missing colon. A round that leaves more
+/// errors than it started with is undone, and fixing stops there.
+pub fn fix(text: &str, options: ParseOptions) -> String {
+    let mut text = text.to_string();
+    // The text before the last round, and how many errors it had.
+    let mut last: Option<(String, usize)> = None;
+    for round in 0..=MAX_ROUNDS {
+        let parse = Parser::new(&text).options(options).parse();
+        if let Some((previous, errors)) = last.take() {
+            if parse.errors.len() > errors {
+                return previous;
+            }
+        }
+        if round == MAX_ROUNDS {
+            break;
+        }
+        let errors = parse.errors.len();
+        let mut edits: Vec<TextEdit> = vec![];
+        for diagnostic in parse.errors {
+            let overlaps = diagnostic.fix.iter().any(|new| {
+                edits.iter().any(|old| {
+                    old.range.start() < new.range.end() && new.range.start() < old.range.end()
+                })
+            });
+            if !overlaps {
+                edits.extend(diagnostic.fix);
+            }
+        }
+        if edits.is_empty() {
+            break;
+        }
+        let fixed = apply(&text, &edits);
+        last = Some((std::mem::replace(&mut text, fixed), errors));
+    }
+    text
+}
+
+/// The length of what starts `rest` where a key was expected, and the key
+/// to replace it with: a single quoted string, or an identifier followed by
+/// a colon. A single quoted string that has no JSON equivalent comes with
+/// no key, so that nothing in it is fixed either.
+pub(crate) fn key(rest: &str) -> Option<(TextSize, Option<String>)> {
+    if let Some(fix) = single_quoted(rest) {
+        return Some(fix);
+    }
+    let is_start = |c: char| c.is_ascii_alphabetic() || c == '_' || c == '$';
+    if !rest.starts_with(is_start) {
+        return None;
+    }
+    let len = rest
+        .find(|c: char| !is_start(c) && !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let (name, after) = rest.split_at(len);
+    after
+        .trim_start_matches([' ', '\t', '\r', '\n'])
+        .starts_with(':')
+        .then(|| (TextSize::of(name), Some(format!("\"{}\"", name))))
+}
+
+/// Like [`key`], where a value was expected: a single quoted string, or
+/// `NaN` or an infinity, which become `null` as in `JSON.stringify`.
+pub(crate) fn value(rest: &str) -> Option<(TextSize, Option<String>)> {
+    if let Some(fix) = single_quoted(rest) {
+        return Some(fix);
+    }
+    ["NaN", "Infinity", "-Infinity"]
+        .into_iter()
+        .find(|word| {
+            rest.strip_prefix(word)
+                .is_some_and(|after| !after.starts_with(|c: char| c.is_ascii_alphanumeric()))
+        })
+        .map(|word| (TextSize::of(word), Some("null".to_string())))
+}
+
+/// The length of a single quoted string at the start of `rest` on one
+/// line, and the string written with double quotes if it has one.
+fn single_quoted(rest: &str) -> Option<(TextSize, Option<String>)> {
+    let body = rest.strip_prefix('\'')?;
+    let mut escaped = false;
+    for (i, c) in body.char_indices() {
+        match c {
+            '\n' | '\r' => return None,
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '\'' => return Some((TextSize::of(&rest[..i + 2]), double_quoted(&body[..i]))),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The string `body` of a single quoted string, with double quotes, if
+/// every escape in it is one JSON has or `\'`.
+fn double_quoted(body: &str) -> Option<String> {
+    let mut string = String::from("\"");
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                '\'' => string.push('\''),
+                escaped @ ('"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't') => {
+                    string.push('\\');
+                    string.push(escaped);
+                }
+                'u' => {
+                    string.push_str("\\u");
+                    for _ in 0..4 {
+                        string.push(chars.next().filter(char::is_ascii_hexdigit)?);
+                    }
+                }
+                _ => return None,
+            },
+            '"' => string.push_str("\\\""),
+            c if c < ' ' => string += &format!("\\u{:04x}", c as u32),
+            c => string.push(c),
+        }
+    }
+    string.push('"');
+    Some(string)
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::{expect, Expect};
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::options::Dialect;
+    use crate::strategies::{layout, value};
+
+    fn check(text: &str, expected: Expect) {
+        let parse = Parser::new(text).parse();
+        let mut actual = String::new();
+        for diagnostic in &parse.errors {
+            let edits: Vec<_> = diagnostic
+                .fix
+                .iter()
+                .map(|edit| format!("{:?} {:?}", edit.range, edit.new_text))
+                .collect();
+            actual += &format!("{}: {}\n", diagnostic.message, edits.join(", "));
+        }
+        actual += &format!("--- fixed\n{}", fix(text, ParseOptions::default()));
+        expected.assert_eq(&actual);
+    }
+
+    #[test]
+    fn fixes_punctuation() {
+        check(
+            r#"{"a": [1 2,], "b": {"c": 3"#,
+            expect![[r#"
+            expected `,`: 8..8 ","
+            trailing commas are not allowed in JSON: 10..11 ""
+            expected `}`: 26..26 "}"
+            expected `}`: 26..26 "}"
+            --- fixed
+            {"a": [1, 2], "b": {"c": 3}}"#]],
+        );
+        check(
+            "[1, {\"a\": 2\n",
+            expect![[r#"
+            expected `}`: 11..11 "}"
+            expected `]`: 11..11 "]"
+            --- fixed
+            [1, {"a": 2}]
+        "#]],
+        );
+    }
+
+    #[test]
+    fn fixes_javascript() {
+        check(
+            r#"{a: 'it\'s "x"', $b_2 : NaN, 'c': [-Infinity, undefined 'd]}"#,
+            expect![[r#"
+                expected string key: 1..2 "\"a\""
+                expected value: 4..15 "\"it's \\\"x\\\"\""
+                expected string key: 17..21 "\"$b_2\""
+                expected value: 24..27 "null"
+                expected string key: 29..32 "\"c\""
+                expected value: 35..44 "null"
+                expected value: 
+                expected `,`: 
+                expected value: 
+                --- fixed
+                {"a": "it's \"x\"", "$b_2" : null, "c": [null, undefined 'd]}"#]],
+        );
+        check(
+            r#"{a 1, Infinityx: 2}"#,
+            expect![[r#"
+            expected string key: 
+            expected `:`: 
+            expected string key: 6..15 "\"Infinityx\""
+            --- fixed
+            {a 1, "Infinityx": 2}"#]],
+        );
+    }
+
+    #[test]
+    fn leaves_invalid_escapes() {
+        check(
+            r#"['\u12']"#,
+            expect![[r#"
+                expected value: 
+                --- fixed
+                ['\u12']"#]],
+        );
+        check(
+            r#"['\x41', '\u00e9\/']"#,
+            expect![[r#"
+                expected value: 
+                expected value: 9..19 "\"\\u00e9\\/\""
+                --- fixed
+                ['\x41', "\u00e9\/"]"#]],
+        );
+        check(
+            r#"{"k": 'tab\0'}"#,
+            expect![[r#"
+                expected value: 
+                --- fixed
+                {"k": 'tab\0'}"#]],
+        );
+    }
+
+    #[test]
+    fn keeps_what_the_dialect_allows() {
+        let jsonc = ParseOptions {
+            dialect: Dialect::Jsonc,
+            ..ParseOptions::default()
+        };
+        assert_eq!(fix("[1, 2,] // ok", jsonc), "[1, 2,] // ok");
+        assert_eq!(fix("[1, 2,]", ParseOptions::default()), "[1, 2]");
+    }
+
+    proptest! {
+        #[test]
+        fn leaves_valid_input_alone((_, text) in value().prop_flat_map(layout)) {
+            prop_assert_eq!(fix(&text, ParseOptions::default()), text);
+        }
+
+        #[test]
+        fn never_adds_errors(text in "[\\[\\]{}:,\"'\\\\ aNIux$0-9.e-]{0,32}") {
+            let errors = |text: &str| Parser::new(text).parse().errors.len();
+            let fixed = fix(&text, ParseOptions::default());
+            prop_assert!(errors(&fixed) <= errors(&text), "{:?}", fixed);
+        }
+    }
+}
@@ -1,7 +1,12 @@
 use std::{fmt, iter::Peekable};
 
+use rowan::{TextRange, TextSize};
+
+use crate::diagnostic::Diagnostic;
 use crate::lexer::{Lexer, SyntaxKind};
+use crate::options::{string_too_long, Limit, ParseOptions, COMMENT_ERROR};
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value<'a> {
     String(&'a str),
     Boolean(bool),
@@ -43,132 +48,217 @@ impl<'a> fmt::Display for Value<'a> {
 }
 
 pub struct Parser<'a> {
+    input: &'a str,
     lexer: Peekable<Lexer<'a>>,
+    offset: TextSize,
+    options: ParseOptions,
+    depth: usize,
+    nodes: usize,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(input: &'a str) -> Self {
+        Self::with_options(input, ParseOptions::default())
+    }
+
+    pub fn with_options(input: &'a str, options: ParseOptions) -> Self {
         Self {
+            input,
             lexer: Lexer::new(input).peekable(),
+            offset: 0.into(),
+            options,
+            depth: 0,
+            nodes: 0,
         }
     }
 
-    pub fn parse(&mut self) -> Value<'a> {
-        self.parse_element()
+    /// Parses a single value, stopping at the first error.
+    pub fn parse(&mut self) -> Result<Value<'a>, Diagnostic> {
+        if self.input.len() > self.options.max_bytes {
+            let excess = TextRange::new(
+                TextSize::from(self.options.max_bytes as u32),
+                TextSize::of(self.input),
+            );
+            return Err(Diagnostic::new(Limit::Bytes.message(&self.options), excess));
+        }
+        let value = self.parse_element()?;
+        if self.peek().is_some() {
+            return Err(self.error("expected end of input"));
+        }
+        Ok(value)
     }
 
-    pub fn parse_element(&mut self) -> Value<'a> {
-        self.skip_whitespace();
+    pub fn parse_element(&mut self) -> Result<Value<'a>, Diagnostic> {
+        self.skip_trivia()?;
+        self.nodes += 1;
+        if self.nodes > self.options.max_nodes && self.peek().is_some() {
+            return Err(self.limit(Limit::Nodes));
+        }
         let res = match self.peek() {
-            Some(t) => match t {
-                SyntaxKind::LeftBrace => self.parse_object(),
-                SyntaxKind::LeftBracket => self.parse_array(),
-                SyntaxKind::True => {
-                    self.bump();
-                    Value::Boolean(true)
-                }
-                SyntaxKind::False => {
-                    self.bump();
-                    Value::Boolean(false)
-                }
-                SyntaxKind::Null => {
-                    self.bump();
-                    Value::Null
-                }
-                SyntaxKind::String => {
-                    let (_, inner) = self.bump();
-                    Value::String(inner)
-                }
-                SyntaxKind::Number => {
-                    let (_, inner) = self.bump();
-                    let res = inner.parse().unwrap();
-                    Value::Number(res)
-                }
-                // SyntaxKind::Whitespace
-                // | SyntaxKind::Error
-                // | SyntaxKind::RightBrace
-                // | SyntaxKind::Colon
-                // | SyntaxKind::Comma
-                // | SyntaxKind::RightBracket => unreachable!(),
-                _ => {
-                    unreachable!()
-                }
-            },
-            None => {
-                unreachable!("expected have a element")
+            Some(SyntaxKind::LeftBrace) => self.parse_object()?,
+            Some(SyntaxKind::LeftBracket) => self.parse_array()?,
+            Some(SyntaxKind::True) => {
+                self.bump();
+                Value::Boolean(true)
+            }
+            Some(SyntaxKind::False) => {
+                self.bump();
+                Value::Boolean(false)
             }
+            Some(SyntaxKind::Null) => {
+                self.bump();
+                Value::Null
+            }
+            Some(SyntaxKind::String) => Value::String(self.bump_string()?),
+            Some(SyntaxKind::Number) => {
+                let (_, inner) = self.bump();
+                let res = inner.parse().unwrap();
+                Value::Number(res)
+            }
+            _ => return Err(self.error("expected value")),
         };
-        self.skip_whitespace();
-        res
+        self.skip_trivia()?;
+        Ok(res)
     }
 
-    pub fn parse_member(&mut self) -> (&'a str, Value<'a>) {
-        self.skip_whitespace();
+    pub fn parse_member(&mut self) -> Result<(&'a str, Value<'a>), Diagnostic> {
+        self.skip_trivia()?;
 
         let key = match self.peek() {
-            Some(SyntaxKind::String) => self.bump().1,
-            None => todo!(),
-            _ => {
-                let res = self.lexer.next().unwrap();
-                panic!("{:?}", res);
-            }
+            Some(SyntaxKind::String) => self.bump_string()?,
+            _ => return Err(self.error("expected string key")),
         };
-        self.skip_whitespace();
-        assert!(matches!(self.peek(), Some(SyntaxKind::Colon)));
-        self.bump();
-        (key, self.parse_element())
+        self.skip_trivia()?;
+        self.expect(SyntaxKind::Colon, "expected `:`")?;
+        Ok((key, self.parse_element()?))
     }
-    pub(crate) fn parse_array(&mut self) -> Value<'a> {
+
+    pub(crate) fn parse_array(&mut self) -> Result<Value<'a>, Diagnostic> {
+        self.enter()?;
         self.bump();
-        self.skip_whitespace();
+        self.skip_trivia()?;
         let mut ret = vec![];
         if self.peek() != Some(SyntaxKind::RightBracket) {
-            ret.push(self.parse_element());
+            ret.push(self.parse_element()?);
         }
-        self.skip_whitespace();
+        self.skip_trivia()?;
         while let Some(SyntaxKind::Comma) = self.peek() {
-            self.bump();
-            ret.push(self.parse_element());
-        }
-        if self.peek() == Some(SyntaxKind::RightBracket) {
-            self.bump();
-        } else {
-            unimplemented!("should be ] to finish array") // TODO
+            if !self.bump_comma(SyntaxKind::RightBracket)? {
+                break;
+            }
+            ret.push(self.parse_element()?);
         }
-        Value::Array(ret)
+        self.expect(SyntaxKind::RightBracket, "expected `]`")?;
+        self.depth -= 1;
+        Ok(Value::Array(ret))
     }
 
-    pub(crate) fn parse_object(&mut self) -> Value<'a> {
+    pub(crate) fn parse_object(&mut self) -> Result<Value<'a>, Diagnostic> {
+        self.enter()?;
         self.bump();
-        self.skip_whitespace();
+        self.skip_trivia()?;
         let mut ret: Vec<(&'a str, Value<'a>)> = vec![];
         if self.peek() != Some(SyntaxKind::RightBrace) {
-            ret.push(self.parse_member());
+            self.check_members(ret.len())?;
+            ret.push(self.parse_member()?);
         }
 
         while let Some(SyntaxKind::Comma) = self.peek() {
-            self.bump();
-            ret.push(self.parse_member());
+            if !self.bump_comma(SyntaxKind::RightBrace)? {
+                break;
+            }
+            self.check_members(ret.len())?;
+            ret.push(self.parse_member()?);
         }
-        if self.peek() == Some(SyntaxKind::RightBrace) {
+        self.expect(SyntaxKind::RightBrace, "expected `}`")?;
+        self.depth -= 1;
+        Ok(Value::Object(ret))
+    }
+
+    /// Counts the container about to be opened against the depth limit.
+    fn enter(&mut self) -> Result<(), Diagnostic> {
+        if self.depth == self.options.max_depth {
+            return Err(self.limit(Limit::Depth));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Fails if an object that already has `count` members may not have
+    /// another one.
+    fn check_members(&mut self, count: usize) -> Result<(), Diagnostic> {
+        if count == self.options.max_members {
+            self.skip_trivia()?;
+            return Err(self.limit(Limit::Members));
+        }
+        Ok(())
+    }
+
+    fn bump_string(&mut self) -> Result<&'a str, Diagnostic> {
+        match self.lexer.peek() {
+            Some((_, text)) if string_too_long(text, &self.options) => {
+                Err(self.limit(Limit::StringLen))
+            }
+            _ => Ok(self.bump().1),
+        }
+    }
+
+    fn limit(&mut self, limit: Limit) -> Diagnostic {
+        let message = limit.message(&self.options);
+        self.error(&message)
+    }
+
+    /// Skips whitespace, and comments if the dialect allows them.
+    pub fn skip_trivia(&mut self) -> Result<(), Diagnostic> {
+        while let Some(kind) = self.peek().filter(|kind| kind.is_trivia()) {
+            if kind == SyntaxKind::Comment && !self.options.dialect.allows_comments() {
+                return Err(self.error(COMMENT_ERROR));
+            }
             self.bump();
-        } else {
-            unimplemented!(r#"should be {} to finish object, {:?}"#, "}", self.peek())
-            // TODO
         }
-        Value::Object(ret)
+        Ok(())
+    }
+
+    /// Bumps the comma in front of the next item, returning `false` if it
+    /// turns out to be a trailing comma the dialect allows.
+    fn bump_comma(&mut self, close: SyntaxKind) -> Result<bool, Diagnostic> {
+        let comma = self.offset;
+        self.bump();
+        self.skip_trivia()?;
+        if self.peek() != Some(close) {
+            return Ok(true);
+        }
+        if self.options.dialect.allows_trailing_commas() {
+            return Ok(false);
+        }
+        let message = "trailing commas are not allowed in JSON";
+        Err(Diagnostic::new(message, TextRange::at(comma, 1.into())))
     }
-    pub fn skip_whitespace(&mut self) {
-        while let Some(SyntaxKind::Whitespace) = self.peek() {
+
+    fn expect(&mut self, kind: SyntaxKind, message: &str) -> Result<(), Diagnostic> {
+        if self.peek() == Some(kind) {
             self.bump();
+            Ok(())
+        } else {
+            Err(self.error(message))
         }
     }
 
+    fn error(&mut self, message: &str) -> Diagnostic {
+        let len = self
+            .lexer
+            .peek()
+            .map_or(0.into(), |(_, text)| TextSize::of(*text));
+        Diagnostic::new(message, TextRange::at(self.offset, len))
+    }
+
     fn peek(&mut self) -> Option<SyntaxKind> {
         self.lexer.peek().map(|(kind, _)| *kind)
     }
 
     fn bump(&mut self) -> (SyntaxKind, &'a str) {
-        self.lexer.next().unwrap()
+        let token = self.lexer.next().unwrap();
+        self.offset += TextSize::of(token.1);
+        token
     }
 }
@@ -0,0 +1,330 @@
+//! Renames object keys, as [`TextEdit`]s that touch only the key tokens.
+//!
+//! A rename covers the key under the cursor, the same key in every object
+//! of the array that object is in, or every key a path leads to. It is
+//! refused when a renamed object already has a member with the new name,
+//! since the document would then repeat a key.
+
+use std::fmt;
+
+use rowan::TextSize;
+
+use crate::diagnostic::Diagnostic;
+use crate::edit::TextEdit;
+use crate::escape::escape;
+use crate::lexer::SyntaxKind;
+use crate::syntax::{elements, members, SyntaxElement, SyntaxNode};
+use crate::value::{parse_path, PathSegment};
+
+/// Which objects a rename at an offset covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenameScope {
+    /// Only the object the key is in.
+    Object,
+    /// Every object in the array the key's object is an element of, such
+    /// as all the records of a list. The same as [`Object`](Self::Object)
+    /// when that object is not in an array.
+    Siblings,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RenameError {
+    /// The offset is not on an object key.
+    NoKey,
+    /// The path is malformed, or does not end with a key.
+    InvalidPath,
+    /// Objects that already have a member with the new name, one
+    /// diagnostic each.
+    Collisions(Vec<Diagnostic>),
+}
+
+impl fmt::Display for RenameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenameError::NoKey => f.write_str("there is no key here"),
+            RenameError::InvalidPath => f.write_str("the path does not lead to a key"),
+            RenameError::Collisions(diagnostics) => {
+                let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+                f.write_str(&messages.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for RenameError {}
+
+/// Renames the key at `offset` to `new_name` in the objects `scope` covers.
+pub fn rename_key(
+    root: &SyntaxNode,
+    offset: TextSize,
+    new_name: &str,
+    scope: RenameScope,
+) -> Result<Vec<TextEdit>, RenameError> {
+    let key = root
+        .token_at_offset(offset)
+        .find(|token| token.kind() == SyntaxKind::String)
+        .ok_or(RenameError::NoKey)?;
+    let object = key
+        .parent()
+        .filter(|node| node.kind() == SyntaxKind::Object)
+        .ok_or(RenameError::NoKey)?;
+    let member = members(&object)
+        .into_iter()
+        .find(|member| member.key == key)
+        .ok_or(RenameError::NoKey)?;
+    let objects = match object.parent() {
+        Some(array) if scope == RenameScope::Siblings && array.kind() == SyntaxKind::Array => {
+            elements(&array)
+                .into_iter()
+                .filter_map(|element| element.into_node())
+                .filter(|node| node.kind() == SyntaxKind::Object)
+                .collect()
+        }
+        _ => vec![object],
+    };
+    rename_in(&objects, &member.name(), new_name)
+}
+
+/// Renames every key `path` leads to, such as `$.jobs[*].name`, to
+/// `new_name`. The path has the syntax of [`Value::path`], with `*` or
+/// `[*]` standing for every member or element.
+///
+/// [`Value::path`]: crate::value::Value::path
+pub fn rename_path(
+    root: &SyntaxNode,
+    path: &str,
+    new_name: &str,
+) -> Result<Vec<TextEdit>, RenameError> {
+    let mut segments = parse_path(path).ok_or(RenameError::InvalidPath)?;
+    let Some(PathSegment::Name(old_name)) = segments.pop() else {
+        return Err(RenameError::InvalidPath);
+    };
+    let mut selected: Vec<SyntaxElement> = root
+        .children_with_tokens()
+        .find(|element| !element.kind().is_trivia())
+        .into_iter()
+        .collect();
+    for segment in &segments {
+        selected = selected
+            .iter()
+            .filter_map(|element| element.as_node())
+            .flat_map(|node| step(node, segment))
+            .collect();
+    }
+    let objects: Vec<_> = selected
+        .into_iter()
+        .filter_map(|element| element.into_node())
+        .filter(|node| node.kind() == SyntaxKind::Object)
+        .collect();
+    rename_in(&objects, &old_name, new_name)
+}
+
+/// The values one segment of a path leads to from `node`.
+fn step(node: &SyntaxNode, segment: &PathSegment) -> Vec<SyntaxElement> {
+    match (node.kind(), segment) {
+        (SyntaxKind::Object, PathSegment::Name(name)) => members(node)
+            .into_iter()
+            .filter(|member| member.name() == *name)
+            .filter_map(|member| member.value)
+            .collect(),
+        (SyntaxKind::Object, PathSegment::Wildcard) => members(node)
+            .into_iter()
+            .filter_map(|member| member.value)
+            .collect(),
+        (SyntaxKind::Array, PathSegment::Name(index)) => match index.parse() {
+            Ok(index) => elements(node).into_iter().nth(index).into_iter().collect(),
+            Err(_) => vec![],
+        },
+        (SyntaxKind::Array, PathSegment::Wildcard) => elements(node),
+        _ => vec![],
+    }
+}
+
+/// Renames `old_name` to `new_name` in each of `objects`, unless one of
+/// them already has a member named `new_name`.
+fn rename_in(
+    objects: &[SyntaxNode],
+    old_name: &str,
+    new_name: &str,
+) -> Result<Vec<TextEdit>, RenameError> {
+    if old_name == new_name {
+        return Ok(vec![]);
+    }
+    let key = format!("\"{}\"", escape(new_name));
+    let mut edits = vec![];
+    let mut collisions = vec![];
+    for object in objects {
+        let members = members(object);
+        let renamed: Vec<_> = members
+            .iter()
+            .filter(|member| member.name() == old_name)
+            .collect();
+        let Some(first) = renamed.first() else {
+            continue;
+        };
+        if let Some(existing) = members.iter().find(|member| member.name() == new_name) {
+            let message = format!("key `{}` already exists", new_name);
+            collisions.push(
+                Diagnostic::new(message, existing.key.text_range()).with_label(
+                    format!("`{}` is renamed here", old_name),
+                    first.key.text_range(),
+                ),
+            );
+        }
+        edits.extend(
+            renamed
+                .iter()
+                .map(|member| TextEdit::replace(member.key.text_range(), key.clone())),
+        );
+    }
+    match collisions.is_empty() {
+        true => Ok(edits),
+        false => Err(RenameError::Collisions(collisions)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::{expect, Expect};
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::backend::syntax_to_value;
+    use crate::edit::apply;
+    use crate::parser::Parser;
+    use crate::strategies::{layout, value};
+    use crate::value::Value;
+
+    const TEXT: &str = r#"{
+  "users": [
+    {"name": "ada", "role": "admin"},
+    {"name": "bob", "tags": {"name": 1}},
+    "name"
+  ],
+  "owner": {"name": "cy", "nick": "c"}
+}"#;
+
+    fn check(result: Result<Vec<TextEdit>, RenameError>, expected: Expect) {
+        let actual = match result {
+            Ok(edits) => apply(TEXT, &edits),
+            Err(RenameError::Collisions(diagnostics)) => diagnostics
+                .iter()
+                .map(|d| {
+                    format!(
+                        "{} at {:?}, {} at {:?}\n",
+                        d.message, d.range, d.labels[0].message, d.labels[0].range
+                    )
+                })
+                .collect(),
+            Err(err) => err.to_string(),
+        };
+        expected.assert_eq(&actual);
+    }
+
+    #[test]
+    fn renames_keys_in_scope() {
+        let root = Parser::new(TEXT).parse().syntax();
+        let offset = TextSize::from(TEXT.find(r#""name""#).unwrap() as u32 + 2);
+        check(
+            rename_key(&root, offset, "full \"name\"", RenameScope::Siblings),
+            expect![[r#"
+                {
+                  "users": [
+                    {"full \"name\"": "ada", "role": "admin"},
+                    {"full \"name\"": "bob", "tags": {"name": 1}},
+                    "name"
+                  ],
+                  "owner": {"name": "cy", "nick": "c"}
+                }"#]],
+        );
+        check(
+            rename_key(&root, offset, "label", RenameScope::Object),
+            expect![[r#"
+                {
+                  "users": [
+                    {"label": "ada", "role": "admin"},
+                    {"name": "bob", "tags": {"name": 1}},
+                    "name"
+                  ],
+                  "owner": {"name": "cy", "nick": "c"}
+                }"#]],
+        );
+        check(
+            rename_path(&root, "$.*.name", "label"),
+            expect![[r#"
+            {
+              "users": [
+                {"name": "ada", "role": "admin"},
+                {"name": "bob", "tags": {"name": 1}},
+                "name"
+              ],
+              "owner": {"label": "cy", "nick": "c"}
+            }"#]],
+        );
+        check(
+            rename_path(&root, "users[*].name", "role"),
+            expect![[r#"
+            key `role` already exists at 35..41, `name` is renamed here at 20..26
+        "#]],
+        );
+        check(
+            rename_path(&root, "owner.name", "nick"),
+            expect![[r#"
+            key `nick` already exists at 137..143, `name` is renamed here at 123..129
+        "#]],
+        );
+        check(
+            rename_path(&root, "users[*]", "x"),
+            expect![[r#"the path does not lead to a key"#]],
+        );
+        check(
+            rename_path(&root, r#"["\ud800\udc€€"]"#, "x"),
+            expect![[r#"the path does not lead to a key"#]],
+        );
+        let value = TextSize::from(
+            TEXT.find(
+                r#""name"
+"#,
+            )
+            .unwrap() as u32,
+        );
+        check(
+            rename_key(&root, value, "x", RenameScope::Siblings),
+            expect![[r#"there is no key here"#]],
+        );
+        check(
+            rename_key(&root, TextSize::from(0), "x", RenameScope::Object),
+            expect![[r#"there is no key here"#]],
+        );
+    }
+
+    fn renamed(value: &Value, old_name: &str, new_name: &str) -> Value {
+        match value {
+            Value::Object(members) => Value::Object(
+                members
+                    .iter()
+                    .map(|(key, value)| {
+                        let key = if key == old_name { new_name } else { key };
+                        (key.to_string(), value.clone())
+                    })
+                    .collect(),
+            ),
+            value => value.clone(),
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn renames_top_level_keys((value, text) in value().prop_flat_map(layout)) {
+            let root = Parser::new(&text).parse().syntax();
+            let Value::Object(members) = &value else { return Ok(()) };
+            let Some((old_name, _)) = members.first() else { return Ok(()) };
+            let path = format!("[\"{}\"]", escape(old_name));
+            let new_name = "\u{1}\"\\ renamed";
+            let edits = rename_path(&root, &path, new_name).unwrap();
+            let text = apply(&text, &edits);
+            let actual = syntax_to_value(&Parser::new(&text).parse().syntax());
+            prop_assert_eq!(actual, Some(renamed(&value, old_name, new_name)));
+        }
+    }
+}
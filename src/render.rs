@@ -0,0 +1,296 @@
+//! Renders diagnostics as annotated source snippets for a terminal.
+//!
+//! ```text
+//! error: expected `}`
+//!  --> 1:13
+//!   |
+//! 1 | [1 2, {"a" 3
+//!   |             ^
+//!   |       - unclosed `{` opened here
+//! ```
+
+use std::fmt::Write;
+
+use rowan::{TextRange, TextSize};
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::line_index::{LineIndex, WideEncoding};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Highlight with ANSI escape codes.
+    Always,
+    Never,
+}
+
+/// Renders `diagnostics` without color.
+pub fn render_diagnostics(source: &str, diagnostics: &[Diagnostic]) -> String {
+    render_diagnostics_with(source, diagnostics, ColorChoice::Never)
+}
+
+pub fn render_diagnostics_with(
+    source: &str,
+    diagnostics: &[Diagnostic],
+    color: ColorChoice,
+) -> String {
+    let renderer = Renderer {
+        source,
+        index: LineIndex::new(source),
+        color,
+    };
+    let mut out = String::new();
+    for (i, diagnostic) in diagnostics.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        renderer.render(&mut out, diagnostic);
+    }
+    out
+}
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// A range to underline: `^` without a message for the primary range, `-`
+/// and the label's message for secondary ones.
+struct Annotation<'d> {
+    range: TextRange,
+    message: Option<&'d str>,
+}
+
+struct Renderer<'s> {
+    source: &'s str,
+    index: LineIndex,
+    color: ColorChoice,
+}
+
+impl Renderer<'_> {
+    fn render(&self, out: &mut String, diagnostic: &Diagnostic) {
+        let mut annotations = vec![Annotation {
+            range: diagnostic.range,
+            message: None,
+        }];
+        annotations.extend(diagnostic.labels.iter().map(|label| Annotation {
+            range: label.range,
+            message: Some(&label.message),
+        }));
+        let line_of = |offset: TextSize| self.index.line_col(offset).line;
+        let mut lines: Vec<u32> = annotations
+            .iter()
+            .map(|a| line_of(a.range.start()))
+            .collect();
+        lines.sort_unstable();
+        lines.dedup();
+        let width = (lines.last().unwrap() + 1).to_string().len();
+        let gutter = |out: &mut String, number: Option<u32>| {
+            let number = number.map_or(String::new(), |line| (line + 1).to_string());
+            let _ = write!(
+                out,
+                "{}",
+                self.paint(BLUE, &format!("{:>width$} |", number))
+            );
+        };
+
+        let start = self.index.line_col(diagnostic.range.start());
+        let column = self.index.to_wide(WideEncoding::Utf32, start).col;
+        let (severity, color) = match diagnostic.severity {
+            Severity::Error => ("error", RED),
+            Severity::Warning => ("warning", YELLOW),
+        };
+        let _ = writeln!(
+            out,
+            "{}{}",
+            self.paint(color, severity),
+            self.paint(BOLD, &format!(": {}", diagnostic.message))
+        );
+        let _ = writeln!(
+            out,
+            "{:width$}{} {}:{}",
+            "",
+            self.paint(BLUE, "-->"),
+            start.line + 1,
+            column + 1
+        );
+        gutter(out, None);
+        out.push('\n');
+
+        for (i, &line) in lines.iter().enumerate() {
+            if i > 0 && line > lines[i - 1] + 1 {
+                out.push_str(&self.paint(BLUE, "..."));
+                out.push('\n');
+            }
+            let line_range = self.index.line_range(line).unwrap();
+            let text = &self.source[line_range];
+            gutter(out, Some(line));
+            if !text.is_empty() {
+                out.push(' ');
+                out.push_str(text);
+            }
+            out.push('\n');
+            for annotation in annotations
+                .iter()
+                .filter(|a| line_of(a.range.start()) == line)
+            {
+                gutter(out, None);
+                out.push(' ');
+                self.underline(out, line_range, annotation, color);
+                out.push('\n');
+            }
+        }
+    }
+
+    /// Writes the markers under the part of `annotation` on the line at
+    /// `line_range`, followed by its message. Primary markers are drawn in
+    /// the severity's `color`.
+    fn underline(
+        &self,
+        out: &mut String,
+        line_range: TextRange,
+        annotation: &Annotation,
+        color: &str,
+    ) {
+        let start = annotation.range.start();
+        let end = annotation.range.end().min(line_range.end()).max(start);
+        // Keep tabs so the markers line up however wide the terminal draws them.
+        let padding: String = self.source[TextRange::new(line_range.start(), start)]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let len = self.source[TextRange::new(start, end)]
+            .chars()
+            .count()
+            .max(1);
+        out.push_str(&padding);
+        match annotation.message {
+            None => out.push_str(&self.paint(color, &"^".repeat(len))),
+            Some(message) => {
+                let text = format!("{} {}", "-".repeat(len), message);
+                out.push_str(&self.paint(BLUE, &text));
+            }
+        }
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        match self.color {
+            ColorChoice::Always => format!("{}{}{}", color, text, RESET),
+            ColorChoice::Never => text.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::{expect, Expect};
+
+    use super::*;
+    use crate::backend::{Chumsky, JsonBackend};
+    use crate::parser::Parser;
+
+    fn check(source: &str, expected: Expect) {
+        let errors = Parser::new(source).parse().errors;
+        expected.assert_eq(&render_diagnostics(source, &errors));
+    }
+
+    #[test]
+    fn renders_unclosed_delimiters() {
+        check(
+            r#"[1 2, {"a" 3"#,
+            expect![[r#"
+                error: expected `,`
+                 --> 1:4
+                  |
+                1 | [1 2, {"a" 3
+                  |    ^
+
+                error: expected `:`
+                 --> 1:12
+                  |
+                1 | [1 2, {"a" 3
+                  |            ^
+
+                error: expected `}`
+                 --> 1:13
+                  |
+                1 | [1 2, {"a" 3
+                  |             ^
+                  |       - unclosed `{` opened here
+
+                error: expected `]`
+                 --> 1:13
+                  |
+                1 | [1 2, {"a" 3
+                  |             ^
+                  | - unclosed `[` opened here
+            "#]],
+        );
+    }
+
+    #[test]
+    fn renders_labels_on_other_lines() {
+        check(
+            "{\n  \"a\": 1,\n  \"b\": [true,\n\n\n\t\"é\" null\n",
+            expect![[r#"
+                error: expected `,`
+                 --> 6:6
+                  |
+                6 | 	"é" null
+                  | 	    ^^^^
+
+                error: expected `]`
+                 --> 7:1
+                  |
+                3 |   "b": [true,
+                  |        - unclosed `[` opened here
+                ...
+                7 |
+                  | ^
+
+                error: expected `}`
+                 --> 7:1
+                  |
+                1 | {
+                  | - unclosed `{` opened here
+                ...
+                7 |
+                  | ^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn renders_chumsky_diagnostics() {
+        let source = "[1, {\"a\": 2]";
+        let errors = Chumsky.parse(source).unwrap_err();
+        expect![[r#"
+            error: unexpected `]`, expected `,` or `}`
+             --> 1:12
+              |
+            1 | [1, {"a": 2]
+              |            ^
+
+            error: unexpected `:`, expected `,` or `]`
+             --> 1:9
+              |
+            1 | [1, {"a": 2]
+              |         ^
+        "#]]
+        .assert_eq(&render_diagnostics(source, &errors));
+    }
+
+    #[test]
+    fn renders_color() {
+        let source = "[1";
+        let errors = Parser::new(source).parse().errors;
+        expect![[r#"
+            "\u{1b}[1;31merror\u{1b}[0m\u{1b}[1m: expected `]`\u{1b}[0m\n \u{1b}[1;34m-->\u{1b}[0m 1:3\n\u{1b}[1;34m  |\u{1b}[0m\n\u{1b}[1;34m1 |\u{1b}[0m [1\n\u{1b}[1;34m  |\u{1b}[0m   \u{1b}[1;31m^\u{1b}[0m\n\u{1b}[1;34m  |\u{1b}[0m \u{1b}[1;34m- unclosed `[` opened here\u{1b}[0m\n"
+        "#]]
+        .assert_debug_eq(&render_diagnostics_with(
+            source,
+            &errors,
+            ColorChoice::Always,
+        ));
+    }
+}
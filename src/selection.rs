@@ -0,0 +1,206 @@
+//! Extend and shrink selection, growing a range one syntactic step at a
+//! time the way rust-analyzer's extend selection does.
+//!
+//! From a cursor in a string the steps are the string's contents, the
+//! string, its member, the body of the object between the braces, and the
+//! object. Arrays have a body too, so `1, 2` is selected before `[1, 2]`.
+
+use rowan::{NodeOrToken, TextRange, TextSize};
+
+use crate::lexer::SyntaxKind;
+use crate::syntax::{members, SyntaxElement, SyntaxNode, SyntaxToken};
+
+/// The smallest step that strictly contains `range`, or `range` itself if
+/// it is already the whole document.
+pub fn extend_selection(root: &SyntaxNode, range: TextRange) -> TextRange {
+    let start = match range.is_empty() {
+        true => match word_at(root, range.start()) {
+            Some(token) => NodeOrToken::Token(token),
+            None => return root.text_range(),
+        },
+        false => root.covering_element(range),
+    };
+    steps(start, range)
+        .into_iter()
+        .filter(|step| step.contains_range(range) && *step != range)
+        .min_by_key(|step| step.len())
+        .unwrap_or(range)
+}
+
+/// The largest step strictly inside `range` on the way to its first
+/// token, or an empty range at its start if there is none. Without a
+/// record of how `range` was extended this goes back down towards the
+/// start rather than the cursor the selection grew from.
+pub fn shrink_selection(root: &SyntaxNode, range: TextRange) -> TextRange {
+    let first = match root.covering_element(range) {
+        NodeOrToken::Token(token) => Some(token),
+        NodeOrToken::Node(node) => node
+            .descendants_with_tokens()
+            .filter_map(|element| element.into_token())
+            .find(|token| {
+                token.kind() != SyntaxKind::Whitespace && range.contains_range(token.text_range())
+            }),
+    };
+    let Some(first) = first else {
+        return TextRange::empty(range.start());
+    };
+    let inside = TextRange::empty(first.text_range().start());
+    steps(NodeOrToken::Token(first), inside)
+        .into_iter()
+        .filter(|step| range.contains_range(*step) && *step != range)
+        .max_by_key(|step| step.len())
+        .unwrap_or(TextRange::empty(range.start()))
+}
+
+/// The token a cursor at `offset` is on. Between two tokens, a value wins
+/// over punctuation, and anything wins over whitespace.
+fn word_at(root: &SyntaxNode, offset: TextSize) -> Option<SyntaxToken> {
+    let rank = |token: &SyntaxToken| match token.kind() {
+        SyntaxKind::String
+        | SyntaxKind::Number
+        | SyntaxKind::True
+        | SyntaxKind::False
+        | SyntaxKind::Null => 0,
+        SyntaxKind::Whitespace => 2,
+        _ => 1,
+    };
+    root.token_at_offset(offset).min_by_key(rank)
+}
+
+/// The ranges a selection can grow through, from `start` up to the root,
+/// for a selection of `range` inside it.
+fn steps(start: SyntaxElement, range: TextRange) -> Vec<TextRange> {
+    let mut steps = vec![];
+    let node = match start {
+        NodeOrToken::Token(token) => {
+            if token.kind() == SyntaxKind::String && token.text().len() > 2 {
+                let inner = token.text_range();
+                let one = TextSize::from(1);
+                steps.push(TextRange::new(inner.start() + one, inner.end() - one));
+            }
+            if token.kind() != SyntaxKind::Whitespace {
+                steps.push(token.text_range());
+            }
+            token.parent()
+        }
+        NodeOrToken::Node(node) => Some(node),
+    };
+    for node in node.into_iter().flat_map(|node| node.ancestors()) {
+        if node.kind() == SyntaxKind::Object {
+            steps.extend(
+                members(&node)
+                    .iter()
+                    .map(|member| member.range())
+                    .filter(|member| member.contains_range(range)),
+            );
+        }
+        if matches!(node.kind(), SyntaxKind::Object | SyntaxKind::Array) {
+            steps.extend(body(&node));
+        }
+        steps.push(node.text_range());
+    }
+    steps
+}
+
+/// The range between a container's delimiters, without the whitespace at
+/// either end.
+fn body(node: &SyntaxNode) -> Option<TextRange> {
+    let inner: Vec<_> = node
+        .children_with_tokens()
+        .filter(|element| element.kind() != SyntaxKind::Whitespace)
+        .collect();
+    let (first, last) = match inner.as_slice() {
+        [_, first, .., last, _] => (first, last),
+        [_, only, _] => (only, only),
+        _ => return None,
+    };
+    Some(first.text_range().cover(last.text_range()))
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::parser::Parser;
+    use crate::strategies::{layout, value};
+
+    fn steps(text: &str) -> String {
+        let cursor = text.find('$').unwrap();
+        let text = text.replace('$', "");
+        let root = Parser::new(&text).parse().syntax();
+        let mut range = TextRange::empty(TextSize::from(cursor as u32));
+        let mut grown = vec![range];
+        loop {
+            let next = extend_selection(&root, range);
+            if next == range {
+                break;
+            }
+            grown.push(next);
+            range = next;
+        }
+        let mut out = String::new();
+        for range in &grown[1..] {
+            out += &format!("{:?} {}\n", range, &text[*range]);
+        }
+        for range in &grown[1..] {
+            out += &format!("{:?} -> {:?}\n", range, shrink_selection(&root, *range));
+        }
+        out
+    }
+
+    #[test]
+    fn grows_through_strings_members_and_bodies() {
+        expect![[r#"
+            24..26 zz
+            23..27 "zz"
+            16..27 "x y", "zz"
+            15..28 ["x y", "zz"]
+            10..28 "b": ["x y", "zz"]
+            2..28 "a": 1, "b": ["x y", "zz"]
+            1..29 {"a": 1, "b": ["x y", "zz"]}
+            0..30  {"a": 1, "b": ["x y", "zz"]} 
+            24..26 -> 24..24
+            23..27 -> 24..26
+            16..27 -> 16..21
+            15..28 -> 16..27
+            10..28 -> 10..13
+            2..28 -> 2..8
+            1..29 -> 2..28
+            0..30 -> 1..29
+        "#]]
+        .assert_eq(&steps(r#" {"a": 1, "b": ["x y", "z$z"]} "#));
+        expect![[r#"
+            1..2 1
+            1..6 1, {}
+            0..7 [1, {}]
+            1..2 -> 1..1
+            1..6 -> 1..2
+            0..7 -> 1..6
+        "#]]
+        .assert_eq(&steps(r#"[1$, {}]"#));
+    }
+
+    proptest! {
+        #[test]
+        fn extends_and_shrinks_within_bounds(
+            (_, text) in value().prop_flat_map(layout),
+            cursor in any::<prop::sample::Index>(),
+        ) {
+            let root = Parser::new(&text).parse().syntax();
+            let mut range = TextRange::empty(TextSize::from(cursor.index(text.len() + 1) as u32));
+            loop {
+                let next = extend_selection(&root, range);
+                if next == range {
+                    break;
+                }
+                prop_assert!(next.contains_range(range));
+                let shrunk = shrink_selection(&root, next);
+                prop_assert!(next.contains_range(shrunk) && shrunk != next);
+                range = next;
+            }
+            prop_assert_eq!(range, root.text_range());
+        }
+    }
+}
@@ -0,0 +1,385 @@
+//! A lexer that finds token boundaries with simdjson-style byte
+//! classification instead of the logos automaton.
+//!
+//! Whitespace runs are skipped and string bodies are scanned a vector at a
+//! time using whitespace and quote/backslash/control character bitmasks (AVX2 or SSE2 on
+//! x86_64, a scalar loop elsewhere). Anything the fast path cannot prove to
+//! be a complete token, such as an invalid escape or a dangling `1.`, is
+//! handed to logos starting at the same offset. Logos keeps no state between
+//! tokens, so the resulting stream is exactly the one [`crate::lexer::Lexer`]
+//! produces with the logos backend, error tokens included.
+
+use logos::Logos;
+
+use crate::lexer::SyntaxKind;
+
+pub(crate) struct SimdLexer<'a> {
+    input: &'a str,
+    pos: usize,
+    level: Level,
+}
+
+/// The widest instruction set available, detected once per lexer.
+#[derive(Clone, Copy)]
+enum Level {
+    #[cfg(not(target_arch = "x86_64"))]
+    Scalar,
+    #[cfg(target_arch = "x86_64")]
+    Sse2,
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+}
+
+impl Level {
+    fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if std::is_x86_feature_detected!("avx2") {
+                return Level::Avx2;
+            }
+            Level::Sse2
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        Level::Scalar
+    }
+
+    /// Returns the end of the whitespace run starting at `pos`, which must
+    /// be a whitespace byte.
+    fn skip_whitespace(self, bytes: &[u8], pos: usize) -> usize {
+        // Most runs are a single space or newline; don't load a vector for those.
+        if !bytes.get(pos + 1).copied().is_some_and(is_whitespace) {
+            return pos + 1;
+        }
+        match self {
+            #[cfg(not(target_arch = "x86_64"))]
+            Level::Scalar => scalar::skip_whitespace(bytes, pos),
+            // SAFETY: SSE2 is part of the x86_64 baseline.
+            #[cfg(target_arch = "x86_64")]
+            Level::Sse2 => unsafe { x86::skip_whitespace_sse2(bytes, pos) },
+            // SAFETY: `detect` checked for AVX2 support.
+            #[cfg(target_arch = "x86_64")]
+            Level::Avx2 => unsafe { x86::skip_whitespace_avx2(bytes, pos) },
+        }
+    }
+
+    /// Returns the position of the next quote, backslash or control
+    /// character at or after `pos`.
+    fn find_string_special(self, bytes: &[u8], pos: usize) -> Option<usize> {
+        match self {
+            #[cfg(not(target_arch = "x86_64"))]
+            Level::Scalar => scalar::find_string_special(bytes, pos),
+            // SAFETY: SSE2 is part of the x86_64 baseline.
+            #[cfg(target_arch = "x86_64")]
+            Level::Sse2 => unsafe { x86::find_string_special_sse2(bytes, pos) },
+            // SAFETY: `detect` checked for AVX2 support.
+            #[cfg(target_arch = "x86_64")]
+            Level::Avx2 => unsafe { x86::find_string_special_avx2(bytes, pos) },
+        }
+    }
+}
+
+impl<'a> SimdLexer<'a> {
+    pub(crate) fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            pos: 0,
+            level: Level::detect(),
+        }
+    }
+
+    fn token_end(&self, kind: SyntaxKind) -> Option<usize> {
+        let bytes = self.input.as_bytes();
+        let start = self.pos;
+        match kind {
+            SyntaxKind::Whitespace => Some(self.level.skip_whitespace(bytes, start)),
+            SyntaxKind::String => string_end(self.level, bytes, start),
+            SyntaxKind::Number => number_end(bytes, start),
+            SyntaxKind::True => keyword_end(bytes, start, b"true"),
+            SyntaxKind::False => keyword_end(bytes, start, b"false"),
+            SyntaxKind::Null => keyword_end(bytes, start, b"null"),
+            _ => Some(start + 1),
+        }
+    }
+
+    /// Lexes one token with logos, for input the fast path does not handle.
+    fn fallback(&self) -> (SyntaxKind, usize) {
+        let mut lexer = SyntaxKind::lexer(&self.input[self.pos..]);
+        let kind = lexer.next().unwrap();
+        (kind, self.pos + lexer.span().end)
+    }
+}
+
+impl<'a> Iterator for SimdLexer<'a> {
+    type Item = (SyntaxKind, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let byte = *self.input.as_bytes().get(self.pos)?;
+        let kind = match byte {
+            b'{' => Some(SyntaxKind::LeftBrace),
+            b'}' => Some(SyntaxKind::RightBrace),
+            b'[' => Some(SyntaxKind::LeftBracket),
+            b']' => Some(SyntaxKind::RightBracket),
+            b':' => Some(SyntaxKind::Colon),
+            b',' => Some(SyntaxKind::Comma),
+            b'"' => Some(SyntaxKind::String),
+            b'-' | b'0'..=b'9' => Some(SyntaxKind::Number),
+            b't' => Some(SyntaxKind::True),
+            b'f' => Some(SyntaxKind::False),
+            b'n' => Some(SyntaxKind::Null),
+            _ if is_whitespace(byte) => Some(SyntaxKind::Whitespace),
+            _ => None,
+        };
+        let (kind, end) = match kind.and_then(|kind| Some((kind, self.token_end(kind)?))) {
+            Some(token) => token,
+            None => self.fallback(),
+        };
+        let text = &self.input[self.pos..end];
+        self.pos = end;
+        Some((kind, text))
+    }
+}
+
+/// Matches the lexer's `Whitespace` token.
+fn is_whitespace(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\r' | b'\n')
+}
+
+fn keyword_end(bytes: &[u8], start: usize, keyword: &[u8]) -> Option<usize> {
+    bytes[start..]
+        .starts_with(keyword)
+        .then(|| start + keyword.len())
+}
+
+/// Returns the end of the string starting at `start`, or `None` if it is
+/// unterminated or contains an escape or a control character the `String`
+/// token does not accept.
+fn string_end(level: Level, bytes: &[u8], start: usize) -> Option<usize> {
+    let mut pos = start + 1;
+    loop {
+        pos = level.find_string_special(bytes, pos)?;
+        match bytes[pos] {
+            b'"' => return Some(pos + 1),
+            b'\\' => {}
+            _ => return None,
+        }
+        pos += match *bytes.get(pos + 1)? {
+            b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't' => 2,
+            b'u' => {
+                let hex = bytes.get(pos + 2..pos + 6)?;
+                if !hex.iter().all(|b| b.is_ascii_hexdigit()) {
+                    return None;
+                }
+                6
+            }
+            _ => return None,
+        };
+    }
+}
+
+/// Returns the end of the number starting at `start`, or `None` if the
+/// longest match is not a complete number (e.g. `1.` or `-`).
+fn number_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let digits = |pos: usize| {
+        pos + bytes[pos..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count()
+    };
+    let mut pos = start;
+    if bytes[pos] == b'-' {
+        pos += 1;
+    }
+    match bytes.get(pos)? {
+        b'0' => pos += 1,
+        b'1'..=b'9' => pos = digits(pos),
+        _ => return None,
+    }
+    if bytes.get(pos) == Some(&b'.') {
+        let end = digits(pos + 1);
+        if end == pos + 1 {
+            return None;
+        }
+        pos = end;
+    }
+    if matches!(bytes.get(pos), Some(b'e' | b'E')) {
+        pos += 1;
+        if matches!(bytes.get(pos), Some(b'+' | b'-')) {
+            pos += 1;
+        }
+        let end = digits(pos);
+        if end == pos {
+            return None;
+        }
+        pos = end;
+    }
+    Some(pos)
+}
+
+mod scalar {
+    pub(super) fn skip_whitespace(bytes: &[u8], pos: usize) -> usize {
+        pos + bytes[pos..]
+            .iter()
+            .take_while(|&&b| super::is_whitespace(b))
+            .count()
+    }
+
+    pub(super) fn find_string_special(bytes: &[u8], pos: usize) -> Option<usize> {
+        bytes[pos..]
+            .iter()
+            .position(|&b| b == b'"' || b == b'\\' || b < 0x20)
+            .map(|offset| pos + offset)
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn skip_whitespace_sse2(bytes: &[u8], mut pos: usize) -> usize {
+        while pos + 16 <= bytes.len() {
+            let chunk = _mm_loadu_si128(bytes.as_ptr().add(pos) as *const __m128i);
+            let eq = |byte: u8| _mm_cmpeq_epi8(chunk, _mm_set1_epi8(byte as i8));
+            let whitespace = _mm_or_si128(
+                _mm_or_si128(eq(b' '), eq(b'\t')),
+                _mm_or_si128(eq(b'\n'), eq(b'\r')),
+            );
+            let other = !_mm_movemask_epi8(whitespace) as u32 & 0xffff;
+            if other != 0 {
+                return pos + other.trailing_zeros() as usize;
+            }
+            pos += 16;
+        }
+        super::scalar::skip_whitespace(bytes, pos)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn skip_whitespace_avx2(bytes: &[u8], mut pos: usize) -> usize {
+        while pos + 32 <= bytes.len() {
+            let chunk = _mm256_loadu_si256(bytes.as_ptr().add(pos) as *const __m256i);
+            let eq = |byte: u8| _mm256_cmpeq_epi8(chunk, _mm256_set1_epi8(byte as i8));
+            let whitespace = _mm256_or_si256(
+                _mm256_or_si256(eq(b' '), eq(b'\t')),
+                _mm256_or_si256(eq(b'\n'), eq(b'\r')),
+            );
+            let other = !_mm256_movemask_epi8(whitespace) as u32;
+            if other != 0 {
+                return pos + other.trailing_zeros() as usize;
+            }
+            pos += 32;
+        }
+        super::scalar::skip_whitespace(bytes, pos)
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn find_string_special_sse2(bytes: &[u8], mut pos: usize) -> Option<usize> {
+        let quote = _mm_set1_epi8(b'"' as i8);
+        let backslash = _mm_set1_epi8(b'\\' as i8);
+        let control = _mm_set1_epi8(0x1f);
+        while pos + 16 <= bytes.len() {
+            let chunk = _mm_loadu_si128(bytes.as_ptr().add(pos) as *const __m128i);
+            // A byte is a control character if raising it to 0x1f leaves 0x1f.
+            let found = _mm_or_si128(
+                _mm_or_si128(
+                    _mm_cmpeq_epi8(chunk, quote),
+                    _mm_cmpeq_epi8(chunk, backslash),
+                ),
+                _mm_cmpeq_epi8(_mm_max_epu8(chunk, control), control),
+            );
+            let mask = _mm_movemask_epi8(found) as u32;
+            if mask != 0 {
+                return Some(pos + mask.trailing_zeros() as usize);
+            }
+            pos += 16;
+        }
+        super::scalar::find_string_special(bytes, pos)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn find_string_special_avx2(bytes: &[u8], mut pos: usize) -> Option<usize> {
+        let quote = _mm256_set1_epi8(b'"' as i8);
+        let backslash = _mm256_set1_epi8(b'\\' as i8);
+        let control = _mm256_set1_epi8(0x1f);
+        while pos + 32 <= bytes.len() {
+            let chunk = _mm256_loadu_si256(bytes.as_ptr().add(pos) as *const __m256i);
+            let found = _mm256_or_si256(
+                _mm256_or_si256(
+                    _mm256_cmpeq_epi8(chunk, quote),
+                    _mm256_cmpeq_epi8(chunk, backslash),
+                ),
+                _mm256_cmpeq_epi8(_mm256_max_epu8(chunk, control), control),
+            );
+            let mask = _mm256_movemask_epi8(found) as u32;
+            if mask != 0 {
+                return Some(pos + mask.trailing_zeros() as usize);
+            }
+            pos += 32;
+        }
+        super::scalar::find_string_special(bytes, pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::{Lexer, LexerBackend};
+
+    fn check_same_as_logos(input: &str) {
+        let logos: Vec<_> = Lexer::with_backend(input, LexerBackend::Logos).collect();
+        let simd: Vec<_> = Lexer::with_backend(input, LexerBackend::Simd).collect();
+        assert_eq!(simd, logos, "{:?}", input);
+    }
+
+    #[test]
+    fn assets_match_logos() {
+        check_same_as_logos(include_str!("../assets/big.json"));
+        check_same_as_logos(include_str!("../assets/test.json"));
+        check_same_as_logos(include_str!("../assets/array.json"));
+    }
+
+    #[test]
+    fn edge_cases_match_logos() {
+        for input in [
+            "",
+            "-",
+            "1.",
+            "1.e5",
+            "1e",
+            "01",
+            "-0.5E+10x",
+            "tru",
+            "nul1",
+            "falsey",
+            r#""unterminated"#,
+            r#""bad \x escape""#,
+            r#""é ꯍ \n \uABCD""#,
+            "\"tab\tinside\"",
+            "\"long string without escapes that spans more than one vector\"",
+            " \t\r\n                                         \x0c{}",
+            "[1,2,{\"a\":null}]",
+            "@#é",
+            "// line\r\n/* block ** */ /* unterminated",
+        ] {
+            check_same_as_logos(input);
+        }
+    }
+
+    #[test]
+    fn scalar_and_vector_searches_agree() {
+        let input = format!("{}\"{}\\\t", " \n".repeat(40), "x".repeat(70));
+        let bytes = input.as_bytes();
+        let level = Level::detect();
+        for pos in 0..bytes.len() {
+            if is_whitespace(bytes[pos]) {
+                assert_eq!(
+                    level.skip_whitespace(bytes, pos),
+                    scalar::skip_whitespace(bytes, pos)
+                );
+            }
+            assert_eq!(
+                level.find_string_special(bytes, pos),
+                scalar::find_string_special(bytes, pos)
+            );
+        }
+    }
+}
@@ -0,0 +1,471 @@
+//! Reorders object members and array elements, keeping comments and
+//! formatting.
+//!
+//! A container is cut into slots, one per item. A slot keeps its place and
+//! what belongs to the position: the whitespace before an item on the same
+//! line as what precedes it, and the comma after it. The item takes along
+//! what belongs to it: comments on the lines above it and blank lines
+//! before those, comments between it and its comma, and a comment after
+//! the comma on the same line. So the last member stays without a comma
+//! and a trailing comma stays trailing.
+//!
+//! Containers with syntax errors are left as they are.
+
+use std::cmp::Ordering;
+use std::ops::Range;
+
+use rowan::{GreenNode, NodeOrToken};
+
+use crate::escape::unescape;
+use crate::lexer::SyntaxKind;
+use crate::syntax::{SyntaxElement, SyntaxNode};
+use crate::value::Number;
+
+/// How to order the members of an object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyOrder {
+    /// By key, comparing the unescaped keys code point by code point.
+    Alphabetical,
+    /// The listed keys first, in the listed order, then the others in the
+    /// order they were in.
+    Given(Vec<String>),
+}
+
+/// How to order the elements of an array.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArrayOrder {
+    /// Arrays of scalars by value: `null`, then booleans, numbers and
+    /// strings.
+    Values,
+    /// Arrays of objects by the value of a member, with the objects that
+    /// lack it or have a container there last.
+    Field(String),
+}
+
+/// Sorts the members of the root object, or of every object if
+/// `recursive`.
+pub fn sort_keys(root: &SyntaxNode, order: &KeyOrder, recursive: bool) -> SyntaxNode {
+    transform(root, recursive, &|node, items| {
+        if node.kind() != SyntaxKind::Object {
+            return None;
+        }
+        let keys: Vec<_> = items.iter().map(|item| string(&item.core[0])).collect();
+        let mut permutation: Vec<usize> = (0..items.len()).collect();
+        match order {
+            KeyOrder::Alphabetical => permutation.sort_by(|&a, &b| keys[a].cmp(&keys[b])),
+            KeyOrder::Given(listed) => permutation.sort_by_key(|&i| {
+                listed
+                    .iter()
+                    .position(|key| *key == keys[i])
+                    .unwrap_or(listed.len())
+            }),
+        }
+        Some(permutation)
+    })
+}
+
+/// Sorts the elements of the root array, or of every array if
+/// `recursive`. Arrays that do not fit `order`, such as arrays of mixed
+/// scalars and containers, are left as they are.
+pub fn sort_arrays(root: &SyntaxNode, order: &ArrayOrder, recursive: bool) -> SyntaxNode {
+    transform(root, recursive, &|node, items| {
+        if node.kind() != SyntaxKind::Array {
+            return None;
+        }
+        let sort_keys: Vec<Option<Scalar>> = match order {
+            ArrayOrder::Values => items
+                .iter()
+                .map(|item| Scalar::of(item.value()))
+                .collect::<Option<Vec<_>>>()?
+                .into_iter()
+                .map(Some)
+                .collect(),
+            ArrayOrder::Field(field) => items
+                .iter()
+                .map(|item| {
+                    let object = item.value().as_node()?;
+                    let members = split(object)?;
+                    let member = members
+                        .iter()
+                        .find(|member| string(&member.core[0]) == *field);
+                    Some(member.and_then(|member| Scalar::of(member.value())))
+                })
+                .collect::<Option<Vec<_>>>()?,
+        };
+        let mut permutation: Vec<usize> = (0..items.len()).collect();
+        // Missing fields sort last.
+        permutation.sort_by(|&a, &b| match (&sort_keys[a], &sort_keys[b]) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        });
+        Some(permutation)
+    })
+}
+
+/// A value an array can be sorted by.
+#[derive(Debug, PartialEq)]
+enum Scalar {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+impl Scalar {
+    fn of(element: &SyntaxElement) -> Option<Scalar> {
+        let scalar = match element.kind() {
+            SyntaxKind::Null => Scalar::Null,
+            SyntaxKind::True => Scalar::Bool(true),
+            SyntaxKind::False => Scalar::Bool(false),
+            SyntaxKind::Number => {
+                Scalar::Number(Number::from_text(element.as_token()?.text()).as_f64())
+            }
+            SyntaxKind::String => Scalar::String(string(element)),
+            _ => return None,
+        };
+        Some(scalar)
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Scalar::Null => 0,
+            Scalar::Bool(_) => 1,
+            Scalar::Number(_) => 2,
+            Scalar::String(_) => 3,
+        }
+    }
+}
+
+impl Eq for Scalar {}
+
+impl PartialOrd for Scalar {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scalar {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Scalar::Bool(a), Scalar::Bool(b)) => a.cmp(b),
+            (Scalar::Number(a), Scalar::Number(b)) => a.total_cmp(b),
+            (Scalar::String(a), Scalar::String(b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+/// The unescaped contents of a string token.
+fn string(element: &SyntaxElement) -> String {
+    let text = element.as_token().map_or("", |token| token.text());
+    unescape(text.get(1..text.len().saturating_sub(1)).unwrap_or("")).into_owned()
+}
+
+/// A member or element, as ranges of its container's children.
+struct Item {
+    /// What stays at the position: the whitespace and comments on the
+    /// line before the item.
+    slot: Range<usize>,
+    /// What moves: comments above the item, the item itself, and any
+    /// comments before its comma.
+    moving: Range<usize>,
+    comma: Option<usize>,
+    /// A comment after the item on the same line, which moves with it.
+    trailing: Range<usize>,
+    /// The key and value of a member, or the element.
+    core: Vec<SyntaxElement>,
+}
+
+impl Item {
+    fn value(&self) -> &SyntaxElement {
+        self.core.last().unwrap()
+    }
+}
+
+/// The order to put a container's items in, or `None` to leave it alone.
+type Sort<'a> = &'a dyn Fn(&SyntaxNode, &[Item]) -> Option<Vec<usize>>;
+
+/// Applies the permutations `sort` returns for containers, to the root
+/// value or, if `recursive`, to every container.
+fn transform(root: &SyntaxNode, recursive: bool, sort: Sort) -> SyntaxNode {
+    fn rebuild(node: &SyntaxNode, depth: usize, recursive: bool, sort: Sort) -> GreenNode {
+        let children: Vec<_> = node
+            .children_with_tokens()
+            .map(|child| match child {
+                NodeOrToken::Node(child) if recursive || depth == 0 => {
+                    NodeOrToken::Node(rebuild(&child, depth + 1, recursive, sort))
+                }
+                NodeOrToken::Node(child) => NodeOrToken::Node(child.green().into_owned()),
+                NodeOrToken::Token(token) => NodeOrToken::Token(token.green().to_owned()),
+            })
+            .collect();
+        let sorted = (recursive || depth == 1)
+            .then(|| split(node))
+            .flatten()
+            .and_then(|items| Some((sort(node, &items)?, items)));
+        let Some((permutation, items)) = sorted else {
+            return GreenNode::new(node.kind().into(), children);
+        };
+        let mut order = vec![0];
+        for (slot, &moved) in items.iter().zip(&permutation) {
+            let moved = &items[moved];
+            order.extend(slot.slot.clone());
+            order.extend(moved.moving.clone());
+            order.extend(slot.comma);
+            order.extend(moved.trailing.clone());
+        }
+        let last = items.last().map_or(1, |item| item.trailing.end);
+        order.extend(last..children.len());
+        GreenNode::new(
+            node.kind().into(),
+            order.into_iter().map(|i| children[i].clone()),
+        )
+    }
+    SyntaxNode::new_root(rebuild(root, 0, recursive, sort))
+}
+
+/// Cuts a container into items, or returns `None` if it has errors.
+fn split(node: &SyntaxNode) -> Option<Vec<Item>> {
+    let object = match node.kind() {
+        SyntaxKind::Object => true,
+        SyntaxKind::Array => false,
+        _ => return None,
+    };
+    let children: Vec<SyntaxElement> = node.children_with_tokens().collect();
+    let kind = |i: usize| children[i].kind();
+    let is_trivia = |i: usize| kind(i).is_trivia();
+    let newline =
+        |i: usize| kind(i) == SyntaxKind::Whitespace && children[i].to_string().contains('\n');
+    let close = children.len().checked_sub(1)?;
+    let closes = matches!(
+        kind(close),
+        SyntaxKind::RightBrace | SyntaxKind::RightBracket
+    );
+    if close == 0
+        || !closes
+        || children
+            .iter()
+            .any(|child| child.kind() == SyntaxKind::Error)
+    {
+        return None;
+    }
+    let skip_trivia = |mut i: usize| {
+        while i < close && is_trivia(i) {
+            i += 1;
+        }
+        i
+    };
+    // A comment after `i` on the same line, and the index after it.
+    let trailing_comment = |i: usize| {
+        let comment = if i < close && kind(i) == SyntaxKind::Whitespace && !newline(i) {
+            i + 1
+        } else {
+            i
+        };
+        let is_comment = comment < close && kind(comment) == SyntaxKind::Comment;
+        match is_comment && (comment + 1 == close || newline(comment + 1)) {
+            true => comment + 1,
+            false => i,
+        }
+    };
+    let mut items = vec![];
+    let mut i = 1;
+    loop {
+        let start = i;
+        i = skip_trivia(i);
+        if i == close {
+            break;
+        }
+        let moving_start = (start..i).find(|&j| newline(j)).unwrap_or(i);
+        let mut core = vec![];
+        if object {
+            if kind(i) != SyntaxKind::String {
+                return None;
+            }
+            core.push(children[i].clone());
+            i = skip_trivia(i + 1);
+            if i == close || kind(i) != SyntaxKind::Colon {
+                return None;
+            }
+            i = skip_trivia(i + 1);
+        }
+        if i == close || matches!(kind(i), SyntaxKind::Comma | SyntaxKind::Colon) {
+            return None;
+        }
+        core.push(children[i].clone());
+        let after_value = i + 1;
+        i = skip_trivia(after_value);
+        let item = if i < close && kind(i) == SyntaxKind::Comma {
+            let trailing_end = trailing_comment(i + 1);
+            let item = Item {
+                slot: start..moving_start,
+                moving: moving_start..i,
+                comma: Some(i),
+                trailing: i + 1..trailing_end,
+                core,
+            };
+            i = trailing_end;
+            item
+        } else if i == close {
+            let trailing_end = trailing_comment(after_value);
+            i = trailing_end;
+            Item {
+                slot: start..moving_start,
+                moving: moving_start..after_value,
+                comma: None,
+                trailing: after_value..trailing_end,
+                core,
+            }
+        } else {
+            return None;
+        };
+        items.push(item);
+    }
+    Some(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::{expect, Expect};
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::backend::syntax_to_value;
+    use crate::options::{Dialect, ParseOptions};
+    use crate::parser::Parser;
+    use crate::strategies::{layout, value};
+    use crate::value::Value;
+
+    fn parse(text: &str) -> SyntaxNode {
+        let jsonc = ParseOptions {
+            dialect: Dialect::Jsonc,
+            ..ParseOptions::default()
+        };
+        Parser::new(text).options(jsonc).parse().syntax()
+    }
+
+    fn check(text: &str, sort: impl Fn(&SyntaxNode) -> SyntaxNode, expected: Expect) {
+        let sorted = sort(&parse(text)).to_string();
+        expected.assert_eq(&sorted);
+        assert_eq!(sorted.len(), text.len());
+    }
+
+    #[test]
+    fn moves_comments_with_members() {
+        let text = r#"// settings
+{
+  // about b
+  "b": {"y": 1, "x": [3, 1]}, // trailing b
+
+  "c": 2 /* before the comma */,
+  "a": 1 // trailing a
+}
+"#;
+        check(
+            text,
+            |root| sort_keys(root, &KeyOrder::Alphabetical, false),
+            expect![[r#"
+                // settings
+                {
+                  "a": 1, // trailing a
+                  // about b
+                  "b": {"y": 1, "x": [3, 1]}, // trailing b
+
+                  "c": 2 /* before the comma */
+                }
+            "#]],
+        );
+        check(
+            text,
+            |root| sort_keys(root, &KeyOrder::Alphabetical, true),
+            expect![[r#"
+                // settings
+                {
+                  "a": 1, // trailing a
+                  // about b
+                  "b": {"x": [3, 1], "y": 1}, // trailing b
+
+                  "c": 2 /* before the comma */
+                }
+            "#]],
+        );
+        check(
+            text,
+            |root| sort_keys(root, &KeyOrder::Given(vec!["c".to_string()]), false),
+            expect![[r#"
+                // settings
+                {
+
+                  "c": 2 /* before the comma */,
+                  // about b
+                  "b": {"y": 1, "x": [3, 1]}, // trailing b
+                  "a": 1 // trailing a
+                }
+            "#]],
+        );
+        check(
+            r#"{"b": 1, "a": [2, {"d": 0, "c": 0}], }"#,
+            |root| sort_keys(root, &KeyOrder::Alphabetical, true),
+            expect![[r#"{"a": [2, {"c": 0, "d": 0}], "b": 1, }"#]],
+        );
+        check(
+            r#"{"b": 1, "a": 2, "a": }"#,
+            |root| sort_keys(root, &KeyOrder::Alphabetical, true),
+            expect![[r#"{"b": 1, "a": 2, "a": }"#]],
+        );
+    }
+
+    #[test]
+    fn sorts_arrays() {
+        check(
+            r#"[3, "b", null, -1.5, true, "a", false, 1e1]"#,
+            |root| sort_arrays(root, &ArrayOrder::Values, false),
+            expect![[r#"[null, false, true, -1.5, 3, 1e1, "a", "b"]"#]],
+        );
+        check(
+            r#"[
+  {"id": 3, "name": "c"},
+  {"name": "none"},
+  {"id": 1, "name": "a"}, // one
+  {"id": 2}
+]"#,
+            |root| sort_arrays(root, &ArrayOrder::Field("id".to_string()), false),
+            expect![[r#"
+                [
+                  {"id": 1, "name": "a"}, // one
+                  {"id": 2},
+                  {"id": 3, "name": "c"},
+                  {"name": "none"}
+                ]"#]],
+        );
+        check(
+            r#"[[2, 1], [1, {}]]"#,
+            |root| sort_arrays(root, &ArrayOrder::Values, true),
+            expect![[r#"[[1, 2], [1, {}]]"#]],
+        );
+    }
+
+    fn sorted(value: Value) -> Value {
+        match value {
+            Value::Array(elements) => Value::Array(elements.into_iter().map(sorted).collect()),
+            Value::Object(members) => {
+                let mut members: Vec<_> = members
+                    .into_iter()
+                    .map(|(key, value)| (key, sorted(value)))
+                    .collect();
+                members.sort_by(|(a, _), (b, _)| a.cmp(b));
+                Value::Object(members)
+            }
+            value => value,
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn sorts_every_object((value, text) in value().prop_flat_map(layout)) {
+            let root = sort_keys(&parse(&text), &KeyOrder::Alphabetical, true);
+            prop_assert_eq!(root.to_string().len(), text.len());
+            prop_assert_eq!(syntax_to_value(&root), Some(sorted(value)));
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! proptest strategies for generating documents.
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+use crate::lexer::Lexer;
+use crate::printer::{print, Style};
+use crate::value::{Number, Value};
+
+pub(crate) fn value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        number().prop_map(Value::Number),
+        string().prop_map(Value::String),
+    ];
+    leaf.prop_recursive(4, 64, 6, |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..6).prop_map(Value::Array),
+            vec((string(), inner), 0..6).prop_map(Value::Object),
+        ]
+    })
+}
+
+fn number() -> impl Strategy<Value = Number> {
+    prop_oneof![
+        any::<u64>().prop_map(Number::PosInt),
+        (i64::MIN..0).prop_map(Number::NegInt),
+        any::<f64>()
+            .prop_filter("JSON numbers are finite", |n| n.is_finite())
+            .prop_map(Number::Float),
+        // Beyond the largest double.
+        ("-?[1-9][0-9]{0,3}", 309..400).prop_map(|(digits, exponent)| {
+            Number::Large(format!("{}e{}", digits, exponent).into())
+        }),
+    ]
+}
+
+/// Short strings of any characters, control characters and quotes included.
+fn string() -> impl Strategy<Value = String> {
+    "(?s).{0,8}"
+}
+
+/// Spells `value` with random JSON whitespace around every token.
+pub(crate) fn layout(value: Value) -> impl Strategy<Value = (Value, String)> {
+    let minified = print(&value, Style::Minified);
+    let tokens: Vec<String> = Lexer::new(&minified)
+        .map(|(_, text)| text.to_string())
+        .collect();
+    vec("[ \t\r\n]{0,3}", tokens.len() + 1).prop_map(move |whitespace| {
+        let mut text = whitespace[0].clone();
+        for (token, whitespace) in tokens.iter().zip(&whitespace[1..]) {
+            text.push_str(token);
+            text.push_str(whitespace);
+        }
+        (value.clone(), text)
+    })
+}
@@ -1,5 +1,7 @@
+use crate::escape::unescape;
 use crate::lexer::SyntaxKind;
 use num_traits::{FromPrimitive, ToPrimitive};
+use rowan::{NodeOrToken, TextRange};
 
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub enum Json {}
@@ -18,3 +20,106 @@ impl rowan::Language for Json {
 
 pub type SyntaxNode = rowan::SyntaxNode<Json>;
 pub type SyntaxToken = rowan::SyntaxToken<Json>;
+pub type SyntaxElement = rowan::SyntaxElement<Json>;
+
+/// The type of a JSON value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueKind {
+    Object,
+    Array,
+    String,
+    Number,
+    Boolean,
+    Null,
+}
+
+impl ValueKind {
+    /// The type of the value `element` is, or `None` if it is an error.
+    pub fn of(element: &SyntaxElement) -> Option<ValueKind> {
+        let kind = match element.kind() {
+            SyntaxKind::Object => ValueKind::Object,
+            SyntaxKind::Array => ValueKind::Array,
+            SyntaxKind::String => ValueKind::String,
+            SyntaxKind::Number => ValueKind::Number,
+            SyntaxKind::True | SyntaxKind::False => ValueKind::Boolean,
+            SyntaxKind::Null => ValueKind::Null,
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
+
+/// A member of an `Object` node.
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub key: SyntaxToken,
+    /// Missing when the object has an error, as in `{"a": }`.
+    pub value: Option<SyntaxElement>,
+}
+
+impl Member {
+    /// The key, unescaped.
+    pub fn name(&self) -> String {
+        let text = self.key.text();
+        unescape(&text[1..text.len() - 1]).into_owned()
+    }
+
+    /// From the start of the key to the end of the value.
+    pub fn range(&self) -> TextRange {
+        match &self.value {
+            Some(value) => self.key.text_range().cover(value.text_range()),
+            None => self.key.text_range(),
+        }
+    }
+}
+
+/// The members of `object`, in order. A key is a string that follows the
+/// `{` or a comma, and its value is what follows it other than a colon, so
+/// members are found in objects with errors too.
+pub fn members(object: &SyntaxNode) -> Vec<Member> {
+    let mut members: Vec<Member> = vec![];
+    let mut after_separator = false;
+    let mut open = false;
+    for element in items(object) {
+        let kind = element.kind();
+        match element {
+            NodeOrToken::Token(key) if after_separator && kind == SyntaxKind::String => {
+                members.push(Member { key, value: None });
+                open = true;
+            }
+            _ if is_punctuation(kind) => open &= kind == SyntaxKind::Colon,
+            value if open => {
+                members.last_mut().unwrap().value = Some(value);
+                open = false;
+            }
+            _ => {}
+        }
+        after_separator = matches!(kind, SyntaxKind::LeftBrace | SyntaxKind::Comma);
+    }
+    members
+}
+
+/// The elements of `array`, in order.
+pub fn elements(array: &SyntaxNode) -> Vec<SyntaxElement> {
+    items(array)
+        .filter(|element| !is_punctuation(element.kind()))
+        .collect()
+}
+
+/// The children of `node` other than whitespace and comments.
+fn items(node: &SyntaxNode) -> impl Iterator<Item = SyntaxElement> {
+    node.children_with_tokens()
+        .filter(|element| !element.kind().is_trivia())
+}
+
+fn is_punctuation(kind: SyntaxKind) -> bool {
+    matches!(
+        kind,
+        SyntaxKind::LeftBrace
+            | SyntaxKind::RightBrace
+            | SyntaxKind::LeftBracket
+            | SyntaxKind::RightBracket
+            | SyntaxKind::Colon
+            | SyntaxKind::Comma
+    )
+}
@@ -0,0 +1,365 @@
+//! A compact read-only representation of a document, modelled on the tape
+//! of simdjson.
+//!
+//! Every value is one `u64` entry on a flat tape: the top byte is the type
+//! tag and the remaining 56 bits are the payload. Containers are a pair of
+//! open and close entries pointing at each other, so a whole subtree can be
+//! skipped in one step. Strings and numbers point into a side table of
+//! slices borrowed from the input, and numbers are followed by a second
+//! entry holding their `f64` bits. Strings are only unescaped when read.
+
+use std::borrow::Cow;
+
+use crate::diagnostic::Diagnostic;
+use crate::escape::unescape;
+use crate::event::{Event, EventParser};
+use crate::options::ParseOptions;
+
+const PAYLOAD_MASK: u64 = (1 << 56) - 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+enum Tag {
+    ObjectStart = b'{',
+    ObjectEnd = b'}',
+    ArrayStart = b'[',
+    ArrayEnd = b']',
+    String = b'"',
+    Number = b'd',
+    True = b't',
+    False = b'f',
+    Null = b'n',
+}
+
+impl Tag {
+    fn from_entry(entry: u64) -> Tag {
+        match (entry >> 56) as u8 {
+            b'{' => Tag::ObjectStart,
+            b'}' => Tag::ObjectEnd,
+            b'[' => Tag::ArrayStart,
+            b']' => Tag::ArrayEnd,
+            b'"' => Tag::String,
+            b'd' => Tag::Number,
+            b't' => Tag::True,
+            b'f' => Tag::False,
+            b'n' => Tag::Null,
+            tag => unreachable!("invalid tape tag {}", tag),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Object,
+    Array,
+    String,
+    Number,
+    Boolean,
+    Null,
+}
+
+#[derive(Debug, Clone)]
+pub struct Tape<'a> {
+    entries: Vec<u64>,
+    /// Raw string contents (without quotes) and number texts.
+    slices: Vec<&'a str>,
+}
+
+impl<'a> Tape<'a> {
+    /// Builds the tape for `input`, failing on the first syntax error.
+    pub fn parse(input: &'a str) -> Result<Self, Diagnostic> {
+        Self::parse_with(input, ParseOptions::default())
+    }
+
+    /// Like [`Tape::parse`], with other limits. Building the tape never
+    /// recurses, so any nesting depth is fine with
+    /// [`ParseOptions::unlimited`].
+    pub fn parse_with(input: &'a str, options: ParseOptions) -> Result<Self, Diagnostic> {
+        Builder::new(input, options).build()
+    }
+
+    pub fn root(&self) -> Node<'_, 'a> {
+        Node {
+            tape: self,
+            index: 0,
+        }
+    }
+
+    fn tag(&self, index: usize) -> Tag {
+        Tag::from_entry(self.entries[index])
+    }
+
+    fn payload(&self, index: usize) -> usize {
+        (self.entries[index] & PAYLOAD_MASK) as usize
+    }
+
+    /// Returns the index of the entry following the value at `index`.
+    fn skip(&self, index: usize) -> usize {
+        match self.tag(index) {
+            Tag::ObjectStart | Tag::ArrayStart => self.payload(index) + 1,
+            Tag::Number => index + 2,
+            _ => index + 1,
+        }
+    }
+}
+
+/// A value on a [`Tape`].
+#[derive(Clone, Copy)]
+pub struct Node<'t, 'a> {
+    tape: &'t Tape<'a>,
+    index: usize,
+}
+
+impl<'t, 'a> Node<'t, 'a> {
+    pub fn kind(&self) -> Kind {
+        match self.tag() {
+            Tag::ObjectStart => Kind::Object,
+            Tag::ArrayStart => Kind::Array,
+            Tag::String => Kind::String,
+            Tag::Number => Kind::Number,
+            Tag::True | Tag::False => Kind::Boolean,
+            Tag::Null => Kind::Null,
+            Tag::ObjectEnd | Tag::ArrayEnd => unreachable!(),
+        }
+    }
+
+    /// Looks up the first member named `key` of an object.
+    pub fn get(&self, key: &str) -> Option<Node<'t, 'a>> {
+        self.iter_members()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Looks up the element at `index` of an array.
+    pub fn at(&self, index: usize) -> Option<Node<'t, 'a>> {
+        self.iter_elements().nth(index)
+    }
+
+    /// Resolves an RFC 6901 JSON pointer such as `/performances/0/name`.
+    pub fn at_pointer(&self, pointer: &str) -> Option<Node<'t, 'a>> {
+        if pointer.is_empty() {
+            return Some(*self);
+        }
+        let mut node = *self;
+        for token in pointer.strip_prefix('/')?.split('/') {
+            let token = token.replace("~1", "/").replace("~0", "~");
+            node = match node.kind() {
+                Kind::Object => node.get(&token)?,
+                Kind::Array => node.at(token.parse().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(node)
+    }
+
+    /// Iterates over the members of an object, unescaping the keys.
+    pub fn iter_members(&self) -> impl Iterator<Item = (Cow<'a, str>, Node<'t, 'a>)> {
+        let tape = self.tape;
+        let mut children = self.children(Tag::ObjectStart);
+        std::iter::from_fn(move || {
+            let key = children.next()?;
+            let value = children.next()?;
+            Some((
+                unescape(tape.slices[tape.payload(key)]),
+                Node { tape, index: value },
+            ))
+        })
+    }
+
+    /// Iterates over the elements of an array.
+    pub fn iter_elements(&self) -> impl Iterator<Item = Node<'t, 'a>> {
+        let tape = self.tape;
+        self.children(Tag::ArrayStart)
+            .map(move |index| Node { tape, index })
+    }
+
+    pub fn len(&self) -> usize {
+        match self.tag() {
+            Tag::ObjectStart => self.children(Tag::ObjectStart).count() / 2,
+            Tag::ArrayStart => self.children(Tag::ArrayStart).count(),
+            _ => 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The unescaped value of a string.
+    pub fn as_str(&self) -> Option<Cow<'a, str>> {
+        self.raw_str().map(unescape)
+    }
+
+    /// The contents of a string as written in the input, without the quotes.
+    pub fn raw_str(&self) -> Option<&'a str> {
+        (self.tag() == Tag::String).then(|| self.tape.slices[self.tape.payload(self.index)])
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        (self.tag() == Tag::Number).then(|| f64::from_bits(self.tape.entries[self.index + 1]))
+    }
+
+    /// The text of a number as written in the input.
+    pub fn raw_number(&self) -> Option<&'a str> {
+        (self.tag() == Tag::Number).then(|| self.tape.slices[self.tape.payload(self.index)])
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.tag() {
+            Tag::True => Some(true),
+            Tag::False => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.tag() == Tag::Null
+    }
+
+    fn tag(&self) -> Tag {
+        self.tape.tag(self.index)
+    }
+
+    /// Iterates over the tape indices of the direct children of a container
+    /// opened by `open`; yields nothing for other values.
+    fn children(&self, open: Tag) -> impl Iterator<Item = usize> + 't {
+        let tape = self.tape;
+        let (mut index, end) = if self.tag() == open {
+            (self.index + 1, tape.payload(self.index))
+        } else {
+            (0, 0)
+        };
+        std::iter::from_fn(move || {
+            if index >= end {
+                return None;
+            }
+            let child = index;
+            index = tape.skip(index);
+            Some(child)
+        })
+    }
+}
+
+struct Builder<'a> {
+    events: EventParser<'a>,
+    tape: Tape<'a>,
+    /// Tape indices of the open containers.
+    open: Vec<usize>,
+}
+
+impl<'a> Builder<'a> {
+    fn new(input: &'a str, options: ParseOptions) -> Self {
+        Self {
+            events: EventParser::with_options(input, options),
+            tape: Tape {
+                entries: Vec::with_capacity(input.len() / 4),
+                slices: vec![],
+            },
+            open: vec![],
+        }
+    }
+
+    fn build(mut self) -> Result<Tape<'a>, Diagnostic> {
+        while let Some(event) = self.events.next() {
+            match event?.0 {
+                Event::StartObject => self.open(Tag::ObjectStart),
+                Event::StartArray => self.open(Tag::ArrayStart),
+                Event::EndObject => self.close(Tag::ObjectEnd),
+                Event::EndArray => self.close(Tag::ArrayEnd),
+                Event::Key(raw) | Event::String(raw) => self.push_slice(Tag::String, raw),
+                Event::Number(text) => {
+                    self.push_slice(Tag::Number, text);
+                    self.tape
+                        .entries
+                        .push(text.parse::<f64>().unwrap().to_bits());
+                }
+                Event::Boolean(true) => self.push(Tag::True, 0),
+                Event::Boolean(false) => self.push(Tag::False, 0),
+                Event::Null => self.push(Tag::Null, 0),
+            }
+        }
+        Ok(self.tape)
+    }
+
+    fn open(&mut self, tag: Tag) {
+        self.open.push(self.tape.entries.len());
+        self.push(tag, 0);
+    }
+
+    fn close(&mut self, tag: Tag) {
+        let start = self.open.pop().unwrap();
+        let end = self.tape.entries.len();
+        self.tape.entries[start] |= end as u64;
+        self.push(tag, start);
+    }
+
+    fn push(&mut self, tag: Tag, payload: usize) {
+        self.tape
+            .entries
+            .push(((tag as u64) << 56) | payload as u64);
+    }
+
+    fn push_slice(&mut self, tag: Tag, slice: &'a str) {
+        self.push(tag, self.tape.slices.len());
+        self.tape.slices.push(slice);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn navigates_big_json() {
+        let tape = Tape::parse(include_str!("../assets/big.json")).unwrap();
+        let root = tape.root();
+        assert_eq!(root.kind(), Kind::Object);
+        assert_eq!(root.len(), 11);
+        assert_eq!(
+            root.at_pointer("/areaNames/205705994")
+                .unwrap()
+                .as_str()
+                .unwrap(),
+            "1er balcon central"
+        );
+        assert_eq!(root.get("performances").unwrap().len(), 243);
+        assert!(root.at_pointer("/performances/243").is_none());
+        assert!(root.at_pointer("/blockNames").unwrap().is_empty());
+    }
+
+    #[test]
+    fn scalars_and_escapes() {
+        let tape = Tape::parse(r#"{"a~b": [1.5, true, null, "é\n"], "c/d": false}"#).unwrap();
+        let root = tape.root();
+        let array = root.at_pointer("/a~0b").unwrap();
+        assert_eq!(array.at(0).unwrap().as_f64(), Some(1.5));
+        assert_eq!(array.at(0).unwrap().raw_number(), Some("1.5"));
+        assert_eq!(array.at(1).unwrap().as_bool(), Some(true));
+        assert!(array.at(2).unwrap().is_null());
+        assert_eq!(array.at(3).unwrap().as_str().unwrap(), "é\n");
+        assert_eq!(array.at(3).unwrap().raw_str(), Some(r"é\n"));
+        assert_eq!(root.at_pointer("/c~1d").unwrap().as_bool(), Some(false));
+        let keys: Vec<_> = root.iter_members().map(|(key, _)| key).collect();
+        assert_eq!(keys, ["a~b", "c/d"]);
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        for (input, message) in [
+            ("", "expected value"),
+            ("[1 2]", "expected `,` or closing delimiter"),
+            ("[1,]", "trailing commas are not allowed in JSON"),
+            (r#"{"a" 1}"#, "expected `:`"),
+            (r#"{"a": 1]"#, "expected `,` or closing delimiter"),
+            ("[[]", "expected `,` or closing delimiter"),
+            ("1 2", "expected end of input"),
+        ] {
+            assert_eq!(
+                Tape::parse(input).unwrap_err().message,
+                message,
+                "{}",
+                input
+            );
+        }
+    }
+}
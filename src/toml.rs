@@ -0,0 +1,1151 @@
+//! Converts between TOML 1.0 and [`Value`].
+//!
+//! [`parse`] reads a whole document, checking the rules on where tables
+//! and keys may be defined, and reports what JSON cannot express with a
+//! diagnostic: datetimes, `inf` and `nan`. Tables and arrays may be nested
+//! [`ParseOptions::DEFAULT_MAX_DEPTH`] deep, the root table included.
+//!
+//! [`emit`] writes a table's plain keys first, then its tables as
+//! `[headers]`, then its arrays of tables as `[[headers]]`, as TOML
+//! requires, so members can change order. It rejects what TOML cannot
+//! express: `null`, integers above `i64::MAX`, documents that are not
+//! tables, and duplicate keys.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use rowan::{TextRange, TextSize};
+
+use crate::diagnostic::Diagnostic;
+use crate::options::{Limit, ParseOptions};
+use crate::value::{Number, Value};
+
+/// Reads the TOML document `text`.
+pub fn parse(text: &str) -> Result<Value, Diagnostic> {
+    let mut parser = Parser {
+        text,
+        pos: if text.starts_with('\u{feff}') { 3 } else { 0 },
+        depth: 1,
+    };
+    let mut root = Table::new(Kind::Header);
+    // The keys of the table the key/value pairs go into.
+    let mut current = vec![];
+    loop {
+        parser.skip_trivia();
+        match parser.peek() {
+            None => break,
+            Some('[') => {
+                let start = parser.pos;
+                let array = parser.text[start..].starts_with("[[");
+                parser.pos += if array { 2 } else { 1 };
+                let keys = parser.keys()?;
+                let close = if array { "]]" } else { "]" };
+                if !parser.text[parser.pos..].starts_with(close) {
+                    let message = format!("expected `{}`", close);
+                    return Err(parser.error(&message, parser.pos));
+                }
+                parser.pos += close.len();
+                parser.depth = 1;
+                for (_, range) in &keys {
+                    parser.enter(*range)?;
+                }
+                if array {
+                    parser.enter(keys.last().unwrap().1)?;
+                }
+                open_table(&mut root, &keys, array)?;
+                current = keys;
+            }
+            Some(_) => {
+                let (keys, value) = parser.key_value()?;
+                insert(current_table(&mut root, &current), &keys, value)?;
+            }
+        }
+        parser.end_line()?;
+    }
+    Ok(root.into_value())
+}
+
+/// Writes `value`, which must be an object, as a TOML document.
+pub fn emit(value: &Value) -> Result<String, EmitError> {
+    let Value::Object(members) = value else {
+        return Err(EmitError {
+            message: "a TOML document must be a table".to_string(),
+        });
+    };
+    let mut out = String::new();
+    write_table(&mut out, &mut vec![], members)?;
+    Ok(out)
+}
+
+/// Why a value could not be written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmitError {
+    pub message: String,
+}
+
+impl fmt::Display for EmitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for EmitError {}
+
+/// How a table came to be.
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    /// As the parent of a table defined with a header.
+    Implicit,
+    /// With a header, or as the root.
+    Header,
+    /// With a dotted key.
+    Dotted,
+}
+
+struct Table {
+    /// The members, with the range of the key that defined each.
+    entries: Vec<(String, TextRange, Item)>,
+    kind: Kind,
+}
+
+/// A member of a table. Inline tables and arrays are values, so nothing
+/// can be added to them later.
+enum Item {
+    Value(Value),
+    Table(Table),
+    Tables(Vec<Table>),
+}
+
+impl Table {
+    fn new(kind: Kind) -> Self {
+        Self {
+            entries: vec![],
+            kind,
+        }
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.entries.iter().position(|(key, _, _)| key == name)
+    }
+
+    fn into_value(self) -> Value {
+        let members = self.entries.into_iter().map(|(name, _, item)| {
+            let value = match item {
+                Item::Value(value) => value,
+                Item::Table(table) => table.into_value(),
+                Item::Tables(tables) => {
+                    Value::Array(tables.into_iter().map(Table::into_value).collect())
+                }
+            };
+            (name, value)
+        });
+        Value::Object(members.collect())
+    }
+}
+
+type Key = (String, TextRange);
+
+/// Defines the table, or appends to the array of tables, that the header
+/// `keys` names.
+fn open_table(root: &mut Table, keys: &[Key], array: bool) -> Result<(), Diagnostic> {
+    let ((name, range), parents) = keys.split_last().unwrap();
+    let mut table = root;
+    for (i, (parent, range)) in parents.iter().enumerate() {
+        let index = match table.position(parent) {
+            Some(index) => index,
+            None => {
+                let implicit = Item::Table(Table::new(Kind::Implicit));
+                table.entries.push((parent.clone(), *range, implicit));
+                table.entries.len() - 1
+            }
+        };
+        let (_, first, item) = &mut table.entries[index];
+        table = match item {
+            Item::Table(table) => table,
+            Item::Tables(tables) => tables.last_mut().unwrap(),
+            Item::Value(_) => {
+                let message = format!("`{}` is not a table", dotted(&keys[..=i]));
+                return Err(Diagnostic::new(message, *range).with_label("defined here", *first));
+            }
+        };
+    }
+    let Some(index) = table.position(name) else {
+        let item = match array {
+            true => Item::Tables(vec![Table::new(Kind::Header)]),
+            false => Item::Table(Table::new(Kind::Header)),
+        };
+        table.entries.push((name.clone(), *range, item));
+        return Ok(());
+    };
+    let (_, first, item) = &mut table.entries[index];
+    match (item, array) {
+        (Item::Table(table), false) if table.kind == Kind::Implicit => {
+            table.kind = Kind::Header;
+            Ok(())
+        }
+        (Item::Tables(tables), true) => {
+            tables.push(Table::new(Kind::Header));
+            Ok(())
+        }
+        _ => {
+            let message = format!("`{}` is already defined", dotted(keys));
+            Err(Diagnostic::new(message, *range).with_label("first defined here", *first))
+        }
+    }
+}
+
+/// The table named by the header `keys`, which is already defined.
+fn current_table<'t>(root: &'t mut Table, keys: &[Key]) -> &'t mut Table {
+    let mut table = root;
+    for (name, _) in keys {
+        let index = table.position(name).unwrap();
+        table = match &mut table.entries[index].2 {
+            Item::Table(table) => table,
+            Item::Tables(tables) => tables.last_mut().unwrap(),
+            Item::Value(_) => unreachable!(),
+        };
+    }
+    table
+}
+
+/// Adds the key/value pair `keys = value` to `table`.
+fn insert(table: &mut Table, keys: &[Key], value: Value) -> Result<(), Diagnostic> {
+    let ((name, range), parents) = keys.split_last().unwrap();
+    let mut table = table;
+    for (parent, range) in parents {
+        let index = match table.position(parent) {
+            Some(index) => index,
+            None => {
+                let dotted = Item::Table(Table::new(Kind::Dotted));
+                table.entries.push((parent.clone(), *range, dotted));
+                table.entries.len() - 1
+            }
+        };
+        let (_, first, item) = &mut table.entries[index];
+        table = match item {
+            Item::Table(table) if table.kind == Kind::Dotted => table,
+            _ => {
+                let message = format!("`{}` is already defined", parent);
+                return Err(
+                    Diagnostic::new(message, *range).with_label("first defined here", *first)
+                );
+            }
+        };
+    }
+    if let Some(index) = table.position(name) {
+        let message = format!("duplicate key `{}`", name);
+        let first = table.entries[index].1;
+        return Err(Diagnostic::new(message, *range).with_label("first defined here", first));
+    }
+    table
+        .entries
+        .push((name.clone(), *range, Item::Value(value)));
+    Ok(())
+}
+
+fn dotted(keys: &[Key]) -> String {
+    let keys: Vec<_> = keys.iter().map(|(name, _)| key(name)).collect();
+    keys.join(".")
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    /// How many tables and arrays the current position is in, the root
+    /// table included.
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Goes into a table or array defined at `range`, unless that nests it
+    /// deeper than the JSON parsers allow by default.
+    fn enter(&mut self, range: TextRange) -> Result<(), Diagnostic> {
+        if self.depth == ParseOptions::DEFAULT_MAX_DEPTH {
+            let message = Limit::Depth.message(&ParseOptions::default());
+            return Err(Diagnostic::new(message, range));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Reads `keys = value`, with the value in the tables its dotted key
+    /// defines.
+    fn key_value(&mut self) -> Result<(Vec<Key>, Value), Diagnostic> {
+        let keys = self.keys()?;
+        self.expect('=')?;
+        self.skip_spaces();
+        let depth = self.depth;
+        for (_, range) in &keys[..keys.len() - 1] {
+            self.enter(*range)?;
+        }
+        let value = self.value()?;
+        self.depth = depth;
+        Ok((keys, value))
+    }
+
+    /// Reads a key, dotted or not.
+    fn keys(&mut self) -> Result<Vec<Key>, Diagnostic> {
+        let mut keys = vec![];
+        loop {
+            self.skip_spaces();
+            let start = self.pos;
+            let name = match self.peek() {
+                Some('"') => self.basic_string()?,
+                Some('\'') => self.literal_string()?,
+                _ => {
+                    let bare =
+                        self.take_while(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+                    if bare.is_empty() {
+                        return Err(self.error("expected a key", self.pos));
+                    }
+                    bare.to_string()
+                }
+            };
+            keys.push((name, self.range(start, self.pos)));
+            self.skip_spaces();
+            if self.peek() != Some('.') {
+                return Ok(keys);
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, Diagnostic> {
+        let start = self.pos;
+        let rest = &self.text[start..];
+        let value = match self.peek() {
+            Some('"') if rest.starts_with("\"\"\"") => Value::String(self.multiline_string('"')?),
+            Some('\'') if rest.starts_with("'''") => Value::String(self.multiline_string('\'')?),
+            Some('"') => Value::String(self.basic_string()?),
+            Some('\'') => Value::String(self.literal_string()?),
+            Some('[') => {
+                self.enter(self.range(start, start + 1))?;
+                self.pos += 1;
+                let mut elements = vec![];
+                loop {
+                    self.skip_trivia();
+                    if self.eat(']') {
+                        break;
+                    }
+                    elements.push(self.value()?);
+                    self.skip_trivia();
+                    if self.eat(']') {
+                        break;
+                    }
+                    if !self.eat(',') {
+                        return Err(self.error("expected `,` or `]`", self.pos));
+                    }
+                }
+                self.depth -= 1;
+                Value::Array(elements)
+            }
+            Some('{') => {
+                self.enter(self.range(start, start + 1))?;
+                self.pos += 1;
+                let mut table = Table::new(Kind::Header);
+                self.skip_spaces();
+                if !self.eat('}') {
+                    loop {
+                        let (keys, value) = self.key_value()?;
+                        insert(&mut table, &keys, value)?;
+                        self.skip_spaces();
+                        if self.eat('}') {
+                            break;
+                        }
+                        if !self.eat(',') {
+                            return Err(self.error("expected `,` or `}`", self.pos));
+                        }
+                    }
+                }
+                self.depth -= 1;
+                table.into_value()
+            }
+            _ => {
+                let token = self.take_while(|c| c.is_ascii_alphanumeric() || "_+-.:".contains(c));
+                match token {
+                    "" => return Err(self.error("expected a value", self.pos)),
+                    "true" => Value::Bool(true),
+                    "false" => Value::Bool(false),
+                    "inf" | "+inf" | "-inf" | "nan" | "+nan" | "-nan" => {
+                        let message = format!("`{}` has no JSON equivalent", token);
+                        return Err(self.error_at(&message, start, self.pos));
+                    }
+                    _ if is_datetime(token) => {
+                        // A date and a time may be separated by a space.
+                        if self.peek() == Some(' ')
+                            && self.text[self.pos + 1..].starts_with(|c: char| c.is_ascii_digit())
+                        {
+                            self.pos += 1;
+                            self.take_while(|c| c.is_ascii_alphanumeric() || "+-.:".contains(c));
+                        }
+                        let message = "TOML datetimes have no JSON equivalent";
+                        return Err(self.error_at(message, start, self.pos));
+                    }
+                    _ => Value::Number(
+                        number(token)
+                            .map_err(|message| self.error_at(&message, start, self.pos))?,
+                    ),
+                }
+            }
+        };
+        Ok(value)
+    }
+
+    fn basic_string(&mut self) -> Result<String, Diagnostic> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                Some('\\') => self.escape(&mut out)?,
+                Some(c) if c != '\t' && (c < ' ' || c == '\x7f') => {
+                    let message = match c {
+                        '\r' | '\n' => "unterminated string",
+                        _ => "control characters must be escaped",
+                    };
+                    return Err(self.error_at(message, start, self.pos));
+                }
+                Some(c) => {
+                    out.push(c);
+                    self.pos += c.len_utf8();
+                }
+                None => return Err(self.error_at("unterminated string", start, self.pos)),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, Diagnostic> {
+        let start = self.pos;
+        self.pos += 1;
+        match self.text[self.pos..].find(['\'', '\n']) {
+            Some(end) if self.text[self.pos + end..].starts_with('\'') => {
+                let text = self.text[self.pos..self.pos + end].to_string();
+                self.pos += end + 1;
+                Ok(text)
+            }
+            _ => Err(self.error_at("unterminated string", start, self.line_end())),
+        }
+    }
+
+    /// A `"""` or `'''` string, whose first line break is trimmed.
+    fn multiline_string(&mut self, quote: char) -> Result<String, Diagnostic> {
+        let start = self.pos;
+        self.pos += 3;
+        if self.text[self.pos..].starts_with("\r\n") {
+            self.pos += 2;
+        } else if self.text[self.pos..].starts_with('\n') {
+            self.pos += 1;
+        }
+        let mut out = String::new();
+        loop {
+            match self.peek() {
+                Some(c) if c == quote => {
+                    let quotes = self.text[self.pos..].len()
+                        - self.text[self.pos..].trim_start_matches(quote).len();
+                    if quotes >= 3 {
+                        // Up to two quotes may end the content.
+                        let content = quotes.min(5) - 3;
+                        out.extend(std::iter::repeat_n(quote, content));
+                        self.pos += content + 3;
+                        return Ok(out);
+                    }
+                    out.extend(std::iter::repeat_n(quote, quotes));
+                    self.pos += quotes;
+                }
+                Some('\\') if quote == '"' => {
+                    let rest = self.text[self.pos + 1..].trim_start_matches([' ', '\t']);
+                    if rest.starts_with(['\n', '\r']) {
+                        // A line ending backslash trims the whitespace
+                        // after it.
+                        self.pos = self.text.len() - rest.len();
+                        self.skip_trivia_without_comments();
+                    } else {
+                        self.escape(&mut out)?;
+                    }
+                }
+                Some(c) if !matches!(c, '\t' | '\n' | '\r') && (c < ' ' || c == '\x7f') => {
+                    let message = "control characters must be escaped";
+                    return Err(self.error_at(message, self.pos, self.pos + 1));
+                }
+                Some(c) => {
+                    out.push(c);
+                    self.pos += c.len_utf8();
+                }
+                None => return Err(self.error_at("unterminated string", start, self.pos)),
+            }
+        }
+    }
+
+    fn escape(&mut self, out: &mut String) -> Result<(), Diagnostic> {
+        let start = self.pos;
+        self.pos += 1;
+        let escaped = match self.peek() {
+            Some('b') => '\x08',
+            Some('t') => '\t',
+            Some('n') => '\n',
+            Some('f') => '\x0c',
+            Some('r') => '\r',
+            Some('e') => '\x1b',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some(c @ ('u' | 'U')) => {
+                let len = if c == 'u' { 4 } else { 8 };
+                let code = self
+                    .text
+                    .get(self.pos + 1..self.pos + 1 + len)
+                    .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .and_then(char::from_u32);
+                match code {
+                    Some(code) => {
+                        self.pos += len;
+                        code
+                    }
+                    None => {
+                        let end = (self.pos + 1 + len).min(self.line_end());
+                        return Err(self.error_at("invalid unicode escape", start, end));
+                    }
+                }
+            }
+            _ => return Err(self.error_at("invalid escape", start, self.pos + 1)),
+        };
+        self.pos += 1;
+        out.push(escaped);
+        Ok(())
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), Diagnostic> {
+        self.skip_spaces();
+        if !self.eat(c) {
+            let message = format!("expected `{}`", c);
+            return Err(self.error(&message, self.pos));
+        }
+        Ok(())
+    }
+
+    /// Checks that nothing but a comment follows on the line.
+    fn end_line(&mut self) -> Result<(), Diagnostic> {
+        self.skip_spaces();
+        if self.peek() == Some('#') {
+            self.pos = self.line_end();
+        }
+        if !matches!(self.peek(), None | Some('\r' | '\n')) {
+            return Err(self.error("expected the end of the line", self.line_end()));
+        }
+        Ok(())
+    }
+
+    /// Skips whitespace, line breaks and comments.
+    fn skip_trivia(&mut self) {
+        loop {
+            self.skip_trivia_without_comments();
+            if self.peek() != Some('#') {
+                return;
+            }
+            self.pos = self.line_end();
+        }
+    }
+
+    fn skip_trivia_without_comments(&mut self) {
+        self.take_while(|c| matches!(c, ' ' | '\t' | '\r' | '\n'));
+    }
+
+    fn skip_spaces(&mut self) {
+        self.take_while(|c| c == ' ' || c == '\t');
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let start = self.pos;
+        let rest = &self.text[start..];
+        self.pos += rest.find(|c| !f(c)).unwrap_or(rest.len());
+        &self.text[start..self.pos]
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let eaten = self.peek() == Some(c);
+        if eaten {
+            self.pos += c.len_utf8();
+        }
+        eaten
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn line_end(&self) -> usize {
+        self.text[self.pos..]
+            .find('\n')
+            .map_or(self.text.len(), |end| self.pos + end)
+    }
+
+    /// An error from the current position to `end`.
+    fn error(&self, message: &str, end: usize) -> Diagnostic {
+        self.error_at(message, self.pos, end)
+    }
+
+    fn error_at(&self, message: &str, start: usize, end: usize) -> Diagnostic {
+        Diagnostic::new(message, self.range(start, end.max(start)))
+    }
+
+    fn range(&self, start: usize, end: usize) -> TextRange {
+        TextRange::new(TextSize::from(start as u32), TextSize::from(end as u32))
+    }
+}
+
+/// Whether `token` starts like a date, `1979-05-27`, or a time, `07:32`.
+fn is_datetime(token: &str) -> bool {
+    let digits = |range: std::ops::Range<usize>| {
+        token
+            .get(range)
+            .is_some_and(|digits| digits.bytes().all(|b| b.is_ascii_digit()))
+    };
+    let date = digits(0..4) && token.get(4..5) == Some("-") && digits(5..7);
+    let time = digits(0..2) && token.get(2..3) == Some(":") && digits(3..5);
+    date || time
+}
+
+/// The number `token` spells, or why it is not a valid TOML number.
+fn number(token: &str) -> Result<Number, String> {
+    let invalid = || format!("invalid number `{}`", token);
+    let out_of_range = || format!("`{}` does not fit a 64-bit integer", token);
+    for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+        if let Some(digits) = token.strip_prefix(prefix) {
+            let digits = without_underscores(digits, |c| c.is_digit(radix)).ok_or_else(invalid)?;
+            let n = i64::from_str_radix(&digits, radix).map_err(|_| out_of_range())?;
+            return Ok(Number::PosInt(n as u64));
+        }
+    }
+    let (sign, unsigned) = match token.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", token.strip_prefix('+').unwrap_or(token)),
+    };
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(e) => (&unsigned[..e], Some(&unsigned[e + 1..])),
+        None => (unsigned, None),
+    };
+    let (int, fraction) = match mantissa.split_once('.') {
+        Some((int, fraction)) => (int, Some(fraction)),
+        None => (mantissa, None),
+    };
+    let decimal = |digits| without_underscores(digits, |c| c.is_ascii_digit()).ok_or_else(invalid);
+    let int = decimal(int)?;
+    if int.len() > 1 && int.starts_with('0') {
+        return Err(format!("leading zeros are not allowed in `{}`", token));
+    }
+    // The same number spelled as JSON.
+    let mut json = format!("{}{}", sign, int);
+    if let Some(fraction) = fraction {
+        json.push('.');
+        json.push_str(&decimal(fraction)?);
+    }
+    if let Some(exponent) = exponent {
+        json.push('e');
+        if let Some(digits) = exponent.strip_prefix('-') {
+            json.push('-');
+            json.push_str(&decimal(digits)?);
+        } else {
+            json.push_str(&decimal(exponent.strip_prefix('+').unwrap_or(exponent))?);
+        }
+    }
+    if fraction.is_none() && exponent.is_none() && json.parse::<i64>().is_err() {
+        return Err(out_of_range());
+    }
+    Ok(Number::from_text(&json))
+}
+
+/// `digits` without its underscores, if each underscore is between two
+/// digits.
+fn without_underscores(digits: &str, is_digit: impl Fn(char) -> bool) -> Option<String> {
+    let valid = digits.starts_with(&is_digit)
+        && digits.ends_with(&is_digit)
+        && !digits.contains("__")
+        && digits.chars().all(|c| c == '_' || is_digit(c));
+    valid.then(|| digits.replace('_', ""))
+}
+
+fn write_table(
+    out: &mut String,
+    path: &mut Vec<String>,
+    members: &[(String, Value)],
+) -> Result<(), EmitError> {
+    check_duplicates(members)?;
+    for (name, value) in members {
+        if !is_table(value) && !is_array_of_tables(value) {
+            path.push(name.clone());
+            out.push_str(&key(name));
+            out.push_str(" = ");
+            write_inline(out, path, value)?;
+            out.push('\n');
+            path.pop();
+        }
+    }
+    let tables = members.iter().filter_map(|(name, value)| match value {
+        Value::Object(members) => Some((name, members, "[", "]")),
+        _ => None,
+    });
+    let arrays = members
+        .iter()
+        .filter(|(_, value)| is_array_of_tables(value))
+        .flat_map(|(name, value)| {
+            match value {
+                Value::Array(elements) => elements.as_slice(),
+                _ => &[],
+            }
+            .iter()
+            .filter_map(move |element| match element {
+                Value::Object(members) => Some((name, members, "[[", "]]")),
+                _ => None,
+            })
+        });
+    for (name, members, open, close) in tables.chain(arrays) {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        path.push(name.clone());
+        let header: Vec<_> = path.iter().map(|name| key(name)).collect();
+        out.push_str(&format!("{}{}{}\n", open, header.join("."), close));
+        write_table(out, path, members)?;
+        path.pop();
+    }
+    Ok(())
+}
+
+fn write_inline(out: &mut String, path: &mut Vec<String>, value: &Value) -> Result<(), EmitError> {
+    match value {
+        Value::Null => {
+            return Err(EmitError {
+                message: format!("`null` at `{}` has no TOML equivalent", pointer(path)),
+            })
+        }
+        Value::Bool(b) => out.push_str(&b.to_string()),
+        Value::Number(Number::PosInt(n)) if *n > i64::MAX as u64 => {
+            return Err(EmitError {
+                message: format!("`{}` at `{}` does not fit a TOML integer", n, pointer(path)),
+            })
+        }
+        Value::Number(Number::Large(text)) if !text.contains(['.', 'e', 'E']) => {
+            return Err(EmitError {
+                message: format!(
+                    "`{}` at `{}` does not fit a TOML integer",
+                    text,
+                    pointer(path)
+                ),
+            })
+        }
+        // Written as is, since `inf` would not convert back to JSON.
+        Value::Number(Number::Large(text)) => out.push_str(text),
+        Value::Number(Number::Float(n)) if n.is_nan() => out.push_str("nan"),
+        Value::Number(n) if n.as_f64().is_infinite() => {
+            out.push_str(if n.as_f64() > 0.0 { "inf" } else { "-inf" })
+        }
+        Value::Number(n) => out.push_str(&n.to_string()),
+        Value::String(s) => out.push_str(&quote(s)),
+        Value::Array(elements) => {
+            out.push('[');
+            for (i, element) in elements.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                path.push(i.to_string());
+                write_inline(out, path, element)?;
+                path.pop();
+            }
+            out.push(']');
+        }
+        Value::Object(members) if members.is_empty() => out.push_str("{}"),
+        Value::Object(members) => {
+            check_duplicates(members)?;
+            out.push_str("{ ");
+            for (i, (name, value)) in members.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                out.push_str(&key(name));
+                out.push_str(" = ");
+                path.push(name.clone());
+                write_inline(out, path, value)?;
+                path.pop();
+            }
+            out.push_str(" }");
+        }
+    }
+    Ok(())
+}
+
+fn check_duplicates(members: &[(String, Value)]) -> Result<(), EmitError> {
+    let mut names = HashSet::new();
+    match members.iter().find(|(name, _)| !names.insert(name)) {
+        Some((name, _)) => Err(EmitError {
+            message: format!("duplicate key `{}` cannot be written as TOML", name),
+        }),
+        None => Ok(()),
+    }
+}
+
+fn is_table(value: &Value) -> bool {
+    matches!(value, Value::Object(_))
+}
+
+fn is_array_of_tables(value: &Value) -> bool {
+    matches!(value, Value::Array(elements) if !elements.is_empty() && elements.iter().all(is_table))
+}
+
+/// The JSON pointer to the value at `path`, for error messages.
+fn pointer(path: &[String]) -> String {
+    path.iter()
+        .map(|name| format!("/{}", name.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+/// `name` as a bare key if it can be one, and quoted otherwise.
+fn key(name: &str) -> String {
+    let bare = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        name.to_string()
+    } else {
+        quote(name)
+    }
+}
+
+fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c < ' ' || c == '\x7f' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::{expect, Expect};
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::duplicates::{resolve as resolve_duplicates, DuplicateKeys};
+    use crate::printer::{print, Style};
+    use crate::strategies::value;
+
+    fn check(text: &str, expected: Expect) {
+        let actual = match parse(text) {
+            Ok(value) => print(&value, Style::Pretty),
+            Err(err) => format!("{} at {:?}", err.message, err.range),
+        };
+        expected.assert_eq(&actual);
+    }
+
+    #[test]
+    fn reads_tables_and_arrays_of_tables() {
+        check(
+            r#"# package
+title = "rowan"
+"quoted key" = 'C:\path'
+numbers = [0x1F, 0o17, 0b11, -1_000, +1.5e3, 3.0, ]
+nested.inline = { a = 1, b.c = [true, false] }
+
+[server]
+host = """
+multi \
+  line\u00e9"""
+
+[server.limits] # trailing comment
+max = 9_223_372_036_854_775_807
+
+[[jobs]]
+name = "test"
+
+[[jobs]]
+name = '''it's'''
+[jobs.env]
+CI = "1"
+"#,
+            expect![[r#"
+                {
+                  "title": "rowan",
+                  "quoted key": "C:\\path",
+                  "numbers": [
+                    31,
+                    15,
+                    3,
+                    -1000,
+                    1500.0,
+                    3.0
+                  ],
+                  "nested": {
+                    "inline": {
+                      "a": 1,
+                      "b": {
+                        "c": [
+                          true,
+                          false
+                        ]
+                      }
+                    }
+                  },
+                  "server": {
+                    "host": "multi lineé",
+                    "limits": {
+                      "max": 9223372036854775807
+                    }
+                  },
+                  "jobs": [
+                    {
+                      "name": "test"
+                    },
+                    {
+                      "name": "it's",
+                      "env": {
+                        "CI": "1"
+                      }
+                    }
+                  ]
+                }"#]],
+        );
+        check(
+            "[a.b.c]\nx = 1\n[a]\ny = 2\n[a.b.d]\n",
+            expect![[r#"
+            {
+              "a": {
+                "b": {
+                  "c": {
+                    "x": 1
+                  },
+                  "d": {}
+                },
+                "y": 2
+              }
+            }"#]],
+        );
+    }
+
+    #[test]
+    fn rejects_what_json_cannot_express() {
+        check(
+            "when = 1979-05-27 07:32:00Z",
+            expect![[r#"TOML datetimes have no JSON equivalent at 7..27"#]],
+        );
+        check(
+            "t = 07:32:00",
+            expect![[r#"TOML datetimes have no JSON equivalent at 4..12"#]],
+        );
+        check(
+            "x = -inf",
+            expect![[r#"`-inf` has no JSON equivalent at 4..8"#]],
+        );
+        check("a = 1\na = 2", expect![[r#"duplicate key `a` at 6..7"#]]);
+        check("[a]\n[a]", expect![[r#"`a` is already defined at 5..6"#]]);
+        check("a = 1\n[a.b]", expect![[r#"`a` is not a table at 7..8"#]]);
+        check(
+            "a = {}\na.b = 1",
+            expect![[r#"`a` is already defined at 7..8"#]],
+        );
+        check(
+            "a = [1]\n[[a]]",
+            expect![[r#"`a` is already defined at 10..11"#]],
+        );
+        check(
+            "[a.b]\n[a]\nb.c = 1",
+            expect![[r#"`b` is already defined at 10..11"#]],
+        );
+        check(
+            "x = 01",
+            expect![[r#"leading zeros are not allowed in `01` at 4..6"#]],
+        );
+        check("x = 1__0", expect![[r#"invalid number `1__0` at 4..8"#]]);
+        check(
+            "x = 9223372036854775808",
+            expect![[r#"`9223372036854775808` does not fit a 64-bit integer at 4..23"#]],
+        );
+        check("x = \"a\nb\"", expect![[r#"unterminated string at 4..6"#]]);
+        check("x = \"\\q\"", expect![[r#"invalid escape at 5..7"#]]);
+        check(
+            "x = 1 y = 2",
+            expect![[r#"expected the end of the line at 6..11"#]],
+        );
+        check("x = { a = 1, }", expect![[r#"expected a key at 13..13"#]]);
+        check("= 1", expect![[r#"expected a key at 0..0"#]]);
+        check("x = ", expect![[r#"expected a value at 4..4"#]]);
+    }
+
+    #[test]
+    fn limits_nesting() {
+        let nested = |open: &str, close: &str, depth| {
+            format!("x = {}1{}", open.repeat(depth), close.repeat(depth))
+        };
+        assert!(parse(&nested("[", "]", 127)).is_ok());
+        check(
+            &nested("[", "]", 128),
+            expect![[r#"nesting exceeds the limit of 128 levels at 131..132"#]],
+        );
+        check(
+            &nested("[", "]", 1_000_000),
+            expect![[r#"nesting exceeds the limit of 128 levels at 131..132"#]],
+        );
+        check(
+            &nested("{a = ", "}", 1_000_000),
+            expect![[r#"nesting exceeds the limit of 128 levels at 639..640"#]],
+        );
+        let dotted = vec!["a"; 1_000_000].join(".");
+        check(
+            &format!("{} = 1", dotted),
+            expect![[r#"nesting exceeds the limit of 128 levels at 254..255"#]],
+        );
+        check(
+            &format!("[{}]", dotted),
+            expect![[r#"nesting exceeds the limit of 128 levels at 255..256"#]],
+        );
+        let header = format!("[{}]", vec!["a"; 126].join("."));
+        assert!(parse(&format!("{}\nx = [1]", header)).is_ok());
+        check(
+            &format!("{}\nx = [[1]]", header),
+            expect![[r#"nesting exceeds the limit of 128 levels at 259..260"#]],
+        );
+    }
+
+    #[test]
+    fn emits_tables_after_plain_keys() {
+        let value = crate::backend::syntax_to_value(
+            &crate::parser::Parser::new(
+                r#"{"server": {"host": "x", "limits": {"max": 1}}, "name": "a\"b", "n": [1, 2.5, [], {"a": {}}],
+                    "jobs": [{"name": "test", "env": {"CI": "1"}}, {}], "bare-key_1": -1e-7, "": {}}"#,
+            )
+            .parse()
+            .syntax(),
+        )
+        .unwrap();
+        let toml = emit(&value).unwrap();
+        expect![[r#"
+            name = "a\"b"
+            n = [1, 2.5, [], { a = {} }]
+            bare-key_1 = -1e-7
+
+            [server]
+            host = "x"
+
+            [server.limits]
+            max = 1
+
+            [""]
+
+            [[jobs]]
+            name = "test"
+
+            [jobs.env]
+            CI = "1"
+
+            [[jobs]]
+        "#]]
+        .assert_eq(&toml);
+        assert_eq!(emit(&parse(&toml).unwrap()).unwrap(), toml);
+        let errors: Vec<_> = [
+            "[]",
+            r#"{"a": [1, null]}"#,
+            r#"{"a": {"b": 18446744073709551615}}"#,
+            r#"{"a": 1, "a": 2}"#,
+        ]
+        .iter()
+        .map(|text| {
+            let root = crate::parser::Parser::new(text).parse().syntax();
+            let value = crate::backend::syntax_to_value(&root).unwrap();
+            emit(&value).unwrap_err().message
+        })
+        .collect();
+        expect![[r#"
+            [
+                "a TOML document must be a table",
+                "`null` at `/a/1` has no TOML equivalent",
+                "`18446744073709551615` at `/a/b` does not fit a TOML integer",
+                "duplicate key `a` cannot be written as TOML",
+            ]
+        "#]]
+        .assert_debug_eq(&errors);
+        let large = parse("a = -1.5E+400").unwrap();
+        assert_eq!(
+            large.get("a"),
+            Some(&Value::Number(Number::Large("-1.5e400".into())))
+        );
+        assert_eq!(emit(&large).unwrap(), "a = -1.5e400\n");
+        let digits = "1".repeat(400);
+        let integer = Value::Object(vec![(
+            "a".into(),
+            Value::Number(Number::from_text(&digits)),
+        )]);
+        assert!(emit(&integer)
+            .unwrap_err()
+            .message
+            .ends_with("at `/a` does not fit a TOML integer"));
+    }
+
+    /// `value` changed into something TOML can express.
+    fn representable(value: Value) -> Value {
+        match value {
+            Value::Null => Value::Bool(false),
+            Value::Number(Number::PosInt(n)) => Value::Number(Number::PosInt(n >> 1)),
+            Value::Array(elements) => {
+                Value::Array(elements.into_iter().map(representable).collect())
+            }
+            Value::Object(members) => Value::Object(
+                members
+                    .into_iter()
+                    .map(|(name, value)| (name, representable(value)))
+                    .collect(),
+            ),
+            value => value,
+        }
+    }
+
+    /// The members of a table in the order [`emit`] writes them.
+    fn emitted_order(members: Vec<(String, Value)>) -> Vec<(String, Value)> {
+        let table = |value| match value {
+            Value::Object(members) => Value::Object(emitted_order(members)),
+            value => value,
+        };
+        let mut members: Vec<_> = members
+            .into_iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    Value::Array(elements) if elements.iter().all(is_table) => {
+                        Value::Array(elements.into_iter().map(table).collect())
+                    }
+                    value => table(value),
+                };
+                (name, value)
+            })
+            .collect();
+        members.sort_by_key(|(_, value)| match value {
+            _ if is_table(value) => 1,
+            _ if is_array_of_tables(value) => 2,
+            _ => 0,
+        });
+        members
+    }
+
+    proptest! {
+        #[test]
+        fn emit_then_parse_is_identity(value in value()) {
+            let mut value = Value::Object(vec![("v".to_string(), representable(value))]);
+            resolve_duplicates(&mut value, DuplicateKeys::FirstWins);
+            let toml = emit(&value).unwrap();
+            let Value::Object(members) = value else { unreachable!() };
+            let expected = Value::Object(emitted_order(members));
+            prop_assert_eq!(parse(&toml).unwrap(), expected, "{}", toml);
+        }
+    }
+}
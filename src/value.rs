@@ -0,0 +1,328 @@
+//! The canonical owned value every backend can be converted into.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::escape::unescape;
+use crate::lexer::{Lexer, SyntaxKind};
+use crate::printer::{print, Style};
+
+/// A decoded JSON value. Objects keep their members in document order,
+/// duplicates included.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+/// A number, kept as an integer when it is written as one and fits.
+#[derive(Debug, Clone)]
+pub enum Number {
+    PosInt(u64),
+    NegInt(i64),
+    Float(f64),
+    /// A number too large for an `f64`, such as `1e400`, as it was written.
+    /// Its value is an infinity, which JSON has no other way to write.
+    Large(Box<str>),
+}
+
+impl Number {
+    /// Decodes the text of a `Number` token.
+    pub fn from_text(text: &str) -> Number {
+        if !text.contains(['.', 'e', 'E']) {
+            if let Ok(n) = text.parse::<u64>() {
+                return Number::PosInt(n);
+            }
+            if let Ok(n) = text.parse::<i64>() {
+                return Number::NegInt(n);
+            }
+        }
+        match text.parse::<f64>() {
+            Ok(n) if n.is_infinite() => Number::Large(text.into()),
+            Ok(n) => Number::Float(n),
+            Err(_) => Number::Float(f64::NAN),
+        }
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            Number::PosInt(n) => n as f64,
+            Number::NegInt(n) => n as f64,
+            Number::Float(n) => n,
+            Number::Large(ref text) if text.starts_with('-') => f64::NEG_INFINITY,
+            Number::Large(_) => f64::INFINITY,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Number::PosInt(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Number::PosInt(n) => i64::try_from(n).ok(),
+            Number::NegInt(n) => Some(n),
+            Number::Float(_) | Number::Large(_) => None,
+        }
+    }
+}
+
+/// Infinities, which parsers that only keep an `f64` give for numbers too
+/// large for one, become [`Number::Large`] with the smallest power of ten
+/// that is.
+impl From<f64> for Number {
+    fn from(n: f64) -> Self {
+        match n {
+            f64::INFINITY => Number::Large("1e309".into()),
+            f64::NEG_INFINITY => Number::Large("-1e309".into()),
+            n => Number::Float(n),
+        }
+    }
+}
+
+/// Numbers compare by value, so `1`, `1.0` and `1e0` are equal no matter
+/// which backend decoded them.
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Number::PosInt(a), Number::PosInt(b)) => a == b,
+            (Number::NegInt(a), Number::NegInt(b)) => a == b,
+            _ => self.as_f64() == other.as_f64(),
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Number::PosInt(n) => write!(f, "{}", n),
+            Number::NegInt(n) => write!(f, "{}", n),
+            // `Debug` keeps a fractional part or exponent, so the number
+            // reads back as a float.
+            Number::Float(n) if n.is_finite() => write!(f, "{:?}", n),
+            Number::Float(_) => write!(f, "null"),
+            Number::Large(ref text) => f.write_str(text),
+        }
+    }
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Looks up the element at `index` of an array.
+    pub fn at(&self, index: usize) -> Option<&Value> {
+        match self {
+            Value::Array(elements) => elements.get(index),
+            _ => None,
+        }
+    }
+
+    /// Resolves an RFC 6901 JSON pointer such as `/performances/0/name`.
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        if pointer.is_empty() {
+            return Some(self);
+        }
+        let mut value = self;
+        for token in pointer.strip_prefix('/')?.split('/') {
+            let token = token.replace("~1", "/").replace("~0", "~");
+            value = value.step(&token)?;
+        }
+        Some(value)
+    }
+
+    /// Resolves a path such as `$.performances[0].name`, `performances.0.name`
+    /// or `["key with spaces"]`. The leading `$` is optional and quoted keys
+    /// are JSON strings. Returns `None` for a malformed path too, and for a
+    /// path with a wildcard, which can match more than one value.
+    pub fn path(&self, path: &str) -> Option<&Value> {
+        let mut value = self;
+        for segment in parse_path(path)? {
+            match segment {
+                PathSegment::Name(name) => value = value.step(&name)?,
+                PathSegment::Wildcard => return None,
+            }
+        }
+        Some(value)
+    }
+
+    /// One step of a pointer or path: a member name, or an index into an
+    /// array.
+    fn step(&self, segment: &str) -> Option<&Value> {
+        match self {
+            Value::Array(_) => self.at(segment.parse().ok()?),
+            _ => self.get(segment),
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(n.as_f64()),
+            _ => None,
+        }
+    }
+}
+
+/// A step of a path, see [`Value::path`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PathSegment<'a> {
+    /// A member name, or an array index.
+    Name(Cow<'a, str>),
+    /// `*` or `[*]`, every member or element. `["*"]` is a name.
+    Wildcard,
+}
+
+/// Splits a path into its segments, or returns `None` if it is malformed.
+pub(crate) fn parse_path(path: &str) -> Option<Vec<PathSegment<'_>>> {
+    let mut rest = path.strip_prefix('$').unwrap_or(path);
+    let mut segments = vec![];
+    while !rest.is_empty() {
+        let segment;
+        if let Some(quoted) = rest.strip_prefix("[\"") {
+            let end = string_end(quoted)?;
+            if !is_string(&rest[1..end + 3]) {
+                return None;
+            }
+            segment = PathSegment::Name(unescape(&quoted[..end]));
+            rest = quoted[end + 1..].strip_prefix(']')?;
+        } else if let Some(bracketed) = rest.strip_prefix('[') {
+            let end = bracketed.find(']')?;
+            segment = plain_segment(&bracketed[..end]);
+            rest = &bracketed[end + 1..];
+        } else {
+            let dotted = match rest.strip_prefix('.') {
+                Some(dotted) => dotted,
+                None if segments.is_empty() => rest,
+                None => return None,
+            };
+            let end = dotted.find(['.', '[']).unwrap_or(dotted.len());
+            segment = plain_segment(&dotted[..end]);
+            rest = &dotted[end..];
+        }
+        segments.push(segment);
+    }
+    Some(segments)
+}
+
+fn plain_segment(text: &str) -> PathSegment<'_> {
+    match text {
+        "*" => PathSegment::Wildcard,
+        name => PathSegment::Name(Cow::Borrowed(name)),
+    }
+}
+
+/// The offset of the quote that ends the string contents at the start of
+/// `text`.
+fn string_end(text: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' if !escaped => return Some(i),
+            '\\' => escaped = !escaped,
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+/// Whether `text` lexes as exactly one valid string token.
+fn is_string(text: &str) -> bool {
+    let mut tokens = Lexer::new(text);
+    matches!(tokens.next(), Some((SyntaxKind::String, token)) if token.len() == text.len())
+        && tokens.next().is_none()
+}
+
+/// Writes the value as minified JSON.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&print(self, Style::Minified))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers() {
+        assert!(matches!(Number::from_text("12"), Number::PosInt(12)));
+        assert!(matches!(Number::from_text("-12"), Number::NegInt(-12)));
+        assert!(matches!(Number::from_text("1e2"), Number::Float(_)));
+        assert!(matches!(
+            Number::from_text("18446744073709551616"),
+            Number::Float(_)
+        ));
+        assert_eq!(Number::from_text("100"), Number::from_text("1e2"));
+        assert_eq!(Number::Float(0.1).to_string(), "0.1");
+        assert_eq!(Number::Float(1.0).to_string(), "1.0");
+        assert_eq!(Number::Float(1e300).to_string(), "1e300");
+    }
+
+    #[test]
+    fn numbers_too_large_for_a_double_keep_their_text() {
+        let large = Number::from_text("-1E+400");
+        assert!(matches!(large, Number::Large(_)));
+        assert_eq!(large.to_string(), "-1E+400");
+        assert_eq!(large.as_f64(), f64::NEG_INFINITY);
+        assert_eq!(large, Number::from_text("-1e500"));
+        assert_ne!(large, Number::from_text("1e400"));
+        assert_eq!(Number::from(f64::INFINITY).to_string(), "1e309");
+        assert_eq!(Number::from_text("1e309").to_string(), "1e309");
+    }
+
+    #[test]
+    fn display_is_minified_json() {
+        let value = Value::Object(vec![
+            (
+                "a\"".to_string(),
+                Value::Array(vec![Value::Null, Value::Bool(true)]),
+            ),
+            ("b".to_string(), Value::Number(Number::NegInt(-1))),
+        ]);
+        assert_eq!(value.to_string(), r#"{"a\"":[null,true],"b":-1}"#);
+    }
+
+    #[test]
+    fn resolves_pointers_and_paths() {
+        let value = crate::backend::by_name("rowan")
+            .unwrap()
+            .parse_value(r#"{"a": [{"b/c": 1, "d]\"": 2}], "e.f": true}"#)
+            .unwrap();
+        let one = Some(&Value::Number(Number::PosInt(1)));
+        assert_eq!(value.pointer("/a/0/b~1c"), one);
+        assert_eq!(value.pointer(""), Some(&value));
+        assert_eq!(value.pointer("/a/1"), None);
+        assert_eq!(value.pointer("a"), None);
+        assert_eq!(value.path(r#"$.a[0]["b/c"]"#), one);
+        assert_eq!(value.path(r#"a.0["b/c"]"#), one);
+        assert_eq!(
+            value.path(r#"a[0]["d]\""]"#),
+            Some(&Value::Number(Number::PosInt(2)))
+        );
+        assert_eq!(value.path(r#"["e.f"]"#), Some(&Value::Bool(true)));
+        assert_eq!(value.path("$"), Some(&value));
+        assert_eq!(value.path("e.f"), None);
+        assert_eq!(value.path("a[0"), None);
+        assert_eq!(value.path("a[*]"), None);
+        assert_eq!(value.path(r#"["\ud800\udc€€"]"#), None);
+        assert_eq!(value.path(r#"["\x41"]"#), None);
+    }
+}
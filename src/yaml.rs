@@ -0,0 +1,1325 @@
+//! Converts between a subset of YAML 1.2 and [`Value`].
+//!
+//! [`parse`] reads a single document of block and flow mappings and
+//! sequences, and of plain, quoted and block scalars, resolving plain
+//! scalars with the core schema. Anchors, aliases and `<<` merge keys are
+//! resolved as they are read, so the value has no trace of them. What JSON
+//! cannot express is rejected with a diagnostic: tags, keys that are not
+//! strings, and `.inf` and `.nan`. So are directives, multiple documents and
+//! plain scalars spanning lines, which the subset leaves out, and
+//! collections nested deeper than [`ParseOptions::DEFAULT_MAX_DEPTH`].
+//!
+//! [`emit`] writes block style YAML that [`parse`] reads back to the same
+//! value.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use rowan::{TextRange, TextSize};
+
+use crate::diagnostic::Diagnostic;
+use crate::options::{Limit, ParseOptions};
+use crate::value::{Number, Value};
+
+/// How many values aliases may expand to in total, so that a few aliases
+/// of aliases cannot build an exponentially large value.
+const MAX_ALIASED_VALUES: usize = 1_000_000;
+
+/// Reads the YAML document `text`.
+pub fn parse(text: &str) -> Result<Value, Diagnostic> {
+    let mut parser = Parser {
+        text,
+        pos: if text.starts_with('\u{feff}') { 3 } else { 0 },
+        anchors: HashMap::new(),
+        aliased: 0,
+        depth: 0,
+    };
+    parser.skip_blank_lines()?;
+    parser.document_start()?;
+    let value = if parser.at_end() {
+        Value::Null
+    } else {
+        parser.node(-1, Context::Root)?
+    };
+    parser.document_end()?;
+    Ok(value)
+}
+
+/// Writes `value` as a block style YAML document.
+pub fn emit(value: &Value) -> Result<String, EmitError> {
+    let mut out = String::new();
+    match value {
+        Value::Array(elements) if !elements.is_empty() => sequence(&mut out, elements, 0, false)?,
+        Value::Object(members) if !members.is_empty() => mapping(&mut out, members, 0, false)?,
+        value => {
+            out.push_str(&scalar(value));
+            out.push('\n');
+        }
+    }
+    Ok(out)
+}
+
+/// Why a value could not be written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmitError {
+    pub message: String,
+}
+
+impl fmt::Display for EmitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for EmitError {}
+
+/// The collection a node is read in.
+#[derive(Clone, Copy, PartialEq)]
+enum Context {
+    Root,
+    Sequence,
+    Mapping,
+}
+
+/// A member of a block mapping, or the members a `<<` key merges in.
+enum Entry {
+    Member(String, Value),
+    Merge(Vec<(String, Value)>),
+}
+
+#[derive(Clone, Copy)]
+enum Chomp {
+    Strip,
+    Clip,
+    Keep,
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    anchors: HashMap<String, Value>,
+    /// How many values aliases have expanded to so far.
+    aliased: usize,
+    /// How many collections the current position is in.
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Goes into the collection at the current position, unless that nests
+    /// it deeper than the JSON parsers allow by default.
+    fn enter(&mut self) -> Result<(), Diagnostic> {
+        if self.depth == ParseOptions::DEFAULT_MAX_DEPTH {
+            let message = Limit::Depth.message(&ParseOptions::default());
+            return Err(self.error(&message, self.pos + 1));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn document_start(&mut self) -> Result<(), Diagnostic> {
+        if self.peek() == Some('%') {
+            return Err(self.error("directives are not supported", self.line_end()));
+        }
+        if self.at_marker("---") {
+            self.pos += 3;
+            self.skip_spaces();
+            if self.at_line_end() {
+                self.end_line()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn document_end(&mut self) -> Result<(), Diagnostic> {
+        if self.at_marker("...") {
+            self.pos += 3;
+            self.end_line()?;
+        }
+        if self.at_marker("---") {
+            return Err(self.error("multiple documents are not supported", self.pos + 3));
+        }
+        if self.pos < self.text.len() {
+            return Err(self.error("expected the end of the document", self.line_end()));
+        }
+        Ok(())
+    }
+
+    /// Reads the node after a `- `, a `key:` or at the start of the
+    /// document, in a collection indented by `parent` columns, and moves to
+    /// the start of the next line with content.
+    fn node(&mut self, parent: isize, context: Context) -> Result<Value, Diagnostic> {
+        self.skip_spaces();
+        let anchor = self.properties()?;
+        let value = if self.at_line_end() {
+            // The node starts on a later line, or is empty. Only a mapping
+            // lets a sequence value start in its own column.
+            self.end_line()?;
+            let column = self.column() as isize;
+            let indented = column > parent
+                || (context == Context::Mapping && column == parent && self.at_sequence_entry());
+            if !self.at_end() && indented {
+                self.content(parent, true)?
+            } else {
+                Value::Null
+            }
+        } else {
+            self.content(parent, context != Context::Mapping)?
+        };
+        if let Some(anchor) = anchor {
+            self.anchors.insert(anchor, value.clone());
+        }
+        Ok(value)
+    }
+
+    /// Reads the node at the current position. A block collection may
+    /// only start here if `collections` is set, as a mapping cannot start
+    /// on the line of another mapping's key.
+    fn content(&mut self, parent: isize, collections: bool) -> Result<Value, Diagnostic> {
+        let start = self.pos;
+        let column = self.column();
+        let value = match self.peek() {
+            Some('|' | '>') => return self.block_scalar(parent),
+            Some('[' | '{') => self.flow_node()?,
+            Some('*') => self.alias()?,
+            _ if self.at_indicator('?') => {
+                return Err(self.error("complex keys are not supported", start + 1))
+            }
+            _ if self.at_sequence_entry() => match collections {
+                true => return self.block_sequence(column),
+                false => return Err(self.error("a sequence cannot start here", start + 1)),
+            },
+            _ if self.at_key() => match collections {
+                true => return self.block_mapping(column),
+                false => return Err(self.error("a mapping cannot start here", self.line_end())),
+            },
+            Some('"' | '\'') => Value::String(self.quoted()?),
+            _ => {
+                let plain = self.plain();
+                resolve(plain).map_err(|message| self.error_at(&message, start, self.pos))?
+            }
+        };
+        self.skip_spaces();
+        if self.peek() == Some(':') {
+            return Err(self.error_at("mapping keys must be strings", start, self.pos));
+        }
+        self.end_line()?;
+        Ok(value)
+    }
+
+    fn block_sequence(&mut self, column: usize) -> Result<Value, Diagnostic> {
+        self.enter()?;
+        let mut elements = vec![];
+        loop {
+            // The `-`.
+            self.pos += 1;
+            elements.push(self.node(column as isize, Context::Sequence)?);
+            if self.at_end() || self.column() < column || !self.at_sequence_entry() {
+                break;
+            }
+            if self.column() > column {
+                return Err(self.error("unexpected indentation", self.line_end()));
+            }
+        }
+        self.depth -= 1;
+        Ok(Value::Array(elements))
+    }
+
+    fn block_mapping(&mut self, column: usize) -> Result<Value, Diagnostic> {
+        self.enter()?;
+        let mut entries = vec![];
+        let mut keys: HashMap<String, TextRange> = HashMap::new();
+        loop {
+            let start = self.pos;
+            let (key, plain) = match self.peek() {
+                _ if self.at_indicator('?') => {
+                    return Err(self.error("complex keys are not supported", start + 1));
+                }
+                Some('[' | '{') => {
+                    return Err(self.error("mapping keys must be strings", self.line_end()))
+                }
+                Some('*' | '&') => {
+                    return Err(self.error("anchors on keys are not supported", self.line_end()))
+                }
+                Some('!') => return Err(self.error("YAML tags are not supported", self.word_end())),
+                _ if !self.at_key() => {
+                    return Err(self.error("expected a mapping key", self.line_end()))
+                }
+                Some('"' | '\'') => (self.quoted()?, false),
+                _ => (self.plain().to_string(), true),
+            };
+            let range = self.range(start, self.pos);
+            if plain && !matches!(resolve(&key), Ok(Value::String(_))) {
+                let message = format!(
+                    "mapping keys must be strings, quote `{}` to make it one",
+                    key
+                );
+                return Err(Diagnostic::new(message, range));
+            }
+            self.skip_spaces();
+            // The `:`.
+            self.pos += 1;
+            let value = self.node(column as isize, Context::Mapping)?;
+            if plain && key == "<<" {
+                entries.push(Entry::Merge(
+                    merged(value).map_err(|message| Diagnostic::new(message, range))?,
+                ));
+            } else {
+                if let Some(first) = keys.get(&key) {
+                    let message = format!("duplicate key `{}`", key);
+                    return Err(
+                        Diagnostic::new(message, range).with_label("first defined here", *first)
+                    );
+                }
+                keys.insert(key.clone(), range);
+                entries.push(Entry::Member(key, value));
+            }
+            if self.at_end() || self.column() < column {
+                break;
+            }
+            if self.column() > column {
+                return Err(self.error("unexpected indentation", self.line_end()));
+            }
+        }
+        self.depth -= 1;
+        // Explicit keys win over merged ones, and earlier merges over later
+        // ones.
+        let mut members = vec![];
+        let mut seen = HashSet::new();
+        for entry in entries {
+            match entry {
+                Entry::Member(key, value) => members.push((key, value)),
+                Entry::Merge(merged) => {
+                    for (key, value) in merged {
+                        if !keys.contains_key(&key) && seen.insert(key.clone()) {
+                            members.push((key, value));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(Value::Object(members))
+    }
+
+    /// Reads a `|` or `>` scalar, in a collection indented by `parent`
+    /// columns.
+    fn block_scalar(&mut self, parent: isize) -> Result<Value, Diagnostic> {
+        let literal = self.peek() == Some('|');
+        self.pos += 1;
+        let mut chomp = Chomp::Clip;
+        let mut indent = None;
+        for _ in 0..2 {
+            match self.peek() {
+                Some('-') => chomp = Chomp::Strip,
+                Some('+') => chomp = Chomp::Keep,
+                Some(digit @ '1'..='9') => {
+                    let digit = digit.to_digit(10).unwrap() as isize;
+                    indent = Some((parent.max(0) + digit) as usize);
+                }
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        self.finish_line()?;
+
+        // Every line up to the first one indented less than the content,
+        // with empty lines as "".
+        let mut lines = vec![];
+        while self.pos < self.text.len() && !(self.column() == 0 && self.at_end()) {
+            let line = self.rest_of_line();
+            let spaces = line.len() - line.trim_start_matches(' ').len();
+            if line.trim_start_matches([' ', '\t']).is_empty() {
+                lines.push("");
+            } else if spaces as isize <= parent || indent.is_some_and(|indent| spaces < indent) {
+                break;
+            } else {
+                let indent = *indent.get_or_insert(spaces);
+                lines.push(&line[indent..]);
+            }
+            self.next_line();
+        }
+        let trailing = lines
+            .iter()
+            .rev()
+            .take_while(|line| line.is_empty())
+            .count();
+        let body = &lines[..lines.len() - trailing];
+
+        let mut text = if literal { body.join("\n") } else { fold(body) };
+        match chomp {
+            Chomp::Strip => {}
+            Chomp::Clip if body.is_empty() => {}
+            Chomp::Clip => text.push('\n'),
+            Chomp::Keep => {
+                let newlines = trailing + usize::from(!body.is_empty());
+                text.push_str(&"\n".repeat(newlines));
+            }
+        }
+        self.skip_blank_lines()?;
+        Ok(Value::String(text))
+    }
+
+    fn flow_node(&mut self) -> Result<Value, Diagnostic> {
+        let start = self.pos;
+        let anchor = self.properties()?;
+        let value = match self.peek() {
+            Some('[') => {
+                self.enter()?;
+                self.pos += 1;
+                let mut elements = vec![];
+                while !self.flow_end(']')? {
+                    elements.push(self.flow_node()?);
+                    self.flow_separator(']')?;
+                }
+                self.depth -= 1;
+                Value::Array(elements)
+            }
+            Some('{') => {
+                self.enter()?;
+                self.pos += 1;
+                let mut members: Vec<(String, Value)> = vec![];
+                while !self.flow_end('}')? {
+                    let key_start = self.pos;
+                    let key = match self.peek() {
+                        Some('"' | '\'') => self.quoted()?,
+                        Some('[' | '{') => {
+                            return Err(self.error("mapping keys must be strings", key_start + 1))
+                        }
+                        _ => match resolve(self.flow_plain()) {
+                            Ok(Value::String(key)) => key,
+                            _ => {
+                                let key = &self.text[key_start..self.pos];
+                                let message = format!(
+                                    "mapping keys must be strings, quote `{}` to make it one",
+                                    key
+                                );
+                                return Err(self.error_at(&message, key_start, self.pos));
+                            }
+                        },
+                    };
+                    let range = self.range(key_start, self.pos);
+                    self.skip_flow_space()?;
+                    let value = if self.peek() == Some(':') {
+                        self.pos += 1;
+                        self.skip_flow_space()?;
+                        match self.peek() {
+                            Some(',' | '}') => Value::Null,
+                            _ => self.flow_node()?,
+                        }
+                    } else {
+                        Value::Null
+                    };
+                    if members.iter().any(|(name, _)| *name == key) {
+                        let message = format!("duplicate key `{}`", key);
+                        return Err(Diagnostic::new(message, range));
+                    }
+                    members.push((key, value));
+                    self.flow_separator('}')?;
+                }
+                self.depth -= 1;
+                Value::Object(members)
+            }
+            Some('"' | '\'') => Value::String(self.quoted()?),
+            Some('*') => self.alias()?,
+            None | Some(',' | ']' | '}') => return Err(self.error("expected a value", start)),
+            _ => {
+                let plain = self.flow_plain();
+                resolve(plain).map_err(|message| self.error_at(&message, start, self.pos))?
+            }
+        };
+        if let Some(anchor) = anchor {
+            self.anchors.insert(anchor, value.clone());
+        }
+        Ok(value)
+    }
+
+    /// Skips to the next item of a flow collection, returning whether
+    /// `close` ends the collection there.
+    fn flow_end(&mut self, close: char) -> Result<bool, Diagnostic> {
+        self.skip_flow_space()?;
+        if self.peek() == Some(close) {
+            self.pos += 1;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Skips the `,` after an item of a flow collection, unless `close`
+    /// follows it.
+    fn flow_separator(&mut self, close: char) -> Result<(), Diagnostic> {
+        self.skip_flow_space()?;
+        match self.peek() {
+            Some(',') => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(c) if c == close => Ok(()),
+            _ => Err(self.error(&format!("expected `,` or `{}`", close), self.pos)),
+        }
+    }
+
+    fn skip_flow_space(&mut self) -> Result<(), Diagnostic> {
+        loop {
+            match self.peek() {
+                Some(' ' | '\t' | '\r' | '\n') => self.pos += 1,
+                Some('#') => self.pos = self.line_end(),
+                None => return Err(self.error("unterminated flow collection", self.pos)),
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// A plain scalar in a flow collection, which ends at a flow indicator.
+    fn flow_plain(&mut self) -> &'a str {
+        let start = self.pos;
+        let bytes = self.text.as_bytes();
+        while let Some(&byte) = bytes.get(self.pos) {
+            let next = bytes.get(self.pos + 1).copied();
+            let ends = match byte {
+                b',' | b'[' | b']' | b'{' | b'}' | b'\r' | b'\n' => true,
+                b':' => matches!(
+                    next,
+                    None | Some(b' ' | b'\t' | b'\r' | b'\n' | b',' | b']' | b'}')
+                ),
+                b' ' | b'\t' => next == Some(b'#'),
+                _ => false,
+            };
+            if ends {
+                break;
+            }
+            self.pos += 1;
+        }
+        self.text[start..self.pos].trim_end_matches([' ', '\t'])
+    }
+
+    /// A plain scalar in a block collection, which ends at the end of the
+    /// line, at a comment or at the `: ` after a key.
+    fn plain(&mut self) -> &'a str {
+        let line = self.rest_of_line();
+        let end = plain_end(line).unwrap_or(line.len());
+        let start = self.pos;
+        self.pos += end;
+        self.text[start..self.pos].trim_end_matches([' ', '\t'])
+    }
+
+    fn quoted(&mut self) -> Result<String, Diagnostic> {
+        let start = self.pos;
+        let quote = self.peek().unwrap();
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let Some(c) = self.peek() else {
+                return Err(self.error_at("unterminated string", start, self.pos));
+            };
+            self.pos += c.len_utf8();
+            match c {
+                '\'' if quote == '\'' && self.peek() == Some('\'') => {
+                    self.pos += 1;
+                    out.push('\'');
+                }
+                c if c == quote => return Ok(out),
+                '\\' if quote == '"' => self.escape(&mut out)?,
+                '\r' | '\n' => {
+                    if c == '\r' && self.peek() == Some('\n') {
+                        self.pos += 1;
+                    }
+                    // A line break folds into a space, or into the empty
+                    // lines after it.
+                    out.truncate(out.trim_end_matches([' ', '\t']).len());
+                    let newlines = self.skip_quoted_lines();
+                    out.push_str(&if newlines == 0 {
+                        " ".to_string()
+                    } else {
+                        "\n".repeat(newlines)
+                    });
+                }
+                c => out.push(c),
+            }
+        }
+    }
+
+    /// Skips the whitespace and empty lines after a line break in a quoted
+    /// scalar, returning how many empty lines there were.
+    fn skip_quoted_lines(&mut self) -> usize {
+        let mut newlines = 0;
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                Some('\r') => self.pos += 1,
+                Some('\n') => {
+                    self.pos += 1;
+                    newlines += 1;
+                }
+                _ => return newlines,
+            }
+        }
+    }
+
+    fn escape(&mut self, out: &mut String) -> Result<(), Diagnostic> {
+        let start = self.pos - 1;
+        let Some(c) = self.peek() else {
+            return Err(self.error_at("unterminated string", start, self.pos));
+        };
+        self.pos += c.len_utf8();
+        let escaped = match c {
+            '0' => '\0',
+            'a' => '\x07',
+            'b' => '\x08',
+            't' | '\t' => '\t',
+            'n' => '\n',
+            'v' => '\x0b',
+            'f' => '\x0c',
+            'r' => '\r',
+            'e' => '\x1b',
+            ' ' | '"' | '/' | '\\' => c,
+            'N' => '\u{85}',
+            '_' => '\u{a0}',
+            'L' => '\u{2028}',
+            'P' => '\u{2029}',
+            'x' | 'u' | 'U' => {
+                let len = match c {
+                    'x' => 2,
+                    'u' => 4,
+                    _ => 8,
+                };
+                let code = self
+                    .text
+                    .get(self.pos..self.pos + len)
+                    .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .and_then(char::from_u32);
+                match code {
+                    Some(code) => {
+                        self.pos += len;
+                        code
+                    }
+                    None => return Err(self.error_at("invalid escape", start, self.pos)),
+                }
+            }
+            '\r' | '\n' => {
+                // An escaped line break joins the lines without a space.
+                if c == '\r' && self.peek() == Some('\n') {
+                    self.pos += 1;
+                }
+                self.skip_spaces();
+                return Ok(());
+            }
+            _ => return Err(self.error_at("invalid escape", start, self.pos)),
+        };
+        out.push(escaped);
+        Ok(())
+    }
+
+    fn alias(&mut self) -> Result<Value, Diagnostic> {
+        let start = self.pos;
+        self.pos += 1;
+        let name = self.name();
+        let Some(value) = self.anchors.get(name) else {
+            let message = format!("unknown anchor `{}`", name);
+            return Err(self.error_at(&message, start, self.pos));
+        };
+        self.aliased += size(value);
+        if self.aliased > MAX_ALIASED_VALUES {
+            let message = format!("aliases expand to more than {} values", MAX_ALIASED_VALUES);
+            return Err(self.error_at(&message, start, self.pos));
+        }
+        Ok(value.clone())
+    }
+
+    /// Reads the anchor and tag in front of a node, returning the anchor.
+    fn properties(&mut self) -> Result<Option<String>, Diagnostic> {
+        let mut anchor = None;
+        loop {
+            match self.peek() {
+                Some('!') => return Err(self.error("YAML tags are not supported", self.word_end())),
+                Some('&') => {
+                    let start = self.pos;
+                    self.pos += 1;
+                    let name = self.name().to_string();
+                    if name.is_empty() {
+                        return Err(self.error_at("expected an anchor name", start, self.pos));
+                    }
+                    anchor = Some(name);
+                    self.skip_spaces();
+                }
+                _ => return Ok(anchor),
+            }
+        }
+    }
+
+    fn name(&mut self) -> &'a str {
+        let start = self.pos;
+        let end = self.text[start..]
+            .find([' ', '\t', '\r', '\n', ',', '[', ']', '{', '}'])
+            .map_or(self.text.len(), |end| start + end);
+        self.pos = end;
+        &self.text[start..end]
+    }
+
+    /// Whether a key and its `:` start at the current position.
+    fn at_key(&self) -> bool {
+        let line = self.rest_of_line();
+        let after_key = match line.chars().next() {
+            Some(quote @ ('"' | '\'')) => match quoted_end(line, quote) {
+                Some(end) => line[end..].trim_start_matches([' ', '\t']),
+                None => return false,
+            },
+            _ => match plain_end(line) {
+                Some(end) => &line[end..],
+                None => return false,
+            },
+        };
+        after_key.starts_with(':')
+            && matches!(after_key[1..].chars().next(), None | Some(' ' | '\t'))
+    }
+
+    fn at_sequence_entry(&self) -> bool {
+        self.at_indicator('-')
+    }
+
+    /// Whether `indicator` is next, followed by whitespace or nothing.
+    fn at_indicator(&self, indicator: char) -> bool {
+        let rest = &self.text[self.pos..];
+        rest.starts_with(indicator)
+            && matches!(
+                rest[1..].chars().next(),
+                None | Some(' ' | '\t' | '\r' | '\n')
+            )
+    }
+    /// Whether the current line is the document marker `marker`.
+    fn at_marker(&self, marker: &str) -> bool {
+        let rest = &self.text[self.pos..];
+        self.column() == 0
+            && rest.starts_with(marker)
+            && matches!(
+                rest[3..].chars().next(),
+                None | Some(' ' | '\t' | '\r' | '\n')
+            )
+    }
+
+    /// Whether the document has no more content.
+    fn at_end(&self) -> bool {
+        self.pos >= self.text.len() || self.at_marker("---") || self.at_marker("...")
+    }
+
+    fn at_line_end(&self) -> bool {
+        matches!(self.peek(), None | Some('\r' | '\n' | '#'))
+    }
+
+    /// Skips to the start of the next line with content, after checking
+    /// that the current one has nothing but a comment left.
+    fn end_line(&mut self) -> Result<(), Diagnostic> {
+        self.finish_line()?;
+        self.skip_blank_lines()
+    }
+
+    fn finish_line(&mut self) -> Result<(), Diagnostic> {
+        self.skip_spaces();
+        if self.peek() == Some('#') {
+            self.pos = self.line_end();
+        }
+        if !matches!(self.peek(), None | Some('\r' | '\n')) {
+            return Err(self.error("expected the end of the line", self.line_end()));
+        }
+        self.next_line();
+        Ok(())
+    }
+
+    /// Skips the lines that are empty or hold only a comment, from the
+    /// start of a line to the first character of the next line with
+    /// content.
+    fn skip_blank_lines(&mut self) -> Result<(), Diagnostic> {
+        while self.pos < self.text.len() {
+            let line = self.rest_of_line();
+            let content = line.trim_start_matches([' ', '\t']);
+            if content.is_empty() || content.starts_with('#') {
+                self.next_line();
+                continue;
+            }
+            let spaces = line.len() - line.trim_start_matches(' ').len();
+            if line[spaces..].starts_with('\t') {
+                let start = self.pos + spaces;
+                return Err(self.error_at("tabs cannot be used for indentation", start, start + 1));
+            }
+            self.pos += spaces;
+            break;
+        }
+        Ok(())
+    }
+
+    /// Moves past the end of the current line.
+    fn next_line(&mut self) {
+        self.pos = match self.text[self.pos..].find('\n') {
+            Some(end) => self.pos + end + 1,
+            None => self.text.len(),
+        };
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn column(&self) -> usize {
+        self.pos - self.text[..self.pos].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    /// The rest of the current line, without its line break.
+    fn rest_of_line(&self) -> &'a str {
+        let rest = &self.text[self.pos..self.line_end()];
+        rest.strip_suffix('\r').unwrap_or(rest)
+    }
+
+    fn line_end(&self) -> usize {
+        self.text[self.pos..]
+            .find('\n')
+            .map_or(self.text.len(), |end| self.pos + end)
+    }
+
+    fn word_end(&self) -> usize {
+        self.text[self.pos..]
+            .find([' ', '\t', '\r', '\n'])
+            .map_or(self.text.len(), |end| self.pos + end)
+    }
+
+    /// An error from the current position to `end`.
+    fn error(&self, message: &str, end: usize) -> Diagnostic {
+        self.error_at(message, self.pos, end)
+    }
+
+    fn error_at(&self, message: &str, start: usize, end: usize) -> Diagnostic {
+        Diagnostic::new(message, self.range(start, end.max(start)))
+    }
+
+    fn range(&self, start: usize, end: usize) -> TextRange {
+        TextRange::new(TextSize::from(start as u32), TextSize::from(end as u32))
+    }
+}
+
+/// Where a plain scalar on `line` ends: at a comment, or at the `:` after
+/// it if it is a key.
+fn plain_end(line: &str) -> Option<usize> {
+    let bytes = line.as_bytes();
+    bytes.iter().enumerate().find_map(|(i, &byte)| {
+        let next = bytes.get(i + 1).copied();
+        match byte {
+            b':' if matches!(next, None | Some(b' ' | b'\t')) => Some(i),
+            b' ' | b'\t' if next == Some(b'#') => Some(i),
+            _ => None,
+        }
+    })
+}
+
+/// Where the quoted scalar at the start of `line` ends, after its closing
+/// quote.
+fn quoted_end(line: &str, quote: char) -> Option<usize> {
+    let mut chars = line.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if quote == '"' => {
+                chars.next();
+            }
+            '\'' if quote == '\'' && line[i + 1..].starts_with('\'') => {
+                chars.next();
+            }
+            c if c == quote => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Joins the lines of a `>` scalar: lines of text with a space, and lines
+/// indented more than the others and empty lines with line breaks.
+fn fold(lines: &[&str]) -> String {
+    let mut text = String::new();
+    let mut empty = 0;
+    let mut previous_indented = false;
+    let mut first = true;
+    for line in lines {
+        if line.is_empty() {
+            empty += 1;
+            continue;
+        }
+        let indented = line.starts_with([' ', '\t']);
+        if first {
+            text.push_str(&"\n".repeat(empty));
+        } else if indented || previous_indented {
+            text.push_str(&"\n".repeat(empty + 1));
+        } else if empty > 0 {
+            text.push_str(&"\n".repeat(empty));
+        } else {
+            text.push(' ');
+        }
+        text.push_str(line);
+        previous_indented = indented;
+        empty = 0;
+        first = false;
+    }
+    text
+}
+
+/// The members a `<<` key merges into its mapping: those of a mapping, or
+/// of each mapping in a sequence, the first one winning.
+fn merged(value: Value) -> Result<Vec<(String, Value)>, String> {
+    let mappings = match value {
+        Value::Object(members) => return Ok(members),
+        Value::Array(elements) => elements,
+        _ => return Err("`<<` can only merge mappings".to_string()),
+    };
+    let mut members = vec![];
+    for mapping in mappings {
+        match mapping {
+            Value::Object(merged) => members.extend(merged),
+            _ => return Err("`<<` can only merge mappings".to_string()),
+        }
+    }
+    Ok(members)
+}
+
+/// The number of values in `value`, itself included.
+fn size(value: &Value) -> usize {
+    match value {
+        Value::Array(elements) => 1 + elements.iter().map(size).sum::<usize>(),
+        Value::Object(members) => 1 + members.iter().map(|(_, value)| size(value)).sum::<usize>(),
+        _ => 1,
+    }
+}
+
+/// Resolves a plain scalar with the core schema.
+fn resolve(plain: &str) -> Result<Value, String> {
+    let value = match plain {
+        "" | "~" | "null" | "Null" | "NULL" => Value::Null,
+        "true" | "True" | "TRUE" => Value::Bool(true),
+        "false" | "False" | "FALSE" => Value::Bool(false),
+        _ => {
+            let unsigned = plain.strip_prefix(['-', '+']).unwrap_or(plain);
+            if matches!(unsigned, ".inf" | ".Inf" | ".INF")
+                || matches!(plain, ".nan" | ".NaN" | ".NAN")
+            {
+                return Err(format!("`{}` has no JSON equivalent", plain));
+            }
+            match number(plain) {
+                Some(n) => Value::Number(n),
+                None => Value::String(plain.to_string()),
+            }
+        }
+    };
+    Ok(value)
+}
+
+/// The number a plain scalar spells, if it is one.
+fn number(plain: &str) -> Option<Number> {
+    let radix = |digits: &str, radix| {
+        let n = u128::from_str_radix(digits, radix).ok()?;
+        Some(match u64::try_from(n) {
+            Ok(n) => Number::PosInt(n),
+            Err(_) => Number::Float(n as f64),
+        })
+    };
+    if let Some(hex) = plain.strip_prefix("0x") {
+        return radix(hex, 16).filter(|_| !hex.starts_with('+'));
+    }
+    if let Some(octal) = plain.strip_prefix("0o") {
+        return radix(octal, 8).filter(|_| !octal.starts_with('+'));
+    }
+    let (sign, unsigned) = match plain.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", plain.strip_prefix('+').unwrap_or(plain)),
+    };
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(e) => (&unsigned[..e], Some(&unsigned[e + 1..])),
+        None => (unsigned, None),
+    };
+    let (int, fraction) = match mantissa.split_once('.') {
+        Some((int, fraction)) => (int, Some(fraction)),
+        None => (mantissa, None),
+    };
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    let valid = match fraction {
+        Some(fraction) => {
+            digits(int) && digits(fraction) && !(int.is_empty() && fraction.is_empty())
+        }
+        None => !int.is_empty() && digits(int),
+    };
+    let exponent_digits = exponent.map(|e| e.strip_prefix(['-', '+']).unwrap_or(e));
+    if !valid || exponent_digits.is_some_and(|e| e.is_empty() || !digits(e)) {
+        return None;
+    }
+    // The same number spelled as JSON.
+    let mut json = format!("{}{}", sign, if int.is_empty() { "0" } else { int });
+    match fraction {
+        Some("") if exponent.is_none() => json.push_str(".0"),
+        Some("") => {}
+        Some(fraction) => {
+            json.push('.');
+            json.push_str(fraction);
+        }
+        None => {}
+    }
+    if let Some(exponent) = exponent {
+        json.push('e');
+        json.push_str(exponent);
+    }
+    Some(Number::from_text(&json))
+}
+
+fn mapping(
+    out: &mut String,
+    members: &[(String, Value)],
+    indent: usize,
+    inline: bool,
+) -> Result<(), EmitError> {
+    let mut keys = HashSet::new();
+    for (i, (key, value)) in members.iter().enumerate() {
+        if !keys.insert(key) {
+            return Err(EmitError {
+                message: format!("duplicate key `{}` cannot be written as YAML", key),
+            });
+        }
+        if i > 0 || !inline {
+            out.push_str(&" ".repeat(indent));
+        }
+        if key != "<<" && is_plain(key) {
+            out.push_str(key);
+        } else {
+            out.push_str(&quote(key));
+        }
+        out.push(':');
+        match value {
+            Value::Array(elements) if !elements.is_empty() => {
+                out.push('\n');
+                sequence(out, elements, indent + 2, false)?;
+            }
+            Value::Object(members) if !members.is_empty() => {
+                out.push('\n');
+                mapping(out, members, indent + 2, false)?;
+            }
+            value => {
+                out.push(' ');
+                out.push_str(&scalar(value));
+                out.push('\n');
+            }
+        }
+    }
+    Ok(())
+}
+
+fn sequence(
+    out: &mut String,
+    elements: &[Value],
+    indent: usize,
+    inline: bool,
+) -> Result<(), EmitError> {
+    for (i, element) in elements.iter().enumerate() {
+        if i > 0 || !inline {
+            out.push_str(&" ".repeat(indent));
+        }
+        out.push_str("- ");
+        match element {
+            Value::Array(elements) if !elements.is_empty() => {
+                sequence(out, elements, indent + 2, true)?
+            }
+            Value::Object(members) if !members.is_empty() => {
+                mapping(out, members, indent + 2, true)?
+            }
+            value => {
+                out.push_str(&scalar(value));
+                out.push('\n');
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A scalar, or an empty collection in flow style.
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) if is_plain(s) => s.clone(),
+        Value::String(s) => quote(s),
+        Value::Array(_) => "[]".to_string(),
+        Value::Object(_) => "{}".to_string(),
+    }
+}
+
+/// Whether `s` reads back as itself when written as a plain scalar.
+fn is_plain(s: &str) -> bool {
+    let Some(first) = s.chars().next() else {
+        return false;
+    };
+    !"-?:,[]{}#&*!|>'\"%@` ".contains(first)
+        && !s.starts_with("...")
+        && !s.ends_with([' ', ':'])
+        && !s.contains(": ")
+        && !s.contains(" #")
+        && s.chars()
+            .all(|c| c == ' ' || c.is_ascii_graphic() || (!c.is_ascii() && c.is_alphanumeric()))
+        && matches!(resolve(s), Ok(Value::String(_)))
+}
+
+/// `s` as a double-quoted scalar, escaping what YAML does not allow or
+/// would fold.
+fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c < ' '
+                || ('\u{7f}'..='\u{9f}').contains(&c)
+                || matches!(c, '\u{2028}' | '\u{2029}' | '\u{feff}') =>
+            {
+                out.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::{expect, Expect};
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::duplicates::{resolve as resolve_duplicates, DuplicateKeys};
+    use crate::printer::{print, Style};
+    use crate::strategies::value;
+
+    fn check(text: &str, expected: Expect) {
+        let actual = match parse(text) {
+            Ok(value) => print(&value, Style::Pretty),
+            Err(err) => format!("{} at {:?}", err.message, err.range),
+        };
+        expected.assert_eq(&actual);
+    }
+
+    #[test]
+    fn reads_block_and_flow_collections() {
+        check(
+            r#"# defaults
+base: &base
+  image: "rust:1.70"
+  retries: 3
+jobs:
+- name: test
+  <<: *base
+  retries: 5
+  script: |
+    cargo test
+    cargo clippy
+- name: 'it''s'
+  tags: [a, "b c", {x: 1}]
+  when: >-
+    folded
+    text
+
+  env: {}
+empty:
+flags: [true, ~, 0x1F, -1.5e3, .5, 012]
+"#,
+            expect![[r#"
+                {
+                  "base": {
+                    "image": "rust:1.70",
+                    "retries": 3
+                  },
+                  "jobs": [
+                    {
+                      "name": "test",
+                      "image": "rust:1.70",
+                      "retries": 5,
+                      "script": "cargo test\ncargo clippy\n"
+                    },
+                    {
+                      "name": "it's",
+                      "tags": [
+                        "a",
+                        "b c",
+                        {
+                          "x": 1
+                        }
+                      ],
+                      "when": "folded text",
+                      "env": {}
+                    }
+                  ],
+                  "empty": null,
+                  "flags": [
+                    true,
+                    null,
+                    31,
+                    -1500.0,
+                    0.5,
+                    12
+                  ]
+                }"#]],
+        );
+        check(
+            "---\n- - a\n  - \"multi\n    line\\tx\"\n- key:\n    - 1\n  other: x # comment\n...\n",
+            expect![[r#"
+                [
+                  [
+                    "a",
+                    "multi line\tx"
+                  ],
+                  {
+                    "key": [
+                      1
+                    ],
+                    "other": "x"
+                  }
+                ]"#]],
+        );
+    }
+
+    #[test]
+    fn rejects_what_json_cannot_express() {
+        check(
+            "a: !!str 1",
+            expect![["YAML tags are not supported at 3..8"]],
+        );
+        check(
+            "1: a",
+            expect![["mapping keys must be strings, quote `1` to make it one at 0..1"]],
+        );
+        check("[a]: b", expect![["mapping keys must be strings at 0..3"]]);
+        check(
+            "? a\n: b",
+            expect![["complex keys are not supported at 0..1"]],
+        );
+        check(
+            "{null: 1}",
+            expect![["mapping keys must be strings, quote `null` to make it one at 1..5"]],
+        );
+        check(
+            "x: -.inf",
+            expect![["`-.inf` has no JSON equivalent at 3..8"]],
+        );
+        check("a: 1\na: 2", expect![["duplicate key `a` at 5..6"]]);
+        check("a: *b", expect![["unknown anchor `b` at 3..5"]]);
+        check(
+            "a\n---\nb",
+            expect![["multiple documents are not supported at 2..5"]],
+        );
+        check(
+            "%YAML 1.2\n---\na",
+            expect![["directives are not supported at 0..9"]],
+        );
+        check("a: b: c", expect![["a mapping cannot start here at 3..7"]]);
+        check(
+            "a:\n\t- b",
+            expect![["tabs cannot be used for indentation at 3..4"]],
+        );
+        check(
+            "a:\n  b: 1\n    c: 2",
+            expect![["unexpected indentation at 14..18"]],
+        );
+        check("[1, 2", expect![["unterminated flow collection at 5..5"]]);
+        check("\"a", expect![["unterminated string at 0..2"]]);
+    }
+
+    #[test]
+    fn limits_alias_expansion() {
+        let mut text = "a: &a [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]\n".to_string();
+        for (name, previous) in ["b", "c", "d", "e", "f", "g"]
+            .iter()
+            .zip(["a", "b", "c", "d", "e", "f"])
+        {
+            text += &format!(
+                "{}: &{} [{}]\n",
+                name,
+                name,
+                vec![format!("*{}", previous); 10].join(", ")
+            );
+        }
+        let err = parse(&text).unwrap_err();
+        assert_eq!(err.message, "aliases expand to more than 1000000 values");
+    }
+
+    #[test]
+    fn limits_nesting() {
+        assert!(parse(&format!("{}1{}", "[".repeat(128), "]".repeat(128))).is_ok());
+        check(
+            &"[".repeat(129),
+            expect![[r#"nesting exceeds the limit of 128 levels at 128..129"#]],
+        );
+        check(
+            &"{a: ".repeat(1_000_000),
+            expect![[r#"nesting exceeds the limit of 128 levels at 512..513"#]],
+        );
+        check(
+            &"- ".repeat(1_000_000),
+            expect![[r#"nesting exceeds the limit of 128 levels at 256..257"#]],
+        );
+        let mut text = String::new();
+        for depth in 0..1_000 {
+            text += &format!("{}a:\n", " ".repeat(depth));
+        }
+        check(
+            &text,
+            expect![[r#"nesting exceeds the limit of 128 levels at 8640..8641"#]],
+        );
+    }
+
+    #[test]
+    fn emits_block_style() {
+        let value = crate::backend::syntax_to_value(
+            &crate::parser::Parser::new(
+                r#"{"name": "x", "on": true, "n": [1, "2", [], [3, {"a": null, "b": [{"c": "d: e"}]}]],
+                    "": {}, "multi\nline": " padded ", "<<": "1.5"}"#,
+            )
+            .parse()
+            .syntax(),
+        )
+        .unwrap();
+        let yaml = emit(&value).unwrap();
+        expect![[r#"
+            name: x
+            on: true
+            n:
+              - 1
+              - "2"
+              - []
+              - - 3
+                - a: null
+                  b:
+                    - c: "d: e"
+            "": {}
+            "multi\nline": " padded "
+            "<<": "1.5"
+        "#]]
+        .assert_eq(&yaml);
+        assert_eq!(parse(&yaml).unwrap(), value);
+        let duplicates = Value::Object(vec![
+            ("a".to_string(), Value::Null),
+            ("a".to_string(), Value::Null),
+        ]);
+        assert_eq!(
+            emit(&duplicates).unwrap_err().message,
+            "duplicate key `a` cannot be written as YAML"
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn emit_then_parse_is_identity(mut value in value()) {
+            resolve_duplicates(&mut value, DuplicateKeys::FirstWins);
+            let yaml = emit(&value).unwrap();
+            prop_assert_eq!(parse(&yaml).unwrap(), value, "{}", yaml);
+        }
+    }
+}